@@ -0,0 +1,635 @@
+//! Known vulnerabilities from an offline database of OSV advisories.
+//!
+//! The database is a directory of advisories in the [OSV format], such as
+//! the OSV export of the RustSec advisory database or a checkout of the
+//! GitHub advisory database, searched recursively. Only advisories about
+//! crates.io, npm, PyPI and Go packages are kept.
+//!
+//! [OSV format]: https://ossf.github.io/osv-schema/
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::dashboard::{Dependency, Ecosystem};
+use crate::deps::normalize_pypi_name;
+use crate::versions;
+
+mod cvss;
+
+/// How severe a vulnerability is, in the qualitative ratings of CVSS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// A CVSS score below 4.0.
+    Low,
+    /// A CVSS score from 4.0 up to 7.0.
+    Medium,
+    /// A CVSS score from 7.0 up to 9.0.
+    High,
+    /// A CVSS score of 9.0 or more.
+    Critical,
+}
+
+impl Severity {
+    /// Returns the CSS class for this severity.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Self::Low => "vuln-severity low",
+            Self::Medium => "vuln-severity medium",
+            Self::High => "vuln-severity high",
+            Self::Critical => "vuln-severity critical",
+        }
+    }
+
+    /// Returns the display name for this severity.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        }
+    }
+
+    fn from_score(score: f64) -> Option<Self> {
+        match score {
+            9.0.. => Some(Self::Critical),
+            7.0.. => Some(Self::High),
+            4.0.. => Some(Self::Medium),
+            0.1.. => Some(Self::Low),
+            _ => None,
+        }
+    }
+
+    /// Parses a rating such as GitHub's `MODERATE`.
+    fn from_label(label: &str) -> Option<Self> {
+        match label.to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "medium" | "moderate" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            "critical" => Some(Self::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// A known vulnerability affecting the version of a dependency in use.
+#[derive(Debug, Clone)]
+pub struct Vulnerability {
+    /// The advisory ID, such as `RUSTSEC-2024-0003` or `GHSA-…`.
+    pub id: String,
+    /// Other IDs of the same vulnerability, such as its CVE.
+    pub aliases: Vec<String>,
+    /// A one-line description.
+    pub summary: String,
+    /// How severe the vulnerability is, if the advisory says.
+    pub severity: Option<Severity>,
+    /// The lowest version above the one in use that fixes it, if any.
+    pub fixed_version: Option<String>,
+}
+
+impl Vulnerability {
+    /// Returns the advisory's page on osv.dev.
+    pub fn url(&self) -> Url {
+        let mut url = Url::parse("https://osv.dev/vulnerability/").expect("valid base URL");
+        url.path_segments_mut()
+            .expect("cannot be base")
+            .pop_if_empty()
+            .push(&self.id);
+        url
+    }
+}
+
+/// Error returned when the advisory database cannot be loaded.
+#[derive(Debug, thiserror::Error)]
+pub enum AdvisoryError {
+    /// A directory of the database could not be read.
+    #[error("cannot read advisories from {}: {source}", path.display())]
+    Io {
+        /// The directory that could not be read.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+}
+
+#[derive(Deserialize)]
+struct Osv {
+    id: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    summary: String,
+    withdrawn: Option<String>,
+    #[serde(default)]
+    severity: Vec<OsvSeverity>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+    database_specific: Option<SeverityLabel>,
+}
+
+#[derive(Deserialize)]
+struct OsvSeverity {
+    #[serde(rename = "type")]
+    kind: String,
+    score: String,
+}
+
+/// The `database_specific` or `ecosystem_specific` object some databases,
+/// GitHub's among them, put a severity rating in.
+#[derive(Deserialize)]
+struct SeverityLabel {
+    severity: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OsvAffected {
+    package: Option<OsvPackage>,
+    #[serde(default)]
+    severity: Vec<OsvSeverity>,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+    #[serde(default)]
+    versions: Vec<String>,
+    database_specific: Option<SeverityLabel>,
+    ecosystem_specific: Option<SeverityLabel>,
+}
+
+#[derive(Deserialize)]
+struct OsvPackage {
+    ecosystem: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct OsvRange {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Event {
+    Introduced(String),
+    Fixed(String),
+    LastAffected(String),
+    Limit(String),
+}
+
+impl Event {
+    fn version(&self) -> &str {
+        match self {
+            Self::Introduced(v) | Self::Fixed(v) | Self::LastAffected(v) | Self::Limit(v) => v,
+        }
+    }
+}
+
+/// Compares versions in range events, where `0` stands for the lowest
+/// version.
+fn compare_event_versions(ecosystem: Ecosystem, a: &str, b: &str) -> Option<Ordering> {
+    match (a, b) {
+        ("0", "0") => Some(Ordering::Equal),
+        ("0", _) => Some(Ordering::Less),
+        (_, "0") => Some(Ordering::Greater),
+        (a, b) => versions::compare(ecosystem, a, b),
+    }
+}
+
+/// An advisory's details, shared by the packages it affects.
+struct Advisory {
+    id: String,
+    aliases: Vec<String>,
+    summary: String,
+    severity: Option<Severity>,
+}
+
+/// The versions of one package an advisory affects.
+struct Affected {
+    advisory: usize,
+    /// Events of each range, sorted by version.
+    ranges: Vec<Vec<Event>>,
+    versions: Vec<String>,
+}
+
+impl Affected {
+    /// Returns whether `version` is affected, and if so the version
+    /// fixing it, following the evaluation the OSV schema describes. A
+    /// range without an `introduced` event, which the schema requires,
+    /// affects no version.
+    fn check(&self, ecosystem: Ecosystem, version: &str) -> Option<Option<String>> {
+        let equal = |v: &str| {
+            v == version || versions::compare(ecosystem, v, version).is_some_and(Ordering::is_eq)
+        };
+        if self.versions.iter().any(|v| equal(v)) {
+            return Some(None);
+        }
+        self.ranges.iter().find_map(|events| {
+            let mut affected = false;
+            for event in events {
+                let order = compare_event_versions(ecosystem, version, event.version())?;
+                match event {
+                    Event::Introduced(_) if order.is_ge() => affected = true,
+                    Event::Fixed(_) | Event::Limit(_) if order.is_ge() => affected = false,
+                    Event::LastAffected(_) if order.is_gt() => affected = false,
+                    _ => {}
+                }
+            }
+            affected.then(|| {
+                events.iter().find_map(|event| match event {
+                    Event::Fixed(fixed)
+                        if compare_event_versions(ecosystem, fixed, version)
+                            .is_some_and(Ordering::is_gt) =>
+                    {
+                        Some(fixed.clone())
+                    }
+                    _ => None,
+                })
+            })
+        })
+    }
+}
+
+fn ecosystem(name: &str) -> Option<Ecosystem> {
+    match name {
+        "crates.io" => Some(Ecosystem::Cargo),
+        "npm" => Some(Ecosystem::Npm),
+        "PyPI" => Some(Ecosystem::PyPI),
+        "Go" => Some(Ecosystem::Go),
+        _ => None,
+    }
+}
+
+/// Returns the name a package is indexed by, which for PyPI projects is
+/// their normalized name.
+fn package_key(ecosystem: Ecosystem, name: &str) -> (Ecosystem, String) {
+    match ecosystem {
+        Ecosystem::PyPI => (ecosystem, normalize_pypi_name(name)),
+        _ => (ecosystem, name.to_owned()),
+    }
+}
+
+/// Rates an advisory by the first CVSS v3 vector it carries, or else by
+/// the severity label its database gives it.
+fn severity(osv: &Osv) -> Option<Severity> {
+    let vectors = osv
+        .severity
+        .iter()
+        .chain(osv.affected.iter().flat_map(|a| &a.severity));
+    let scored = vectors
+        .filter(|s| s.kind == "CVSS_V3")
+        .find_map(|s| cvss::base_score(&s.score))
+        .and_then(Severity::from_score);
+    let labels = osv.database_specific.iter().chain(
+        osv.affected
+            .iter()
+            .flat_map(|a| a.database_specific.iter().chain(&a.ecosystem_specific)),
+    );
+    scored.or_else(|| {
+        labels
+            .filter_map(|label| label.severity.as_deref())
+            .find_map(Severity::from_label)
+    })
+}
+
+/// An in-memory index of advisories by the package they affect.
+#[derive(Default)]
+pub struct AdvisoryDb {
+    advisories: Vec<Advisory>,
+    affected: HashMap<(Ecosystem, String), Vec<Affected>>,
+}
+
+impl fmt::Debug for AdvisoryDb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdvisoryDb")
+            .field("advisories", &self.advisories.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AdvisoryDb {
+    /// Loads every OSV advisory under `dir`.
+    ///
+    /// Files that are not valid advisories are skipped with a warning, as
+    /// are hidden directories such as `.git`.
+    pub fn load(dir: &Path) -> Result<Self, AdvisoryError> {
+        let mut db = Self::default();
+        let mut pending = vec![dir.to_owned()];
+        while let Some(dir) = pending.pop() {
+            let io = |source| AdvisoryError::Io {
+                path: dir.clone(),
+                source,
+            };
+            for entry in std::fs::read_dir(&dir).map_err(io)? {
+                let path = entry.map_err(io)?.path();
+                let hidden = path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+                if hidden {
+                    continue;
+                }
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "json") {
+                    let parsed = std::fs::read_to_string(&path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|text| {
+                            serde_json::from_str(&text).map_err(|err| err.to_string())
+                        });
+                    match parsed {
+                        Ok(osv) => db.insert(osv),
+                        Err(error) => {
+                            tracing::warn!(error, path = %path.display(), "skipping advisory")
+                        }
+                    }
+                }
+            }
+        }
+        Ok(db)
+    }
+
+    fn insert(&mut self, osv: Osv) {
+        if osv.withdrawn.is_some() {
+            return;
+        }
+        let index = self.advisories.len();
+        let mut relevant = false;
+        for affected in &osv.affected {
+            let Some(package) = &affected.package else {
+                continue;
+            };
+            let Some(ecosystem) = ecosystem(&package.ecosystem) else {
+                continue;
+            };
+            let ranges = affected
+                .ranges
+                .iter()
+                .filter(|range| range.kind != "GIT")
+                .filter_map(|range| {
+                    // Ranges with versions this ecosystem cannot order are
+                    // dropped rather than risk matching every version.
+                    let parses = range.events.iter().all(|event| {
+                        compare_event_versions(ecosystem, event.version(), event.version())
+                            .is_some()
+                    });
+                    let mut events = range.events.clone();
+                    events.sort_by(|a, b| {
+                        compare_event_versions(ecosystem, a.version(), b.version())
+                            .unwrap_or(Ordering::Equal)
+                    });
+                    parses.then_some(events)
+                })
+                .collect();
+            self.affected
+                .entry(package_key(ecosystem, &package.name))
+                .or_default()
+                .push(Affected {
+                    advisory: index,
+                    ranges,
+                    versions: affected.versions.clone(),
+                });
+            relevant = true;
+        }
+        if relevant {
+            self.advisories.push(Advisory {
+                severity: severity(&osv),
+                id: osv.id,
+                aliases: osv.aliases,
+                summary: osv.summary,
+            });
+        }
+    }
+
+    /// Returns the number of advisories loaded.
+    pub fn len(&self) -> usize {
+        self.advisories.len()
+    }
+
+    /// Whether no advisories are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.advisories.is_empty()
+    }
+
+    /// Returns the vulnerabilities affecting the version of `dependency`
+    /// in use, most severe first.
+    pub fn vulnerabilities(&self, dependency: &Dependency) -> Vec<Vulnerability> {
+        let key = package_key(dependency.ecosystem, &dependency.name);
+        let mut vulnerabilities: Vec<Vulnerability> = Vec::new();
+        for affected in self.affected.get(&key).into_iter().flatten() {
+            let Some(fixed_version) =
+                affected.check(dependency.ecosystem, &dependency.current_version)
+            else {
+                continue;
+            };
+            let advisory = &self.advisories[affected.advisory];
+            if vulnerabilities.iter().any(|v| v.id == advisory.id) {
+                continue;
+            }
+            vulnerabilities.push(Vulnerability {
+                id: advisory.id.clone(),
+                aliases: advisory.aliases.clone(),
+                summary: advisory.summary.clone(),
+                severity: advisory.severity,
+                fixed_version,
+            });
+        }
+        vulnerabilities.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.id.cmp(&b.id)));
+        vulnerabilities
+    }
+
+    /// Records the vulnerabilities affecting each of `dependencies`.
+    pub fn annotate(&self, dependencies: &mut [Dependency]) {
+        for dependency in dependencies {
+            dependency.vulnerabilities = self.vulnerabilities(dependency);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::dashboard::DependencyKind;
+
+    fn db(advisories: &[serde_json::Value]) -> AdvisoryDb {
+        let mut db = AdvisoryDb::default();
+        for advisory in advisories {
+            db.insert(serde_json::from_value(advisory.clone()).unwrap());
+        }
+        db
+    }
+
+    /// An advisory about `name` in `ecosystem` with a single range.
+    fn advisory(
+        id: &str,
+        ecosystem: &str,
+        name: &str,
+        events: serde_json::Value,
+    ) -> serde_json::Value {
+        json!({
+            "id": id,
+            "affected": [{
+                "package": { "ecosystem": ecosystem, "name": name },
+                "ranges": [{ "type": "ECOSYSTEM", "events": events }],
+            }],
+        })
+    }
+
+    fn dependency(ecosystem: Ecosystem, name: &str, version: &str) -> Dependency {
+        Dependency {
+            name: name.to_owned(),
+            ecosystem,
+            kind: DependencyKind::Direct,
+            current_version: version.to_owned(),
+            latest_version: None,
+            vulnerabilities: Vec::new(),
+        }
+    }
+
+    /// Returns the fixed version of each vulnerability affecting `version`
+    /// of `name`.
+    fn check(
+        db: &AdvisoryDb,
+        ecosystem: Ecosystem,
+        name: &str,
+        version: &str,
+    ) -> Vec<Option<String>> {
+        db.vulnerabilities(&dependency(ecosystem, name, version))
+            .into_iter()
+            .map(|v| v.fixed_version)
+            .collect()
+    }
+
+    #[test]
+    fn evaluates_range_events_in_version_order() {
+        let db = db(&[advisory(
+            "RUSTSEC-0000-0001",
+            "crates.io",
+            "foo",
+            // Out of order on purpose; events are sorted when loaded.
+            json!([
+                { "introduced": "2.0.0" },
+                { "fixed": "1.2.0" },
+                { "last_affected": "2.1.0" },
+                { "introduced": "0" },
+            ]),
+        )]);
+        for (version, expected) in [
+            ("0.5.0", vec![Some("1.2.0")]),
+            ("1.2.0-rc.1", vec![Some("1.2.0")]),
+            ("1.2.0", vec![]),
+            ("1.5.0", vec![]),
+            ("2.0.0", vec![None]),
+            ("2.1.0", vec![None]),
+            ("2.1.1", vec![]),
+            ("^1.0", vec![]),
+        ] {
+            let expected: Vec<_> = expected.into_iter().map(|v| v.map(str::to_owned)).collect();
+            assert_eq!(
+                check(&db, Ecosystem::Cargo, "foo", version),
+                expected,
+                "{version}"
+            );
+        }
+    }
+
+    #[test]
+    fn handles_edge_cases_of_ranges() {
+        let db = db(&[
+            advisory(
+                "FIXED-ONLY",
+                "npm",
+                "fixed-only",
+                json!([{ "fixed": "1.0.0" }]),
+            ),
+            advisory(
+                "LIMITED",
+                "npm",
+                "limited",
+                json!([{ "introduced": "1.0.0" }, { "limit": "2.0.0" }]),
+            ),
+            advisory(
+                "UNORDERED",
+                "npm",
+                "unordered",
+                json!([{ "introduced": "0" }, { "fixed": "not-a-version" }]),
+            ),
+            json!({
+                "id": "GIT",
+                "affected": [{
+                    "package": { "ecosystem": "npm", "name": "git" },
+                    "ranges": [{ "type": "GIT", "events": [{ "introduced": "0" }] }],
+                }],
+            }),
+            json!({
+                "id": "LISTED",
+                "affected": [{
+                    "package": { "ecosystem": "PyPI", "name": "Typing_Extensions" },
+                    "versions": ["4.0.0"],
+                }],
+            }),
+        ]);
+        for (ecosystem, name, version, affected) in [
+            (Ecosystem::Npm, "fixed-only", "0.5.0", false),
+            (Ecosystem::Npm, "limited", "0.9.0", false),
+            (Ecosystem::Npm, "limited", "1.5.0", true),
+            (Ecosystem::Npm, "limited", "2.0.0", false),
+            (Ecosystem::Npm, "unordered", "1.0.0", false),
+            (Ecosystem::Npm, "git", "1.0.0", false),
+            (Ecosystem::PyPI, "typing-extensions", "4.0", true),
+            (Ecosystem::PyPI, "typing-extensions", "4.0.1", false),
+        ] {
+            assert_eq!(
+                !check(&db, ecosystem, name, version).is_empty(),
+                affected,
+                "{name} {version}"
+            );
+        }
+    }
+
+    #[test]
+    fn skips_withdrawn_advisories_and_other_ecosystems() {
+        let mut withdrawn = advisory("WITHDRAWN", "npm", "foo", json!([{ "introduced": "0" }]));
+        withdrawn["withdrawn"] = json!("2024-01-01T00:00:00Z");
+        let db = db(&[
+            withdrawn,
+            advisory("MAVEN", "Maven", "foo", json!([{ "introduced": "0" }])),
+        ]);
+        assert!(db.is_empty());
+        assert!(check(&db, Ecosystem::Npm, "foo", "1.0.0").is_empty());
+    }
+
+    #[test]
+    fn rates_by_cvss_vector_then_label_and_sorts_most_severe_first() {
+        let mut scored = advisory("A-SCORED", "npm", "foo", json!([{ "introduced": "0" }]));
+        scored["severity"] = json!([{
+            "type": "CVSS_V3",
+            "score": "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H",
+        }]);
+        scored["database_specific"] = json!({ "severity": "LOW" });
+        let mut labelled = advisory("B-LABELLED", "npm", "foo", json!([{ "introduced": "0" }]));
+        labelled["database_specific"] = json!({ "severity": "MODERATE" });
+        let unrated = advisory("C-UNRATED", "npm", "foo", json!([{ "introduced": "0" }]));
+        let db = db(&[unrated, labelled, scored]);
+
+        let rated: Vec<_> = db
+            .vulnerabilities(&dependency(Ecosystem::Npm, "foo", "1.0.0"))
+            .into_iter()
+            .map(|v| (v.id, v.severity))
+            .collect();
+        assert_eq!(
+            rated,
+            [
+                ("A-SCORED".to_owned(), Some(Severity::Critical)),
+                ("B-LABELLED".to_owned(), Some(Severity::Medium)),
+                ("C-UNRATED".to_owned(), None),
+            ]
+        );
+    }
+}
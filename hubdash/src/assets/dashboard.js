@@ -11,4 +11,28 @@ document.addEventListener('alpine:init', () => {
             }
         }
     }));
+
+    Alpine.data('triggerFilter', () => ({
+        selected: [],
+
+        toggle(trigger) {
+            const index = this.selected.indexOf(trigger);
+            if (index === -1) {
+                this.selected.push(trigger);
+            } else {
+                this.selected.splice(index, 1);
+            }
+        },
+
+        isSelected(trigger) {
+            return this.selected.includes(trigger);
+        },
+
+        // Whether a repository with the given space-separated triggers has
+        // every selected one.
+        matches(triggers) {
+            const present = triggers.split(' ');
+            return this.selected.every((trigger) => present.includes(trigger));
+        }
+    }));
 });
@@ -0,0 +1,338 @@
+//! GitHub OAuth sign-in and sign-out.
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use serde::Deserialize;
+use url::Url;
+
+use crate::AppState;
+use crate::github::{self, GitHubClient};
+use crate::session::{SESSION_COOKIE, Session, random_token};
+
+/// GitHub's OAuth authorization endpoint.
+pub const DEFAULT_AUTHORIZE_URL: &str = "https://github.com/login/oauth/authorize";
+
+/// GitHub's OAuth token endpoint.
+pub const DEFAULT_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+
+/// Cookie holding the `state` parameter of an in-flight sign-in.
+const STATE_COOKIE: &str = "hubdash_oauth_state";
+
+/// Settings for signing users in with a GitHub OAuth app.
+#[derive(Clone)]
+pub struct OAuthConfig {
+    client_id: String,
+    client_secret: String,
+    authorize_url: Url,
+    token_url: Url,
+    redirect_url: Option<Url>,
+    api: GitHubClient,
+}
+
+impl OAuthConfig {
+    /// Creates a configuration for the OAuth app with the given credentials.
+    pub fn new(client_id: impl Into<String>, client_secret: impl Into<String>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            authorize_url: Url::parse(DEFAULT_AUTHORIZE_URL).expect("valid authorize URL"),
+            token_url: Url::parse(DEFAULT_TOKEN_URL).expect("valid token URL"),
+            redirect_url: None,
+            api: GitHubClient::new(Url::parse(github::DEFAULT_API_URL).expect("valid API URL")),
+        }
+    }
+
+    /// Overrides the endpoint users are sent to for authorization.
+    pub fn with_authorize_url(mut self, url: Url) -> Self {
+        self.authorize_url = url;
+        self
+    }
+
+    /// Overrides the endpoint authorization codes are exchanged at.
+    pub fn with_token_url(mut self, url: Url) -> Self {
+        self.token_url = url;
+        self
+    }
+
+    /// Sets the callback URL sent to GitHub.
+    ///
+    /// When unset, GitHub falls back to the callback registered for the app.
+    pub fn with_redirect_url(mut self, url: Url) -> Self {
+        self.redirect_url = Some(url);
+        self
+    }
+
+    /// Overrides the API used to look up the signed-in user.
+    pub fn with_api_url(mut self, url: Url) -> Self {
+        self.api = GitHubClient::new(url);
+        self
+    }
+
+    /// Whether cookies should be restricted to HTTPS.
+    fn secure_cookies(&self) -> bool {
+        self.redirect_url
+            .as_ref()
+            .is_some_and(|url| url.scheme() == "https")
+    }
+
+    fn cookie<'c>(&self, name: &'c str, value: String) -> Cookie<'c> {
+        Cookie::build((name, value))
+            .path("/")
+            .http_only(true)
+            .secure(self.secure_cookies())
+            .same_site(SameSite::Lax)
+            .build()
+    }
+
+    fn authorization_url(&self, state: &str) -> Url {
+        let mut url = self.authorize_url.clone();
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("client_id", &self.client_id);
+            query.append_pair("state", state);
+            if let Some(redirect) = &self.redirect_url {
+                query.append_pair("redirect_uri", redirect.as_str());
+            }
+        }
+        url
+    }
+
+    async fn exchange_code(&self, code: &str) -> Result<String, AuthError> {
+        let mut form = vec![
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+            ("code", code),
+        ];
+        if let Some(redirect) = &self.redirect_url {
+            form.push(("redirect_uri", redirect.as_str()));
+        }
+
+        let response: TokenResponse = reqwest::Client::new()
+            .post(self.token_url.clone())
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&form)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| AuthError::Exchange(err.to_string()))?
+            .json()
+            .await
+            .map_err(|err| AuthError::Exchange(err.to_string()))?;
+
+        match response {
+            TokenResponse::Token { access_token } => Ok(access_token),
+            TokenResponse::Error {
+                error,
+                error_description,
+            } => Err(AuthError::Exchange(error_description.unwrap_or(error))),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenResponse {
+    Token {
+        access_token: String,
+    },
+    Error {
+        error: String,
+        error_description: Option<String>,
+    },
+}
+
+/// Query parameters GitHub appends to the callback URL.
+#[derive(Deserialize)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// Reasons a sign-in attempt can fail.
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("sign-in was denied: {0}")]
+    Denied(String),
+    #[error("sign-in request is missing or has an invalid state")]
+    InvalidState,
+    #[error("could not exchange authorization code: {0}")]
+    Exchange(String),
+    #[error("could not look up the signed-in user: {0}")]
+    User(#[from] github::Error),
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        tracing::warn!(error = %self, "sign-in failed");
+        let status = match self {
+            Self::Denied(_) | Self::InvalidState => StatusCode::BAD_REQUEST,
+            Self::Exchange(_) | Self::User(_) => StatusCode::BAD_GATEWAY,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// Starts the OAuth web flow by redirecting to GitHub.
+pub async fn signin(State(state): State<AppState>, jar: CookieJar) -> Response {
+    let Some(oauth) = state.oauth() else {
+        return Redirect::to("/dashboard").into_response();
+    };
+
+    let csrf = random_token();
+    (
+        jar.add(oauth.cookie(STATE_COOKIE, csrf.clone())),
+        Redirect::to(oauth.authorization_url(&csrf).as_str()),
+    )
+        .into_response()
+}
+
+/// Completes the OAuth web flow and starts a session.
+pub async fn callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(params): Query<CallbackParams>,
+) -> Result<Response, AuthError> {
+    let Some(oauth) = state.oauth() else {
+        return Ok(Redirect::to("/dashboard").into_response());
+    };
+    if let Some(error) = params.error {
+        return Err(AuthError::Denied(error));
+    }
+
+    let expected = jar.get(STATE_COOKIE).map(|c| c.value().to_owned());
+    let jar = jar.remove(Cookie::build(STATE_COOKIE).path("/"));
+    let (Some(code), Some(returned), Some(expected)) = (params.code, params.state, expected) else {
+        return Err(AuthError::InvalidState);
+    };
+    if returned != expected {
+        return Err(AuthError::InvalidState);
+    }
+
+    let access_token = oauth.exchange_code(&code).await?;
+    let user = oauth
+        .api
+        .clone()
+        .with_token(access_token.clone())
+        .get_authenticated_user()
+        .await?;
+    tracing::info!(login = %user.login, "user signed in");
+
+    let id = state.sessions().insert(Session {
+        login: user.login,
+        access_token,
+    });
+    let jar = jar.add(oauth.cookie(SESSION_COOKIE, id));
+    Ok((jar, Redirect::to("/dashboard")).into_response())
+}
+
+/// Whether a request was sent by one of the dashboard's own pages, going by
+/// the `Sec-Fetch-Site` header or, from browsers without it, `Origin`.
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    if let Some(site) = headers.get("sec-fetch-site") {
+        return site == "same-origin";
+    }
+    let (Some(origin), Some(host)) = (headers.get(header::ORIGIN), headers.get(header::HOST))
+    else {
+        return false;
+    };
+    let (Ok(origin), Ok(host)) = (origin.to_str(), host.to_str()) else {
+        return false;
+    };
+    origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+        .is_some_and(|origin| origin.eq_ignore_ascii_case(host))
+}
+
+/// Ends the current session, revokes its token and returns to the
+/// landing page.
+///
+/// Only form posts from the dashboard itself are honoured, so other sites
+/// cannot sign users out.
+pub async fn signout(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> Response {
+    if !is_same_origin(&headers) {
+        tracing::warn!("rejected cross-site sign-out");
+        return (
+            StatusCode::FORBIDDEN,
+            "sign-out must come from the dashboard",
+        )
+            .into_response();
+    }
+    let session = jar
+        .get(SESSION_COOKIE)
+        .and_then(|cookie| state.sessions().remove(cookie.value()));
+    if let (Some(oauth), Some(session)) = (state.oauth(), session) {
+        let revoked = oauth
+            .api
+            .revoke_oauth_token(
+                &oauth.client_id,
+                &oauth.client_secret,
+                &session.access_token,
+            )
+            .await;
+        if let Err(err) = revoked {
+            tracing::warn!(login = %session.login, error = %err, "failed to revoke token");
+        }
+    }
+    let jar = jar.remove(Cookie::build(SESSION_COOKIE).path("/"));
+    (jar, Redirect::to("/")).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_out_requires_the_same_origin() {
+        for (headers, expected) in [
+            (&[("sec-fetch-site", "same-origin")][..], true),
+            (&[("sec-fetch-site", "cross-site")], false),
+            (
+                &[
+                    ("sec-fetch-site", "same-site"),
+                    ("origin", "https://dash.example.com"),
+                    ("host", "dash.example.com"),
+                ],
+                false,
+            ),
+            (
+                &[
+                    ("origin", "https://dash.example.com"),
+                    ("host", "dash.example.com"),
+                ],
+                true,
+            ),
+            (
+                &[
+                    ("origin", "http://localhost:3000"),
+                    ("host", "localhost:3000"),
+                ],
+                true,
+            ),
+            (
+                &[
+                    ("origin", "https://evil.example"),
+                    ("host", "dash.example.com"),
+                ],
+                false,
+            ),
+            (&[("origin", "null"), ("host", "dash.example.com")], false),
+            (&[("host", "dash.example.com")], false),
+        ] {
+            let headers: HeaderMap = headers
+                .iter()
+                .map(|&(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+                .collect();
+            assert_eq!(is_same_origin(&headers), expected, "{headers:?}");
+        }
+    }
+}
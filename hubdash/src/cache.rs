@@ -0,0 +1,462 @@
+//! In-process cache in front of a [`RepoSource`].
+//!
+//! Entries are kept per repository. An entry younger than the cache's TTL
+//! is served as is; once it is older, it is still served for a further
+//! grace period while a fresh copy is fetched in the background
+//! (stale-while-revalidate). Entries past the grace period are refetched
+//! before responding, unless the source is rate limited, in which case
+//! whatever is cached keeps being served. Concurrent requests that miss
+//! the cache share a single fetch.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use futures_util::FutureExt;
+use futures_util::future::{BoxFuture, Shared};
+use jiff::{SignedDuration, Timestamp};
+
+use crate::dashboard::{JobSummary, RepoSummary};
+use crate::source::{RepoSource, SourceError};
+
+/// How long cached data is served without revalidation by default.
+pub const DEFAULT_CACHE_TTL: SignedDuration = SignedDuration::from_mins(1);
+
+/// How long stale data may be served while it is revalidated by default.
+pub const DEFAULT_STALE_TTL: SignedDuration = SignedDuration::from_mins(10);
+
+/// Cache key of a repository; GitHub owner and repository names are
+/// case-insensitive.
+type Key = (String, String);
+
+fn key(owner: &str, repo: &str) -> Key {
+    (owner.to_ascii_lowercase(), repo.to_ascii_lowercase())
+}
+
+enum Freshness {
+    Fresh,
+    Stale,
+    Expired,
+}
+
+/// Outcome of looking something up in the cache.
+enum Lookup<T> {
+    /// A fresh value was found.
+    Fresh(T),
+    /// A stale value is cached; `true` if the caller should revalidate it.
+    Stale(bool),
+    /// Nothing usable is cached.
+    Miss,
+}
+
+/// Runs a task such as a revalidation without holding up the current
+/// request, or inline when there is no runtime to spawn it onto.
+pub(crate) async fn background(task: impl Future<Output = ()> + Send + 'static) {
+    #[cfg(feature = "tokio")]
+    tokio::spawn(task);
+    #[cfg(not(feature = "tokio"))]
+    task.await;
+}
+
+struct Entry<T> {
+    value: T,
+    fetched_at: Timestamp,
+    refreshing: bool,
+}
+
+impl<T> Entry<T> {
+    fn new(value: T, fetched_at: Timestamp) -> Self {
+        Self {
+            value,
+            fetched_at,
+            refreshing: false,
+        }
+    }
+}
+
+/// A fetch from the source that every request waiting for it shares.
+type Loading<T> = Shared<BoxFuture<'static, Result<T, Arc<SourceError>>>>;
+
+/// Recreates an error of a shared fetch for one of the requests that
+/// waited for it.
+fn unshare(err: &SourceError) -> SourceError {
+    match err {
+        SourceError::Upstream(source) => SourceError::Upstream(source.to_string().into()),
+        SourceError::RateLimited { until } => SourceError::RateLimited { until: *until },
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    /// The repositories in the source's list, in order.
+    list: Option<Entry<Vec<Key>>>,
+    repos: HashMap<Key, Entry<RepoSummary>>,
+    /// The fetch of the list in progress after a miss, if any.
+    loading_list: Option<Loading<Vec<RepoSummary>>>,
+    /// The fetches of repositories in progress after misses.
+    loading_repos: HashMap<Key, Loading<Option<RepoSummary>>>,
+}
+
+impl Entries {
+    fn collect(&self) -> Vec<RepoSummary> {
+        let Some(list) = &self.list else {
+            return Vec::new();
+        };
+        list.value
+            .iter()
+            .filter_map(|key| self.repos.get(key))
+            .map(|entry| entry.value.clone())
+            .collect()
+    }
+}
+
+struct Inner {
+    source: Arc<dyn RepoSource>,
+    ttl: SignedDuration,
+    stale_ttl: SignedDuration,
+    entries: Mutex<Entries>,
+}
+
+/// Caches the summaries produced by another [`RepoSource`].
+///
+/// Cloning is cheap and yields a handle to the same cache.
+#[derive(Clone)]
+pub struct RepoCache {
+    inner: Arc<Inner>,
+}
+
+impl RepoCache {
+    /// Creates an empty cache in front of `source` with the default TTLs.
+    pub fn new(source: Arc<dyn RepoSource>) -> Self {
+        Self::with_ttl(source, DEFAULT_CACHE_TTL, DEFAULT_STALE_TTL)
+    }
+
+    /// Creates an empty cache that serves entries for `ttl`, then serves
+    /// them for up to `stale_ttl` longer while revalidating.
+    pub fn with_ttl(
+        source: Arc<dyn RepoSource>,
+        ttl: SignedDuration,
+        stale_ttl: SignedDuration,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                source,
+                ttl,
+                stale_ttl,
+                entries: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Returns the source this cache is in front of.
+    pub fn source(&self) -> Arc<dyn RepoSource> {
+        self.inner.source.clone()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.inner.entries.lock().expect("repo cache poisoned")
+    }
+
+    fn freshness(&self, fetched_at: Timestamp) -> Freshness {
+        let age = Timestamp::now().duration_since(fetched_at);
+        if age < self.inner.ttl {
+            Freshness::Fresh
+        } else if age < self.inner.ttl.saturating_add(self.inner.stale_ttl) {
+            Freshness::Stale
+        } else {
+            Freshness::Expired
+        }
+    }
+
+    fn lookup_list(&self) -> Lookup<Vec<RepoSummary>> {
+        let mut entries = self.entries();
+        let Some(list) = entries.list.as_mut() else {
+            return Lookup::Miss;
+        };
+        match self.freshness(list.fetched_at) {
+            Freshness::Fresh => Lookup::Fresh(entries.collect()),
+            Freshness::Stale => Lookup::Stale(!std::mem::replace(&mut list.refreshing, true)),
+            Freshness::Expired => Lookup::Miss,
+        }
+    }
+
+    fn lookup_repo(&self, key: &Key) -> Lookup<RepoSummary> {
+        let mut entries = self.entries();
+        let Some(entry) = entries.repos.get_mut(key) else {
+            return Lookup::Miss;
+        };
+        match self.freshness(entry.fetched_at) {
+            Freshness::Fresh => Lookup::Fresh(entry.value.clone()),
+            Freshness::Stale => Lookup::Stale(!std::mem::replace(&mut entry.refreshing, true)),
+            Freshness::Expired => Lookup::Miss,
+        }
+    }
+
+    /// Stores a freshly fetched summary, stamping it with the fetch time.
+    fn store(entries: &mut Entries, mut summary: RepoSummary, now: Timestamp) -> Key {
+        let key = key(&summary.owner, &summary.repo);
+        summary.fetched_at = now;
+        entries.repos.insert(key.clone(), Entry::new(summary, now));
+        key
+    }
+
+    async fn load_list(&self) -> Result<Vec<RepoSummary>, SourceError> {
+        let fetched = self.inner.source.list_repos().await;
+        let mut entries = self.entries();
+        if let Some(list) = &mut entries.list {
+            list.refreshing = false;
+        }
+        let fetched = match fetched {
+            Err(SourceError::RateLimited { until }) if entries.list.is_some() => {
+                tracing::warn!(%until, "rate limited, serving cached repository list");
+                return Ok(entries.collect());
+            }
+            fetched => fetched?,
+        };
+        let now = Timestamp::now();
+        let keys = fetched
+            .into_iter()
+            .map(|summary| Self::store(&mut entries, summary, now))
+            .collect();
+        entries.list = Some(Entry::new(keys, now));
+        Ok(entries.collect())
+    }
+
+    async fn load_repo(&self, owner: &str, repo: &str) -> Result<Option<RepoSummary>, SourceError> {
+        let fetched = self.inner.source.find_repo(owner, repo).await;
+        let mut entries = self.entries();
+        let key = key(owner, repo);
+        if let Some(entry) = entries.repos.get_mut(&key) {
+            entry.refreshing = false;
+        }
+        let fetched = match fetched {
+            Err(SourceError::RateLimited { until }) if entries.repos.contains_key(&key) => {
+                tracing::warn!(%owner, %repo, %until, "rate limited, serving cached repository");
+                return Ok(entries.repos.get(&key).map(|entry| entry.value.clone()));
+            }
+            fetched => fetched?,
+        };
+        match fetched {
+            Some(summary) => {
+                Self::store(&mut entries, summary, Timestamp::now());
+                Ok(entries.repos.get(&key).map(|entry| entry.value.clone()))
+            }
+            None => {
+                entries.repos.remove(&key);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Fetches the list after a miss, joining a fetch already in progress.
+    async fn load_list_once(&self) -> Result<Vec<RepoSummary>, SourceError> {
+        let loading = self
+            .entries()
+            .loading_list
+            .get_or_insert_with(|| {
+                let cache = self.clone();
+                async move {
+                    let loaded = cache.load_list().await;
+                    cache.entries().loading_list = None;
+                    loaded.map_err(Arc::new)
+                }
+                .boxed()
+                .shared()
+            })
+            .clone();
+        loading.await.map_err(|err| unshare(&err))
+    }
+
+    /// Fetches a repository after a miss, joining a fetch already in
+    /// progress.
+    async fn load_repo_once(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<RepoSummary>, SourceError> {
+        let key = key(owner, repo);
+        let loading = self
+            .entries()
+            .loading_repos
+            .entry(key.clone())
+            .or_insert_with(|| {
+                let cache = self.clone();
+                let (owner, repo) = (owner.to_owned(), repo.to_owned());
+                async move {
+                    let loaded = cache.load_repo(&owner, &repo).await;
+                    cache.entries().loading_repos.remove(&key);
+                    loaded.map_err(Arc::new)
+                }
+                .boxed()
+                .shared()
+            })
+            .clone();
+        loading.await.map_err(|err| unshare(&err))
+    }
+
+    /// Applies `update` to the cached summary of a repository.
+    ///
+    /// Returns `false` if the repository is not cached, in which case the
+    /// next fetch from the source will pick up the change anyway.
+    pub fn update(&self, owner: &str, repo: &str, update: impl FnOnce(&mut RepoSummary)) -> bool {
+        match self.entries().repos.get_mut(&key(owner, repo)) {
+            Some(entry) => {
+                update(&mut entry.value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the cached summary of a repository with a fresh copy from
+    /// the source.
+    pub async fn refresh(&self, owner: &str, repo: &str) -> Result<bool, SourceError> {
+        Ok(self.load_repo(owner, repo).await?.is_some())
+    }
+}
+
+#[async_trait]
+impl RepoSource for RepoCache {
+    async fn list_repos(&self) -> Result<Vec<RepoSummary>, SourceError> {
+        match self.lookup_list() {
+            Lookup::Fresh(repos) => Ok(repos),
+            Lookup::Stale(revalidate) => {
+                if revalidate {
+                    let cache = self.clone();
+                    background(async move {
+                        if let Err(err) = cache.load_list().await {
+                            tracing::warn!(error = %err, "failed to revalidate repository list");
+                        }
+                    })
+                    .await;
+                }
+                Ok(self.entries().collect())
+            }
+            Lookup::Miss => self.load_list_once().await,
+        }
+    }
+
+    async fn find_repo(&self, owner: &str, repo: &str) -> Result<Option<RepoSummary>, SourceError> {
+        let key = key(owner, repo);
+        match self.lookup_repo(&key) {
+            Lookup::Fresh(summary) => Ok(Some(summary)),
+            Lookup::Stale(revalidate) => {
+                if revalidate {
+                    let cache = self.clone();
+                    let (owner, repo) = (owner.to_owned(), repo.to_owned());
+                    background(async move {
+                        if let Err(err) = cache.load_repo(&owner, &repo).await {
+                            tracing::warn!(%owner, %repo, error = %err, "failed to revalidate repository");
+                        }
+                    })
+                    .await;
+                }
+                Ok(self.entries().repos.get(&key).map(|e| e.value.clone()))
+            }
+            Lookup::Miss => self.load_repo_once(owner, repo).await,
+        }
+    }
+
+    /// Jobs are passed straight through: they are only looked at when a
+    /// pipeline is expanded, and change while its run is in progress.
+    async fn list_jobs(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<Vec<JobSummary>, SourceError> {
+        self.inner.source.list_jobs(owner, repo, run_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::MockRepos;
+
+    /// A source that counts its fetches, which take a few polls to finish
+    /// so that concurrent requests overlap.
+    #[derive(Default)]
+    struct Counting {
+        fetches: AtomicU32,
+        fail: bool,
+    }
+
+    impl Counting {
+        async fn fetch(&self) -> Result<(), SourceError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            if self.fail {
+                return Err(SourceError::Upstream("unavailable".into()));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl RepoSource for Counting {
+        async fn list_repos(&self) -> Result<Vec<RepoSummary>, SourceError> {
+            self.fetch().await?;
+            MockRepos.list_repos().await
+        }
+
+        async fn find_repo(
+            &self,
+            owner: &str,
+            repo: &str,
+        ) -> Result<Option<RepoSummary>, SourceError> {
+            self.fetch().await?;
+            MockRepos.find_repo(owner, repo).await
+        }
+
+        async fn list_jobs(
+            &self,
+            owner: &str,
+            repo: &str,
+            run_id: u64,
+        ) -> Result<Vec<JobSummary>, SourceError> {
+            MockRepos.list_jobs(owner, repo, run_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_share_one_fetch() {
+        let source = Arc::new(Counting::default());
+        let cache = RepoCache::new(source.clone());
+
+        let (a, b, c) = tokio::join!(cache.list_repos(), cache.list_repos(), cache.list_repos());
+        assert_eq!(a.unwrap().len(), b.unwrap().len());
+        assert!(!c.unwrap().is_empty());
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 1);
+
+        let cache = RepoCache::new(source.clone());
+        let (a, b) = tokio::join!(
+            cache.find_repo("example", "hubdash"),
+            cache.find_repo("Example", "HubDash")
+        );
+        assert!(a.unwrap().is_some() && b.unwrap().is_some());
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_share_a_failure_and_retry_later() {
+        let source = Arc::new(Counting {
+            fail: true,
+            ..Counting::default()
+        });
+        let cache = RepoCache::new(source.clone());
+
+        let (a, b) = tokio::join!(cache.list_repos(), cache.list_repos());
+        assert!(matches!(a, Err(SourceError::Upstream(_))));
+        assert!(matches!(b, Err(SourceError::Upstream(_))));
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 1);
+
+        assert!(cache.list_repos().await.is_err());
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,341 @@
+//! Server-rendered SVG charts of run history.
+
+use jiff::SignedDuration;
+use jiff::civil::Date;
+use maud::{Markup, html};
+
+use crate::dashboard::{PipelineStatus, format_duration};
+use crate::history::RunRecord;
+
+/// Number of runs shown in a run strip.
+pub(crate) const STRIP_RUNS: u32 = 20;
+
+const STRIP_SEGMENT_WIDTH: u32 = 4;
+const STRIP_SEGMENT_GAP: u32 = 2;
+const STRIP_HEIGHT: u32 = 16;
+
+fn status_fill_class(status: PipelineStatus) -> &'static str {
+    match status {
+        PipelineStatus::Success => "chart-success",
+        PipelineStatus::Failure | PipelineStatus::TimedOut | PipelineStatus::StartupFailure => {
+            "chart-failure"
+        }
+        PipelineStatus::Pending
+        | PipelineStatus::Queued
+        | PipelineStatus::InProgress
+        | PipelineStatus::Waiting
+        | PipelineStatus::ActionRequired => "chart-pending",
+        PipelineStatus::Cancelled
+        | PipelineStatus::Skipped
+        | PipelineStatus::Neutral
+        | PipelineStatus::Stale => "chart-cancelled",
+    }
+}
+
+fn rate_fill_class(rate: u8) -> &'static str {
+    match rate {
+        90..=100 => "chart-success",
+        50..=89 => "chart-pending",
+        _ => "chart-failure",
+    }
+}
+
+fn run_title(run: &RunRecord) -> String {
+    format!(
+        "{}: {} ({})",
+        run.workflow,
+        run.status.as_str(),
+        run.started_at.strftime("%Y-%m-%d %H:%M UTC")
+    )
+}
+
+/// Renders a strip with one segment per run, oldest on the left, each
+/// linking to the run on GitHub.
+///
+/// `runs` are expected newest first, as returned by
+/// [`RunHistory::runs`](crate::history::RunHistory::runs).
+pub(crate) fn run_strip(runs: &[RunRecord]) -> Markup {
+    let runs = &runs[..runs.len().min(STRIP_RUNS as usize)];
+    let step = STRIP_SEGMENT_WIDTH + STRIP_SEGMENT_GAP;
+    let width = STRIP_RUNS * step - STRIP_SEGMENT_GAP;
+    let failures = runs.iter().filter(|r| r.status.is_failure()).count();
+    let label = format!("{failures} of the last {} runs failed", runs.len());
+    // Right-align so the newest run is always in the same place.
+    let offset = (STRIP_RUNS as usize).saturating_sub(runs.len()) as u32 * step;
+    html! {
+        svg class="run-strip" xmlns="http://www.w3.org/2000/svg"
+            width=(width) height=(STRIP_HEIGHT) viewBox={ "0 0 " (width) " " (STRIP_HEIGHT) }
+            role="img" aria-label=(label)
+            "x-on:click.stop"=""
+        {
+            @for (i, run) in (0u32..).zip(runs.iter().rev()) {
+                a href=(run.url) target="_blank" {
+                    title { (run_title(run)) }
+                    rect class=(status_fill_class(run.status))
+                        x=(offset + i * step) y="0"
+                        width=(STRIP_SEGMENT_WIDTH) height=(STRIP_HEIGHT) rx="1" {}
+                }
+            }
+        }
+    }
+}
+
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 160.0;
+const MARGIN_LEFT: f64 = 56.0;
+const MARGIN_RIGHT: f64 = 8.0;
+const MARGIN_TOP: f64 = 8.0;
+const MARGIN_BOTTOM: f64 = 20.0;
+
+/// A workflow's runs on a single day, or on a group of days in long ranges.
+#[derive(Debug, Clone)]
+pub(crate) struct DayStats {
+    /// The day, or the first day of the group, in UTC.
+    pub(crate) day: Date,
+    /// Number of runs started that day.
+    pub(crate) runs: usize,
+    /// Median duration of the runs that finished.
+    pub(crate) p50: Option<SignedDuration>,
+    /// 90th percentile duration of the runs that finished.
+    pub(crate) p90: Option<SignedDuration>,
+    /// Percentage of finished runs that succeeded.
+    pub(crate) success_rate: Option<u8>,
+}
+
+/// Rounds a coordinate to a tenth, which is plenty for the chart sizes.
+fn round(coordinate: f64) -> f64 {
+    (coordinate * 10.0).round() / 10.0
+}
+
+/// Maps days and values onto the plot area of a chart.
+struct Plot {
+    days: usize,
+    max: f64,
+}
+
+impl Plot {
+    fn slot(&self) -> f64 {
+        (CHART_WIDTH - MARGIN_LEFT - MARGIN_RIGHT) / self.days.max(1) as f64
+    }
+
+    /// Returns the horizontal center of the `index`th day.
+    fn x(&self, index: usize) -> f64 {
+        round(MARGIN_LEFT + (index as f64 + 0.5) * self.slot())
+    }
+
+    fn y(&self, value: f64) -> f64 {
+        let height = CHART_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+        round(MARGIN_TOP + height * (1.0 - value / self.max))
+    }
+
+    /// Renders horizontal grid lines at `ticks`, labelled by `label`.
+    fn grid(&self, ticks: &[f64], label: impl Fn(f64) -> String) -> Markup {
+        html! {
+            @for &tick in ticks {
+                line class="chart-grid"
+                    x1=(MARGIN_LEFT) x2=(CHART_WIDTH - MARGIN_RIGHT)
+                    y1=(self.y(tick)) y2=(self.y(tick)) {}
+                text class="chart-axis" x=(MARGIN_LEFT - 6.0) y=(self.y(tick) + 4.0) text-anchor="end" {
+                    (label(tick))
+                }
+            }
+        }
+    }
+
+    /// Labels the first and last day along the bottom.
+    fn day_axis(&self, days: &[DayStats]) -> Markup {
+        let y = CHART_HEIGHT - 4.0;
+        html! {
+            @if let (Some(first), Some(last)) = (days.first(), days.last()) {
+                text class="chart-axis" x=(MARGIN_LEFT) y=(y) text-anchor="start" {
+                    (first.day.strftime("%b %d"))
+                }
+                @if days.len() > 1 {
+                    text class="chart-axis" x=(CHART_WIDTH - MARGIN_RIGHT) y=(y) text-anchor="end" {
+                        (last.day.strftime("%b %d"))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Builds a line through the days that have a value, skipping over
+    /// days that do not.
+    fn path(&self, values: impl Iterator<Item = Option<f64>>) -> String {
+        let points: Vec<_> = values
+            .enumerate()
+            .filter_map(|(index, value)| Some(format!("{},{}", self.x(index), self.y(value?))))
+            .collect();
+        match points.split_first() {
+            Some((first, rest)) if !rest.is_empty() => format!("M{first} L{}", rest.join(" ")),
+            _ => String::new(),
+        }
+    }
+}
+
+fn chart_svg(label: &str, content: Markup) -> Markup {
+    html! {
+        svg class="trend-chart" xmlns="http://www.w3.org/2000/svg"
+            viewBox={ "0 0 " (CHART_WIDTH) " " (CHART_HEIGHT) }
+            role="img" aria-label=(label)
+        {
+            (content)
+        }
+    }
+}
+
+fn empty_chart() -> Markup {
+    html! { p class="chart-empty" { "No finished runs in this window." } }
+}
+
+/// Renders the daily median and 90th percentile run duration as lines.
+pub(crate) fn duration_chart(days: &[DayStats]) -> Markup {
+    let longest = days
+        .iter()
+        .filter_map(|d| d.p90.or(d.p50))
+        .map(|d| d.as_secs_f64())
+        .fold(0.0, f64::max);
+    if longest <= 0.0 {
+        return empty_chart();
+    }
+    // Round the scale up to whole minutes.
+    let plot = Plot {
+        days: days.len(),
+        max: (longest / 60.0).ceil().max(1.0) * 60.0,
+    };
+    let minutes = |secs: f64| format_duration(Some(SignedDuration::from_secs(secs as i64)));
+    let series = [
+        (
+            "p90",
+            "chart-line-p90",
+            days.iter().map(|d| d.p90).collect::<Vec<_>>(),
+        ),
+        (
+            "p50",
+            "chart-line-p50",
+            days.iter().map(|d| d.p50).collect::<Vec<_>>(),
+        ),
+    ];
+    let content = html! {
+        (plot.grid(&[0.0, plot.max / 2.0, plot.max], minutes))
+        (plot.day_axis(days))
+        @for (name, class, values) in &series {
+            path class=(class) d=(plot.path(values.iter().map(|v| v.map(|d| d.as_secs_f64())))) {}
+            @for (index, (day, value)) in days.iter().zip(values).enumerate() {
+                @if let Some(value) = value {
+                    circle class=(class) cx=(plot.x(index)) cy=(plot.y(value.as_secs_f64())) r="2.5" {
+                        title { (day.day) " " (name) ": " (format_duration(Some(*value))) }
+                    }
+                }
+            }
+        }
+    };
+    html! {
+        (chart_svg("Run duration per day", content))
+        div class="chart-legend" {
+            span class="legend-p50" { "p50" }
+            span class="legend-p90" { "p90" }
+        }
+    }
+}
+
+/// Renders the daily success rate as bars.
+pub(crate) fn success_chart(days: &[DayStats]) -> Markup {
+    if days.iter().all(|d| d.success_rate.is_none()) {
+        return empty_chart();
+    }
+    let plot = Plot {
+        days: days.len(),
+        max: 100.0,
+    };
+    let width = round((plot.slot() * 0.7).max(1.0));
+    let content = html! {
+        (plot.grid(&[0.0, 50.0, 100.0], |tick| format!("{tick}%")))
+        (plot.day_axis(days))
+        @for (index, day) in days.iter().enumerate() {
+            @if let Some(rate) = day.success_rate {
+                @let top = plot.y(f64::from(rate));
+                rect class=(rate_fill_class(rate))
+                    x=(round(plot.x(index) - width / 2.0)) y=(top)
+                    width=(width) height=(plot.y(0.0) - top) {
+                    title { (day.day) ": " (rate) "% of " (day.runs) " runs" }
+                }
+            }
+        }
+    };
+    chart_svg("Success rate per day", content)
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::Timestamp;
+
+    use super::*;
+    use crate::history::tests::run;
+
+    /// Returns the link and horizontal position of each strip segment, in
+    /// the order they are drawn.
+    fn segments(strip: &str) -> Vec<(String, u32)> {
+        strip
+            .split("<a href=\"")
+            .skip(1)
+            .map(|segment| {
+                let (href, rest) = segment.split_once('"').unwrap();
+                let x = rest.split_once(" x=\"").unwrap().1;
+                let x = x.split_once('"').unwrap().0;
+                (href.to_owned(), x.parse().unwrap())
+            })
+            .collect()
+    }
+
+    /// Runs `count` down to 1, newest first, failing every fifth.
+    fn runs(count: u64) -> Vec<RunRecord> {
+        let now = Timestamp::now();
+        (1..=count)
+            .rev()
+            .map(|id| {
+                let status = if id % 5 == 0 {
+                    PipelineStatus::Failure
+                } else {
+                    PipelineStatus::Success
+                };
+                run(id, status, now - SignedDuration::from_mins(id as i64))
+            })
+            .collect()
+    }
+
+    fn url(run_id: u64) -> String {
+        format!("https://github.com/example/hubdash/actions/runs/{run_id}")
+    }
+
+    #[test]
+    fn run_strip_right_aligns_a_few_runs() {
+        let strip = run_strip(&runs(3)).into_string();
+
+        let step = STRIP_SEGMENT_WIDTH + STRIP_SEGMENT_GAP;
+        let offset = (STRIP_RUNS - 3) * step;
+        assert_eq!(
+            segments(&strip),
+            [
+                (url(1), offset),
+                (url(2), offset + step),
+                (url(3), offset + 2 * step),
+            ]
+        );
+        assert!(strip.contains(r#"aria-label="0 of the last 3 runs failed""#));
+    }
+
+    #[test]
+    fn run_strip_shows_only_the_newest_runs() {
+        let strip = run_strip(&runs(25)).into_string();
+
+        let step = STRIP_SEGMENT_WIDTH + STRIP_SEGMENT_GAP;
+        let expected: Vec<_> = (6..=25)
+            .zip(0..)
+            .map(|(id, i)| (url(id), i * step))
+            .collect();
+        assert_eq!(segments(&strip), expected);
+        assert_eq!(expected.len(), STRIP_RUNS as usize);
+        assert!(strip.contains(r#"aria-label="4 of the last 20 runs failed""#));
+    }
+}
@@ -1,31 +1,100 @@
 //! Dashboard page showing repository CI/CD health.
 
-use axum::{extract::Path, response::IntoResponse};
+use std::cmp::Reverse;
+
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
 use bitflags::bitflags;
-use jiff::SignedDuration;
+use jiff::{SignedDuration, Timestamp, Unit};
 use maud::{Markup, PreEscaped, html};
+use serde::Deserialize;
 use url::Url;
 
-use crate::layout::{base_layout, check_icon};
-use crate::mocks::{find_repo, mock_repos};
+use crate::AppState;
+use crate::advisories::{Severity, Vulnerability};
+use crate::charts::{STRIP_RUNS, run_strip};
+use crate::github::RateLimits;
+use crate::history::{HistoryError, RunFilter, RunRecord, Window};
+use crate::layout::base_layout;
+use crate::session::Session;
+use crate::source::SourceError;
+use crate::versions::{self, VersionGap, go::PseudoVersion};
+use crate::workflows::WorkflowTriggers;
 
 /// Status of a pipeline run.
+///
+/// Completed runs carry GitHub's conclusion; the others the stage they
+/// have reached.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PipelineStatus {
+    /// The run completed successfully.
     Success,
+    /// The run completed with a failure.
     Failure,
+    /// The run has been requested but not yet queued, or its state is
+    /// unknown.
     Pending,
+    /// The run is queued for a runner.
+    Queued,
+    /// The run is in progress.
+    InProgress,
+    /// The run is waiting for a deployment protection rule.
+    Waiting,
+    /// The run was cancelled before it finished.
     Cancelled,
+    /// The run was skipped, e.g. because its conditions did not hold.
+    Skipped,
+    /// The run completed with neither a success nor a failure.
+    Neutral,
+    /// The run went unfinished for so long that GitHub marked it stale.
+    Stale,
+    /// The run exceeded its time limit.
+    TimedOut,
+    /// The run needs someone to act, e.g. approve a first-time
+    /// contributor's workflows.
+    ActionRequired,
+    /// The run failed before any job started, e.g. on an invalid workflow
+    /// file.
+    StartupFailure,
 }
 
 impl PipelineStatus {
+    /// Every status, in the order they are listed above.
+    pub const ALL: [Self; 13] = [
+        Self::Success,
+        Self::Failure,
+        Self::Pending,
+        Self::Queued,
+        Self::InProgress,
+        Self::Waiting,
+        Self::Cancelled,
+        Self::Skipped,
+        Self::Neutral,
+        Self::Stale,
+        Self::TimedOut,
+        Self::ActionRequired,
+        Self::StartupFailure,
+    ];
+
     /// Returns the CSS class for this status.
     pub fn css_class(self) -> &'static str {
         match self {
             Self::Success => "status-badge status-success",
             Self::Failure => "status-badge status-failure",
             Self::Pending => "status-badge status-pending",
+            Self::Queued => "status-badge status-queued",
+            Self::InProgress => "status-badge status-in-progress",
+            Self::Waiting => "status-badge status-waiting",
             Self::Cancelled => "status-badge status-cancelled",
+            Self::Skipped => "status-badge status-skipped",
+            Self::Neutral => "status-badge status-neutral",
+            Self::Stale => "status-badge status-stale",
+            Self::TimedOut => "status-badge status-timed-out",
+            Self::ActionRequired => "status-badge status-action-required",
+            Self::StartupFailure => "status-badge status-startup-failure",
         }
     }
 
@@ -35,33 +104,224 @@ impl PipelineStatus {
             Self::Success => "success",
             Self::Failure => "failure",
             Self::Pending => "pending",
+            Self::Queued => "queued",
+            Self::InProgress => "in progress",
+            Self::Waiting => "waiting",
             Self::Cancelled => "cancelled",
+            Self::Skipped => "skipped",
+            Self::Neutral => "neutral",
+            Self::Stale => "stale",
+            Self::TimedOut => "timed out",
+            Self::ActionRequired => "action required",
+            Self::StartupFailure => "startup failure",
+        }
+    }
+
+    /// Whether the run has completed, whatever its conclusion.
+    pub fn is_finished(self) -> bool {
+        !matches!(
+            self,
+            Self::Pending | Self::Queued | Self::InProgress | Self::Waiting
+        )
+    }
+
+    /// Whether the run failed, including by timing out or failing to start.
+    pub fn is_failure(self) -> bool {
+        matches!(self, Self::Failure | Self::TimedOut | Self::StartupFailure)
+    }
+
+    /// Whether the run passed, or `None` if it does not count towards a
+    /// success rate.
+    ///
+    /// Only runs that got to a verdict count: unfinished, cancelled,
+    /// skipped, neutral and stale runs are left out, as are runs still
+    /// waiting for someone to act.
+    pub fn passed(self) -> Option<bool> {
+        match self {
+            Self::Success => Some(true),
+            _ if self.is_failure() => Some(false),
+            _ => None,
         }
     }
 }
 
 /// Renders a status badge with appropriate styling.
-fn status_badge(status: PipelineStatus) -> Markup {
+pub fn status_badge(status: PipelineStatus) -> Markup {
     html! { span class=(status.css_class()) { (status.as_str()) } }
 }
 
 bitflags! {
     /// Triggers that cause a pipeline to run.
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    pub struct Triggers: u8 {
+    pub struct Triggers: u16 {
         /// Runs on pushes to the main branch.
-        const MAIN = 0b0001;
+        const MAIN = 0b00_0000_0001;
         /// Runs on pull requests.
-        const PR = 0b0010;
+        const PR = 0b00_0000_0010;
         /// Runs on a schedule.
-        const SCHEDULED = 0b0100;
+        const SCHEDULED = 0b00_0000_0100;
         /// Can be triggered manually.
-        const MANUAL = 0b1000;
+        const MANUAL = 0b00_0000_1000;
+        /// Runs on pushes to branches other than main.
+        const BRANCH = 0b00_0001_0000;
+        /// Runs on pushes of tags.
+        const TAG = 0b00_0010_0000;
+        /// Runs when a release is published or changed.
+        const RELEASE = 0b00_0100_0000;
+        /// Can be called from other workflows.
+        const WORKFLOW_CALL = 0b00_1000_0000;
+        /// Runs on `repository_dispatch` events sent through the API.
+        const DISPATCH = 0b01_0000_0000;
+        /// Runs on pull requests entering a merge queue.
+        const MERGE_GROUP = 0b10_0000_0000;
+    }
+}
+
+/// How a trigger is shown on the dashboard.
+struct TriggerKind {
+    trigger: Triggers,
+    /// Identifies the trigger in filters.
+    key: &'static str,
+    label: &'static str,
+    icon: &'static str,
+    description: &'static str,
+}
+
+/// Every trigger, in the order the dashboard shows them.
+const TRIGGER_KINDS: [TriggerKind; 10] = [
+    TriggerKind {
+        trigger: Triggers::MAIN,
+        key: "main",
+        label: "main",
+        icon: "⬆",
+        description: "Runs on pushes to the main branch",
+    },
+    TriggerKind {
+        trigger: Triggers::BRANCH,
+        key: "branch",
+        label: "branches",
+        icon: "⑂",
+        description: "Runs on pushes to other branches",
+    },
+    TriggerKind {
+        trigger: Triggers::TAG,
+        key: "tag",
+        label: "tags",
+        icon: "⌗",
+        description: "Runs on pushes of tags",
+    },
+    TriggerKind {
+        trigger: Triggers::PR,
+        key: "pr",
+        label: "PR",
+        icon: "⇄",
+        description: "Runs on pull requests",
+    },
+    TriggerKind {
+        trigger: Triggers::MERGE_GROUP,
+        key: "merge-group",
+        label: "merge queue",
+        icon: "⇶",
+        description: "Runs in the merge queue",
+    },
+    TriggerKind {
+        trigger: Triggers::SCHEDULED,
+        key: "schedule",
+        label: "schedule",
+        icon: "⏱",
+        description: "Runs on a schedule",
+    },
+    TriggerKind {
+        trigger: Triggers::MANUAL,
+        key: "manual",
+        label: "manual",
+        icon: "▷",
+        description: "Can be triggered manually",
+    },
+    TriggerKind {
+        trigger: Triggers::RELEASE,
+        key: "release",
+        label: "releases",
+        icon: "⚑",
+        description: "Runs on releases",
+    },
+    TriggerKind {
+        trigger: Triggers::DISPATCH,
+        key: "dispatch",
+        label: "dispatch",
+        icon: "⚡",
+        description: "Runs on repository dispatch events",
+    },
+    TriggerKind {
+        trigger: Triggers::WORKFLOW_CALL,
+        key: "call",
+        label: "workflow call",
+        icon: "↪",
+        description: "Can be called from other workflows",
+    },
+];
+
+/// Returns how each trigger in `triggers` is shown.
+fn trigger_kinds(triggers: Triggers) -> impl Iterator<Item = &'static TriggerKind> {
+    TRIGGER_KINDS
+        .iter()
+        .filter(move |kind| triggers.contains(kind.trigger))
+}
+
+/// Lists the triggers in `triggers` for display.
+fn trigger_labels(triggers: Triggers) -> String {
+    let labels: Vec<_> = trigger_kinds(triggers).map(|kind| kind.label).collect();
+    if labels.is_empty() {
+        "—".into()
+    } else {
+        labels.join(", ")
+    }
+}
+
+/// Renders an icon for every trigger, dimming those not in `triggers`
+/// so that each keeps its place from row to row.
+fn trigger_icons(triggers: Triggers) -> Markup {
+    html! {
+        span class="trigger-icons" {
+            @for kind in &TRIGGER_KINDS {
+                @let on = triggers.contains(kind.trigger);
+                span
+                    class=(if on { "trigger-icon on" } else { "trigger-icon off" })
+                    title=(kind.description)
+                    aria-hidden=[(!on).then_some("true")]
+                {
+                    (kind.icon)
+                }
+            }
+        }
+    }
+}
+
+/// Renders the header of the triggers column, whose icons filter the
+/// table down to repositories with every selected trigger.
+fn triggers_header() -> Markup {
+    html! {
+        th class="triggers-header" {
+            "Triggers"
+            span class="trigger-icons trigger-filter" {
+                @for kind in &TRIGGER_KINDS {
+                    button
+                        type="button"
+                        class="trigger-icon"
+                        title={ "Filter: " (kind.description) }
+                        x-bind:class={ "{ 'selected': isSelected('" (kind.key) "') }" }
+                        x-on:click={ "toggle('" (kind.key) "')" }
+                    {
+                        (kind.icon)
+                    }
+                }
+            }
+        }
     }
 }
 
 /// Formats a duration for display.
-fn format_duration(duration: Option<SignedDuration>) -> String {
+pub fn format_duration(duration: Option<SignedDuration>) -> String {
     match duration {
         None => "—".into(),
         Some(d) => {
@@ -73,6 +333,17 @@ fn format_duration(duration: Option<SignedDuration>) -> String {
     }
 }
 
+/// Formats how long ago a timestamp was, e.g. `3m ago`.
+fn format_age(at: Timestamp, now: Timestamp) -> String {
+    let secs = now.duration_since(at).as_secs().max(0);
+    match secs {
+        0..60 => "just now".into(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
 fn repo_expand_url(owner: &str, repo: &str) -> Url {
     let mut url = Url::parse("relative:/").expect("valid base");
     url.path_segments_mut()
@@ -85,6 +356,17 @@ fn repo_expand_url(owner: &str, repo: &str) -> Url {
     url
 }
 
+fn repo_page_url(owner: &str, repo: &str) -> Url {
+    let mut url = Url::parse("relative:/").expect("valid base");
+    url.path_segments_mut()
+        .expect("cannot be base")
+        .push("dashboard")
+        .push("repo")
+        .push(owner)
+        .push(repo);
+    url
+}
+
 fn repo_deps_url(owner: &str, repo: &str) -> Url {
     let mut url = Url::parse("relative:/").expect("valid base");
     url.path_segments_mut()
@@ -97,23 +379,56 @@ fn repo_deps_url(owner: &str, repo: &str) -> Url {
     url
 }
 
+fn run_jobs_url(owner: &str, repo: &str, run_id: u64) -> Url {
+    let mut url = Url::parse("relative:/").expect("valid base");
+    url.path_segments_mut()
+        .expect("cannot be base")
+        .push("dashboard")
+        .push("repo")
+        .push(owner)
+        .push(repo)
+        .push("run")
+        .push(&run_id.to_string())
+        .push("jobs");
+    url
+}
+
 /// Generates the Alpine.js `x-data` attribute value for an expandable component.
 fn expandable_directive(url: &Url, element_id: &str) -> String {
     format!("expandable('{}', '{}')", url.path(), element_id)
 }
 
 /// Repository pipeline summary for display.
+#[derive(Debug, Clone)]
 pub struct RepoSummary {
+    /// The user or organization owning the repository.
     pub owner: String,
+    /// The repository name.
     pub repo: String,
+    /// The repository description.
     pub description: String,
+    /// Percentage of recent pipeline runs that succeeded, as reported by
+    /// the source.
+    ///
+    /// The dashboard computes the rate from run history instead when one
+    /// is kept.
     pub success_rate: u8,
+    /// Status of the most recent pipeline run.
     pub last_status: PipelineStatus,
+    /// Events that trigger the repository's pipelines.
     pub triggers: Triggers,
+    /// Total number of dependencies.
     pub deps_total: u32,
+    /// Number of dependencies known to be on their latest version.
     pub deps_up_to_date: u32,
+    /// Number of known vulnerabilities affecting the dependencies.
+    pub vulnerabilities: u32,
+    /// The repository's pipelines.
     pub pipelines: Vec<PipelineSummary>,
+    /// The repository's dependencies.
     pub dependencies: Vec<Dependency>,
+    /// When this summary was fetched from its source.
+    pub fetched_at: Timestamp,
 }
 
 impl RepoSummary {
@@ -136,9 +451,55 @@ impl RepoSummary {
     pub fn runs_manual(&self) -> bool {
         self.triggers.contains(Triggers::MANUAL)
     }
+
+    /// The filter keys of the repository's triggers, separated by spaces.
+    fn trigger_keys(&self) -> String {
+        trigger_kinds(self.triggers)
+            .map(|kind| kind.key)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
 impl RepoSummary {
+    /// Replaces the repository's dependencies, updating the counts shown
+    /// on the dashboard.
+    pub fn set_dependencies(&mut self, dependencies: Vec<Dependency>) {
+        self.deps_total = dependencies.len() as u32;
+        self.deps_up_to_date = dependencies.iter().filter(|d| d.is_up_to_date()).count() as u32;
+        self.vulnerabilities = dependencies
+            .iter()
+            .map(|d| d.vulnerabilities.len() as u32)
+            .sum();
+        self.dependencies = dependencies;
+    }
+
+    /// Takes the repository's triggers, and those of its pipelines, from
+    /// its workflow files, keeping the ones observed in runs if it has
+    /// none.
+    pub(crate) fn set_workflow_triggers(&mut self, workflows: &[WorkflowTriggers]) {
+        if workflows.is_empty() {
+            return;
+        }
+        self.triggers = workflows
+            .iter()
+            .fold(Triggers::empty(), |acc, w| acc | w.triggers);
+        for pipeline in &mut self.pipelines {
+            if let Some(workflow) = workflows.iter().find(|w| w.name == pipeline.name) {
+                pipeline.triggers = workflow.triggers;
+            }
+        }
+    }
+
+    /// Returns the highest severity among the dependencies'
+    /// vulnerabilities.
+    fn worst_severity(&self) -> Option<Severity> {
+        self.dependencies
+            .iter()
+            .filter_map(Dependency::worst_severity)
+            .max()
+    }
+
     fn full_name(&self) -> String {
         format!("{}/{}", self.owner, self.repo)
     }
@@ -154,20 +515,154 @@ impl RepoSummary {
 }
 
 /// Individual pipeline/workflow summary.
+#[derive(Debug, Clone)]
 pub struct PipelineSummary {
+    /// The workflow name.
     pub name: String,
+    /// Status of the latest run.
     pub status: PipelineStatus,
+    /// Duration of the latest run, if it has finished.
     pub run_time: Option<SignedDuration>,
+    /// Link to the latest run on GitHub.
     pub github_url: Url,
+    /// Events that trigger the workflow, if its file could be read.
+    pub triggers: Triggers,
+    /// ID of the latest run, if the source reports it.
+    pub run_id: Option<u64>,
+}
+
+/// A job within a pipeline run, for display.
+#[derive(Debug, Clone)]
+pub struct JobSummary {
+    /// The job name.
+    pub name: String,
+    /// Status of the job.
+    pub status: PipelineStatus,
+    /// How long the job took, if it has finished.
+    pub run_time: Option<SignedDuration>,
+    /// Labels used to select the job's runner.
+    pub runner_labels: Vec<String>,
+    /// Name of the runner that picked up the job, once one has.
+    pub runner_name: Option<String>,
+    /// Link to the job on GitHub.
+    pub github_url: Option<Url>,
+    /// The job's steps, in order.
+    pub steps: Vec<StepSummary>,
+}
+
+/// A step within a [`JobSummary`].
+#[derive(Debug, Clone)]
+pub struct StepSummary {
+    /// The step's position within the job, starting at 1.
+    pub number: u32,
+    /// The step name.
+    pub name: String,
+    /// Status of the step.
+    pub status: PipelineStatus,
+    /// How long the step took, if it has finished.
+    pub run_time: Option<SignedDuration>,
+}
+
+/// The package ecosystem a dependency comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ecosystem {
+    /// Rust crates from crates.io.
+    Cargo,
+    /// JavaScript packages from the npm registry.
+    Npm,
+    /// Python packages from PyPI.
+    PyPI,
+    /// Go modules.
+    Go,
+}
+
+impl Ecosystem {
+    /// Returns the display name for this ecosystem.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Npm => "npm",
+            Self::PyPI => "pypi",
+            Self::Go => "go",
+        }
+    }
+}
+
+/// How a repository depends on a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+    /// Declared directly and needed at runtime.
+    Direct,
+    /// Declared directly, only needed at build time.
+    Build,
+    /// Declared directly, only needed for development and tests.
+    Dev,
+    /// Pulled in by another dependency.
+    Transitive,
+}
+
+impl DependencyKind {
+    /// Returns the display name for this kind.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::Build => "build",
+            Self::Dev => "dev",
+            Self::Transitive => "transitive",
+        }
+    }
 }
 
 /// Dependency status.
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Dependency {
+    /// The package name.
     pub name: String,
+    /// The ecosystem the package comes from.
+    pub ecosystem: Ecosystem,
+    /// How the repository depends on the package.
+    pub kind: DependencyKind,
+    /// The version currently in use.
     pub current_version: String,
-    pub latest_version: String,
-    pub is_outdated: bool,
+    /// The newest published version, if known.
+    pub latest_version: Option<String>,
+    /// Known vulnerabilities affecting the version in use.
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+impl Dependency {
+    /// Returns how far the current version is behind the latest one, or
+    /// `None` if it is up to date or either version is unknown.
+    pub fn gap(&self) -> Option<VersionGap> {
+        versions::gap(
+            self.ecosystem,
+            &self.current_version,
+            self.latest_version.as_deref()?,
+        )
+    }
+
+    /// Whether a newer version is available.
+    pub fn is_outdated(&self) -> bool {
+        self.gap().is_some()
+    }
+
+    /// Whether the current version is known to be the latest one.
+    ///
+    /// A dependency whose latest version is unknown, or whose versions
+    /// cannot be compared, such as a requirement like `^4.18.2` taken from
+    /// a manifest without a lockfile, is neither up to date nor outdated.
+    pub fn is_up_to_date(&self) -> bool {
+        self.latest_version.as_deref().is_some_and(|latest| {
+            versions::compare(self.ecosystem, &self.current_version, latest)
+                .is_some_and(|order| order.is_ge())
+        })
+    }
+
+    /// Returns the highest severity among the vulnerabilities affecting
+    /// this dependency.
+    pub fn worst_severity(&self) -> Option<Severity> {
+        self.vulnerabilities.iter().filter_map(|v| v.severity).max()
+    }
 }
 
 fn rate_class(rate: u8) -> &'static str {
@@ -179,6 +674,106 @@ fn rate_class(rate: u8) -> &'static str {
     }
 }
 
+/// Windows offered on the dashboard, besides whichever is selected.
+const WINDOW_CHOICES: [Window; 4] = [
+    Window::Days(7),
+    Window::Days(30),
+    Window::Days(90),
+    Window::Runs(20),
+];
+
+/// Percentage of finished runs that succeeded, or `None` if no run has
+/// finished.
+pub fn history_rate(runs: &[RunRecord]) -> Option<u8> {
+    let (successes, finished) =
+        runs.iter()
+            .fold((0u32, 0u32), |(ok, total), run| match run.status.passed() {
+                Some(true) => (ok + 1, total + 1),
+                Some(false) => (ok, total + 1),
+                None => (ok, total),
+            });
+    (finished > 0).then(|| ((successes as f64 / finished as f64) * 100.0).round() as u8)
+}
+
+/// What a repository row shows of the repository's run history.
+struct RunOverview {
+    /// Success rate over the selected window, `None` if no run finished.
+    success_rate: Option<u8>,
+    /// The most recent runs, newest first.
+    recent: Vec<RunRecord>,
+}
+
+impl RunOverview {
+    /// Falls back to the rate reported by the source, without any runs.
+    fn from_source(repo: &RepoSummary) -> Self {
+        Self {
+            success_rate: Some(repo.success_rate),
+            recent: Vec::new(),
+        }
+    }
+}
+
+/// Reads each repository's success rate over `window` and its most recent
+/// runs from run history.
+///
+/// Without run history, or when it cannot be read, the rate reported by
+/// the source is used.
+async fn run_overviews(
+    state: &AppState,
+    repos: &[RepoSummary],
+    window: Window,
+    now: Timestamp,
+) -> Vec<RunOverview> {
+    let Some(history) = state.run_history() else {
+        return repos.iter().map(RunOverview::from_source).collect();
+    };
+    let in_window = window.filter(now);
+    let recent = RunFilter {
+        limit: Some(STRIP_RUNS),
+        ..RunFilter::default()
+    };
+    let mut overviews = Vec::with_capacity(repos.len());
+    for repo in repos {
+        let runs = async {
+            let windowed = history.runs(&repo.owner, &repo.repo, &in_window).await?;
+            let recent = history.runs(&repo.owner, &repo.repo, &recent).await?;
+            Ok::<_, HistoryError>((windowed, recent))
+        };
+        let overview = match runs.await {
+            Ok((windowed, recent)) => RunOverview {
+                success_rate: history_rate(&windowed),
+                recent,
+            },
+            Err(err) => {
+                tracing::warn!(error = %err, repo = %repo.full_name(), "failed to read run history");
+                RunOverview::from_source(repo)
+            }
+        };
+        overviews.push(overview);
+    }
+    overviews
+}
+
+/// Renders links selecting the window success rates are computed over.
+pub fn window_picker(selected: Window) -> Markup {
+    let mut choices = WINDOW_CHOICES.to_vec();
+    if !choices.contains(&selected) {
+        choices.push(selected);
+    }
+    html! {
+        nav class="window-picker" aria-label="Success rate window" {
+            span class="window-label" { "Success rate over the " strong { (selected.label()) } }
+            @for window in choices {
+                @if window == selected {
+                    a href={ "?window=" (window) } class="window-choice active" aria-current="page" { (window) }
+                } @else {
+                    a href={ "?window=" (window) } class="window-choice" { (window) }
+                }
+            }
+        }
+    }
+}
+
 fn deps_rate(up_to_date: u32, total: u32) -> u8 {
     if total == 0 {
         return 100;
@@ -186,12 +781,16 @@ fn deps_rate(up_to_date: u32, total: u32) -> u8 {
     ((up_to_date as f64 / total as f64) * 100.0).round() as u8
 }
 
-fn repo_row(repo: &RepoSummary) -> Markup {
+fn repo_row(repo: &RepoSummary, runs: &RunOverview, now: Timestamp) -> Markup {
     let dep_rate = deps_rate(repo.deps_up_to_date, repo.deps_total);
     let detail_id = format!("detail-{}-{}", repo.owner, repo.repo);
     let expand_url = repo_expand_url(&repo.owner, &repo.repo);
     html! {
-        tbody x-data=(expandable_directive(&expand_url, &detail_id)) {
+        tbody
+            x-data=(expandable_directive(&expand_url, &detail_id))
+            data-triggers=(repo.trigger_keys())
+            x-show="matches($el.dataset.triggers)"
+        {
             tr class="repo-row"
                x-bind:class="{ 'expanded': expanded }"
                x-on:click="toggle()"
@@ -201,29 +800,36 @@ fn repo_row(repo: &RepoSummary) -> Markup {
                 }
                 td class="repo-name" { (repo.full_name()) }
                 td class="success-rate" {
-                    span class=(rate_class(repo.success_rate)) {
-                        (repo.success_rate) "%"
+                    @if let Some(rate) = runs.success_rate {
+                        span class=(rate_class(rate)) { (rate) "%" }
+                    } @else {
+                        span class="rate-none" title="No finished runs in this window" { "—" }
                     }
                 }
                 td class="last-status" { (status_badge(repo.last_status)) }
-                td class="trigger-checks" {
-                    (check_icon(repo.runs_on_main()))
-                }
-                td class="trigger-checks" {
-                    (check_icon(repo.runs_on_pr()))
-                }
-                td class="trigger-checks" {
-                    (check_icon(repo.runs_scheduled()))
-                }
-                td class="trigger-checks" {
-                    (check_icon(repo.runs_manual()))
+                td class="recent-runs" {
+                    @if !runs.recent.is_empty() {
+                        (run_strip(&runs.recent))
+                    }
                 }
+                td class="triggers" { (trigger_icons(repo.triggers)) }
                 td class="deps-total" { (repo.deps_total) }
                 td class="deps-current" {
                     span class=(rate_class(dep_rate)) {
                         (repo.deps_up_to_date)
                     }
                 }
+                td class="deps-vulns" {
+                    @if repo.vulnerabilities == 0 {
+                        span class="vuln-count none" { "0" }
+                    } @else {
+                        @let class = repo.worst_severity().map_or("vuln-severity unknown", Severity::css_class);
+                        span class=(class) { (repo.vulnerabilities) }
+                    }
+                }
+                td class="fetched-at" title=(repo.fetched_at.round(Unit::Second).unwrap_or(repo.fetched_at)) {
+                    (format_age(repo.fetched_at, now))
+                }
             }
             tr class="repo-detail-row" x-show="expanded" x-cloak {
                 td colspan="10" class="repo-detail-cell" {
@@ -236,7 +842,14 @@ fn repo_row(repo: &RepoSummary) -> Markup {
 
 fn get_sorted_dependencies(deps: &[Dependency]) -> Vec<Dependency> {
     let mut sorted_deps = deps.to_vec();
-    sorted_deps.sort_by(|a, b| b.is_outdated.cmp(&a.is_outdated));
+    // Vulnerable first, most severe first, then furthest behind first.
+    sorted_deps.sort_by_key(|dep| {
+        (
+            Reverse((!dep.vulnerabilities.is_empty(), dep.worst_severity())),
+            Reverse(dep.gap()),
+            dep.kind,
+        )
+    });
     sorted_deps
 }
 
@@ -250,6 +863,9 @@ fn render_repo_detail(repo: &RepoSummary) -> Markup {
                     (PreEscaped("🔗")) " " (repo.github_url())
                 }
                 p class="repo-description" { (repo.description.as_str()) }
+                a href=(repo_page_url(&repo.owner, &repo.repo).path()) class="repo-trends-link" {
+                    "Trends"
+                }
             }
 
             @if !repo.pipelines.is_empty() {
@@ -258,25 +874,16 @@ fn render_repo_detail(repo: &RepoSummary) -> Markup {
                     table class="pipelines-table" {
                         thead {
                             tr {
+                                th {}
                                 th { "Name" }
                                 th { "Status" }
                                 th { "Duration" }
+                                th { "Triggers" }
                                 th { "Link" }
                             }
                         }
-                        tbody {
-                            @for pipeline in &repo.pipelines {
-                                tr {
-                                    td { (pipeline.name.as_str()) }
-                                    td { (status_badge(pipeline.status)) }
-                                    td class="pipeline-time" { (format_duration(pipeline.run_time)) }
-                                    td {
-                                        a href=(pipeline.github_url) target="_blank" class="pipeline-link" {
-                                            "View"
-                                        }
-                                    }
-                                }
-                            }
+                        @for pipeline in &repo.pipelines {
+                            (pipeline_rows(repo, pipeline))
                         }
                     }
                 }
@@ -301,6 +908,118 @@ fn render_repo_detail(repo: &RepoSummary) -> Markup {
     }
 }
 
+/// Renders the rows of a pipeline, which expand to list the jobs of its
+/// latest run when the run is known.
+fn pipeline_rows(repo: &RepoSummary, pipeline: &PipelineSummary) -> Markup {
+    let cells = html! {
+        td { (pipeline.name.as_str()) }
+        td { (status_badge(pipeline.status)) }
+        td class="pipeline-time" { (format_duration(pipeline.run_time)) }
+        td class="pipeline-triggers" { (trigger_labels(pipeline.triggers)) }
+        td {
+            a href=(pipeline.github_url) target="_blank" class="pipeline-link" "x-on:click.stop"="" {
+                "View"
+            }
+        }
+    };
+    let Some(run_id) = pipeline.run_id else {
+        return html! { tbody { tr { td class="expand-cell" {} (cells) } } };
+    };
+    let jobs_id = format!("jobs-{}-{}-{run_id}", repo.owner, repo.repo);
+    let jobs_url = run_jobs_url(&repo.owner, &repo.repo, run_id);
+    html! {
+        tbody x-data=(expandable_directive(&jobs_url, &jobs_id)) {
+            tr class="pipeline-row"
+               x-bind:class="{ 'expanded': expanded }"
+               x-on:click="toggle()"
+            {
+                td class="expand-cell" {
+                    span class="expand-arrow" { (PreEscaped("▶")) }
+                }
+                (cells)
+            }
+            tr class="jobs-row" x-show="expanded" x-cloak {
+                td colspan="6" {
+                    div id=(jobs_id) class="jobs-list" {}
+                }
+            }
+        }
+    }
+}
+
+/// Renders the jobs of a pipeline run with their steps.
+fn render_jobs(jobs: &[JobSummary]) -> Markup {
+    html! {
+        @if jobs.is_empty() {
+            div class="jobs-empty" { "No jobs found for this run" }
+        }
+        @for job in jobs {
+            div class="job" {
+                div class="job-header" {
+                    (status_badge(job.status))
+                    span class="job-name" { (job.name.as_str()) }
+                    span class="pipeline-time" { (format_duration(job.run_time)) }
+                    span class="job-runner" title=[job.runner_name.as_deref()] {
+                        @for label in &job.runner_labels {
+                            span class="runner-label" { (label.as_str()) }
+                        }
+                    }
+                    @if let Some(url) = &job.github_url {
+                        a href=(url) target="_blank" class="pipeline-link" { "View" }
+                    }
+                }
+                @if !job.steps.is_empty() {
+                    table class="steps-table" {
+                        tbody {
+                            @for step in &job.steps {
+                                tr {
+                                    td class="step-number" { (step.number) }
+                                    td { (step.name.as_str()) }
+                                    td { (status_badge(step.status)) }
+                                    td class="pipeline-time" { (format_duration(step.run_time)) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders a link to an advisory, labelled with its ID and severity.
+fn advisory_link(vulnerability: &Vulnerability) -> Markup {
+    let mut title = vulnerability.summary.clone();
+    if !vulnerability.aliases.is_empty() {
+        title = format!("{title} ({})", vulnerability.aliases.join(", "));
+    }
+    html! {
+        div class="advisory" {
+            @if let Some(severity) = vulnerability.severity {
+                span class=(severity.css_class()) { (severity.as_str()) }
+                " "
+            }
+            a href=(vulnerability.url()) target="_blank" title=(title) { (vulnerability.id.as_str()) }
+            @if let Some(fixed) = &vulnerability.fixed_version {
+                span class="advisory-fix" { " fixed in " (fixed.as_str()) }
+            }
+        }
+    }
+}
+
+/// Describes the commit a Go pseudo-version refers to.
+fn pseudo_version_title(dep: &Dependency) -> Option<String> {
+    if dep.ecosystem != Ecosystem::Go {
+        return None;
+    }
+    let pseudo = PseudoVersion::parse(&dep.current_version)?;
+    Some(format!(
+        "Commit {} from {}",
+        pseudo.revision,
+        pseudo.time.strftime("%Y-%m-%d")
+    ))
+}
+
 fn render_deps_list(repo: &RepoSummary) -> Markup {
     let deps = get_sorted_dependencies(&repo.dependencies);
 
@@ -309,22 +1028,46 @@ fn render_deps_list(repo: &RepoSummary) -> Markup {
             thead {
                 tr {
                     th { "Package" }
+                    th { "Kind" }
                     th { "Current" }
                     th { "Latest" }
                     th { "Status" }
+                    th { "Advisories" }
                 }
             }
             tbody {
                 @for dep in &deps {
-                    tr class=(if dep.is_outdated { "dep-outdated" } else { "dep-current" }) {
+                    @let gap = dep.gap();
+                    @let class = if !dep.vulnerabilities.is_empty() {
+                        "dep-vulnerable"
+                    } else if gap.is_some() {
+                        "dep-outdated"
+                    } else if dep.is_up_to_date() {
+                        "dep-current"
+                    } else {
+                        "dep-unknown"
+                    };
+                    tr class=(class) {
                         td { (dep.name.as_str()) }
-                        td class="version" { (dep.current_version.as_str()) }
-                        td class="version" { (dep.latest_version.as_str()) }
+                        td class="dep-kind" title=(dep.ecosystem.as_str()) { (dep.kind.as_str()) }
+                        td class="version" title=[pseudo_version_title(dep)] { (dep.current_version.as_str()) }
+                        td class="version" { (dep.latest_version.as_deref().unwrap_or("—")) }
                         td {
-                            @if dep.is_outdated {
-                                span class="dep-status outdated" { "Outdated" }
-                            } @else {
+                            @if let Some(gap) = gap {
+                                span class=(gap.css_class()) { (gap.as_str()) }
+                            } @else if dep.is_up_to_date() {
                                 span class="dep-status current" { "Current" }
+                            } @else if dep.latest_version.is_none() {
+                                span class="dep-status unknown" { "Unknown" }
+                            } @else {
+                                span class="dep-status unknown" title="The versions cannot be compared" {
+                                    "Unknown"
+                                }
+                            }
+                        }
+                        td class="dep-advisories" {
+                            @for vulnerability in &dep.vulnerabilities {
+                                (advisory_link(vulnerability))
                             }
                         }
                     }
@@ -334,50 +1077,145 @@ fn render_deps_list(repo: &RepoSummary) -> Markup {
     }
 }
 
+/// Renders a warning that GitHub is rate limiting requests until `until`.
+pub fn rate_limit_banner(until: Timestamp) -> Markup {
+    html! {
+        div class="rate-limit-banner" role="status" {
+            "Rate limited until " (until.strftime("%H:%M UTC"))
+        }
+    }
+}
+
+/// Renders an error loading repository data, logging it.
+pub fn render_source_error(err: &SourceError) -> Markup {
+    tracing::warn!(error = %err, "failed to load repository data");
+    match err {
+        SourceError::RateLimited { until } => rate_limit_banner(*until),
+        SourceError::Upstream(_) => {
+            html! { div class="error" { "Repository data is currently unavailable" } }
+        }
+    }
+}
+
 /// Returns the expanded detail HTML for a repository row.
-pub async fn repo_expand(Path((owner, repo)): Path<(String, String)>) -> impl IntoResponse {
-    match find_repo(&owner, &repo) {
-        Some(r) => render_repo_detail(&r),
-        None => html! { div class="error" { "Repository not found" } },
+pub async fn repo_expand(
+    State(state): State<AppState>,
+    Path((owner, repo)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.repos().find_repo(&owner, &repo).await {
+        Ok(Some(r)) => render_repo_detail(&r),
+        Ok(None) => html! { div class="error" { "Repository not found" } },
+        Err(err) => render_source_error(&err),
     }
 }
 
 /// Returns the dependencies list HTML for a repository.
-pub async fn repo_deps(Path((owner, repo)): Path<(String, String)>) -> impl IntoResponse {
-    match find_repo(&owner, &repo) {
-        Some(r) => render_deps_list(&r),
-        None => html! { div class="error" { "Repository not found" } },
+pub async fn repo_deps(
+    State(state): State<AppState>,
+    Path((owner, repo)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.repos().find_repo(&owner, &repo).await {
+        Ok(Some(r)) => render_deps_list(&r),
+        Ok(None) => html! { div class="error" { "Repository not found" } },
+        Err(err) => render_source_error(&err),
+    }
+}
+
+/// Returns the jobs list HTML for a pipeline run.
+pub async fn run_jobs(
+    State(state): State<AppState>,
+    Path((owner, repo, run_id)): Path<(String, String, u64)>,
+) -> impl IntoResponse {
+    match state.repos().list_jobs(&owner, &repo, run_id).await {
+        Ok(jobs) => render_jobs(&jobs),
+        Err(err) => render_source_error(&err),
+    }
+}
+
+/// Query parameters accepted by the dashboard page.
+#[derive(Debug, Deserialize)]
+pub struct DashboardQuery {
+    /// The window success rates are computed over, e.g. `7d` or `20runs`.
+    window: Option<String>,
+}
+
+impl DashboardQuery {
+    /// Returns the selected window, falling back to the default for
+    /// missing or unknown values.
+    pub fn window(&self) -> Window {
+        self.window
+            .as_deref()
+            .and_then(|w| w.parse().ok())
+            .unwrap_or_default()
     }
 }
 
 /// Renders the main dashboard page.
-pub async fn dashboard_page() -> impl IntoResponse {
-    let repos = mock_repos();
+pub async fn dashboard_page(
+    State(state): State<AppState>,
+    Query(query): Query<DashboardQuery>,
+    session: Option<Extension<Session>>,
+) -> impl IntoResponse {
+    let now = Timestamp::now();
+    let window = query.window();
+    let (repos, error) = match state.repos().list_repos().await {
+        Ok(repos) => (repos, None),
+        Err(err) => (Vec::new(), Some(render_source_error(&err))),
+    };
+    let overviews = run_overviews(&state, &repos, window, now).await;
+    let has_history = state.run_history().is_some();
+    // Cached data keeps being served while rate limited; say so.
+    let banner = state
+        .rate_limits()
+        .and_then(RateLimits::limited_until)
+        .filter(|_| error.is_none())
+        .map(rate_limit_banner);
 
     let body = html! {
         div class="dashboard-container" {
             header class="dashboard-header" {
                 h1 { "Hubdash" }
-                a href="/auth/signout" class="sign-out-link" { "Sign out" }
+                @if has_history {
+                    (window_picker(window))
+                }
+                div class="dashboard-user" {
+                    a href="/dashboard/status" class="status-link" { "Status" }
+                    @if let Some(Extension(session)) = &session {
+                        span class="signed-in-as" { "Signed in as " strong { (session.login) } }
+                    }
+                    form method="post" action="/auth/signout" class="sign-out-form" {
+                        button type="submit" class="sign-out-link" { "Sign out" }
+                    }
+                }
             }
             main class="dashboard-main" {
-                table class="repo-table" {
+                @if let Some(banner) = banner {
+                    (banner)
+                }
+                @if let Some(error) = error {
+                    (error)
+                }
+                table class="repo-table" x-data="triggerFilter" {
                     thead {
                         tr {
                             th class="expand-header" { }
                             th { "Repository" }
-                            th { "Success Rate" }
+                            @if has_history {
+                                th title={ "Finished runs that succeeded over the " (window.label()) } { "Success Rate" }
+                            } @else {
+                                th { "Success Rate" }
+                            }
                             th { "Last Run" }
-                            th title="Runs on main branch" { "Main" }
-                            th title="Runs on pull requests" { "PR" }
-                            th title="Scheduled runs" { "Sched" }
-                            th title="Manual trigger" { "Manual" }
+                            th title={ "Last " (STRIP_RUNS) " runs, oldest first" } { "Recent Runs" }
+                            (triggers_header())
                             th title="Total dependencies" { "Deps" }
                             th title="Dependencies up to date" { "Current" }
+                            th title="Known vulnerabilities in dependencies" { "Vulns" }
+                            th title="When the data was fetched from GitHub" { "Updated" }
                         }
                     }
-                    @for repo in &repos {
-                        (repo_row(repo))
+                    @for (repo, runs) in repos.iter().zip(&overviews) {
+                        (repo_row(repo, runs, now))
                     }
                 }
             }
@@ -391,3 +1229,110 @@ pub async fn dashboard_page() -> impl IntoResponse {
         body,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::history::tests::run;
+    use crate::{MemoryHistory, MockRepos, RepoSource, RunHistory};
+
+    struct BrokenHistory;
+
+    #[async_trait]
+    impl RunHistory for BrokenHistory {
+        async fn record(&self, _: &[RunRecord]) -> Result<(), HistoryError> {
+            Ok(())
+        }
+
+        async fn runs(
+            &self,
+            _: &str,
+            _: &str,
+            _: &RunFilter,
+        ) -> Result<Vec<RunRecord>, HistoryError> {
+            Err(HistoryError::Storage("disk on fire".into()))
+        }
+    }
+
+    #[test]
+    fn history_rate_leaves_out_unfinished_runs() {
+        let now = Timestamp::now();
+        let runs = |statuses: &[PipelineStatus]| -> Vec<RunRecord> {
+            (0..)
+                .zip(statuses)
+                .map(|(id, &status)| run(id, status, now))
+                .collect()
+        };
+        for (statuses, expected) in [
+            (&[][..], None),
+            (&[PipelineStatus::InProgress, PipelineStatus::Queued], None),
+            (
+                &[PipelineStatus::Success, PipelineStatus::InProgress],
+                Some(100),
+            ),
+            (
+                &[
+                    PipelineStatus::Success,
+                    PipelineStatus::Failure,
+                    PipelineStatus::TimedOut,
+                ],
+                Some(33),
+            ),
+            (
+                &[PipelineStatus::Success, PipelineStatus::Cancelled],
+                Some(100),
+            ),
+        ] {
+            assert_eq!(history_rate(&runs(statuses)), expected, "{statuses:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn run_overviews_compute_rates_over_the_window() {
+        let now = Timestamp::now();
+        let hours_ago = |hours| now - SignedDuration::from_hours(hours);
+        let history = MemoryHistory::from(vec![
+            run(1, PipelineStatus::InProgress, hours_ago(1)),
+            run(2, PipelineStatus::Success, hours_ago(2)),
+            run(3, PipelineStatus::Failure, hours_ago(3)),
+            run(4, PipelineStatus::Success, hours_ago(40 * 24)),
+        ]);
+        let state = AppState::new(MockRepos).with_history(Arc::new(history));
+        let repos = MockRepos.list_repos().await.unwrap();
+        assert_eq!(repos[0].full_name(), "example/hubdash");
+
+        for (window, expected) in [
+            (Window::Days(30), Some(50)),
+            (Window::Days(90), Some(67)),
+            (Window::Runs(2), Some(100)),
+            (Window::Runs(1), None),
+        ] {
+            let overviews = run_overviews(&state, &repos, window, now).await;
+            assert_eq!(overviews[0].success_rate, expected, "{window}");
+            assert_eq!(overviews[0].recent.len(), 4, "{window}");
+            // Repositories without recorded runs have no rate to show.
+            assert_eq!(overviews[1].success_rate, None, "{window}");
+        }
+    }
+
+    #[tokio::test]
+    async fn run_overviews_fall_back_to_the_source_rate() {
+        let now = Timestamp::now();
+        let repos = MockRepos.list_repos().await.unwrap();
+        let source_rates: Vec<_> = repos.iter().map(|r| Some(r.success_rate)).collect();
+
+        for state in [
+            AppState::new(MockRepos),
+            AppState::new(MockRepos).with_history(Arc::new(BrokenHistory)),
+        ] {
+            let overviews = run_overviews(&state, &repos, Window::default(), now).await;
+            let rates: Vec<_> = overviews.iter().map(|o| o.success_rate).collect();
+            assert_eq!(rates, source_rates);
+            assert!(overviews.iter().all(|o| o.recent.is_empty()));
+        }
+    }
+}
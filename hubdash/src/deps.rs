@@ -0,0 +1,438 @@
+//! Dependency extraction from the manifests and lockfiles in a repository.
+//!
+//! Each supported ecosystem has an extractor that reads the files it needs
+//! through [`RepoFiles`], so extraction works the same whether the files
+//! come from GitHub or anywhere else.
+
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::dashboard::{Dependency, DependencyKind, Ecosystem};
+
+mod cargo;
+mod go;
+mod npm;
+mod python;
+
+pub(crate) use python::normalize_name as normalize_pypi_name;
+
+/// Files at the root of a repository that the extractors read, for
+/// sources that can fetch them ahead of time in one request.
+pub(crate) const ROOT_FILES: [&str; 16] = [
+    "Cargo.toml",
+    "Cargo.lock",
+    "package.json",
+    "package-lock.json",
+    "pnpm-workspace.yaml",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "pyproject.toml",
+    "requirements.txt",
+    "requirements-dev.txt",
+    "uv.lock",
+    "poetry.lock",
+    "go.mod",
+    "go.sum",
+    "go.work",
+    "dagger.json",
+];
+
+/// Error returned when a repository's dependencies cannot be extracted.
+#[derive(Debug, thiserror::Error)]
+pub enum DepsError {
+    /// The repository's files could not be read.
+    #[error("could not read repository files: {0}")]
+    Fetch(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// A manifest or lockfile is malformed.
+    #[error("could not parse `{path}`: {message}")]
+    Parse {
+        /// Path of the file within the repository.
+        path: String,
+        /// What is wrong with it.
+        message: String,
+    },
+}
+
+impl DepsError {
+    fn parse(path: &str, message: impl ToString) -> Self {
+        Self::Parse {
+            path: path.to_owned(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// An entry in a repository directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    /// The entry's name within its directory.
+    pub name: String,
+    /// Whether the entry is a directory.
+    pub is_dir: bool,
+}
+
+/// Read access to the files of a repository.
+///
+/// Paths are relative to the repository root and use `/` separators; the
+/// root itself is the empty path.
+#[async_trait]
+pub trait RepoFiles: Send + Sync {
+    /// Returns the contents of the file at `path`, or `None` if there is
+    /// no such file.
+    async fn read(&self, path: &str) -> Result<Option<String>, DepsError>;
+
+    /// Returns the entries of the directory at `path`, or `None` if there
+    /// is no such directory.
+    async fn list(&self, path: &str) -> Result<Option<Vec<DirEntry>>, DepsError>;
+}
+
+/// Whether the directory listing `dir` has a file called `name`.
+fn has_file(dir: &[DirEntry], name: &str) -> bool {
+    dir.iter().any(|entry| !entry.is_dir && entry.name == name)
+}
+
+/// Joins a directory and a name into a repository path.
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{dir}/{name}")
+    }
+}
+
+/// Whether `name` matches a glob `pattern` of literal characters, `*`
+/// and `?`.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let mut chars = pattern.chars();
+    match chars.next() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len())
+            .filter(|&i| name.is_char_boundary(i))
+            .any(|i| glob_matches(chars.as_str(), &name[i..])),
+        Some(expected) => {
+            let mut rest = name.chars();
+            match rest.next() {
+                Some(c) if expected == '?' || expected == c => {
+                    glob_matches(chars.as_str(), rest.as_str())
+                }
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Expands a path pattern such as `crates/*` into the directories it
+/// matches, in order.
+async fn expand_dirs(files: &dyn RepoFiles, pattern: &str) -> Result<Vec<String>, DepsError> {
+    let mut dirs = vec![String::new()];
+    for segment in pattern.trim_matches('/').split('/') {
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        let mut next = Vec::new();
+        for dir in &dirs {
+            if !segment.contains(['*', '?']) {
+                next.push(join(dir, segment));
+                continue;
+            }
+            let mut entries = files.list(dir).await?.unwrap_or_default();
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            next.extend(
+                entries
+                    .into_iter()
+                    .filter(|entry| entry.is_dir && glob_matches(segment, &entry.name))
+                    .map(|entry| join(dir, &entry.name)),
+            );
+        }
+        dirs = next;
+    }
+    Ok(dirs)
+}
+
+/// Expands workspace member patterns into the directories they match,
+/// leaving out the root and any directory an `exclude` pattern matches.
+async fn member_dirs<'a>(
+    files: &dyn RepoFiles,
+    include: impl IntoIterator<Item = &'a str>,
+    exclude: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<String>, DepsError> {
+    let include: Vec<_> = include.into_iter().collect();
+    let exclude: Vec<_> = exclude.into_iter().collect();
+    let mut excluded = HashSet::new();
+    for pattern in exclude {
+        excluded.extend(expand_dirs(files, pattern).await?);
+    }
+    let mut dirs = Vec::new();
+    for pattern in include {
+        for dir in expand_dirs(files, pattern).await? {
+            if !dir.is_empty() && !excluded.contains(&dir) && !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    Ok(dirs)
+}
+
+/// Reads and parses the TOML file at `path`, or returns `None` if there
+/// is no such file.
+async fn read_toml<T: for<'de> Deserialize<'de>>(
+    files: &dyn RepoFiles,
+    path: &str,
+) -> Result<Option<T>, DepsError> {
+    let Some(text) = files.read(path).await? else {
+        return Ok(None);
+    };
+    toml::from_str(&text)
+        .map(Some)
+        .map_err(|err| DepsError::parse(path, err.message()))
+}
+
+/// Appends the `locked` packages that are not among the direct
+/// dependencies in `dependencies` as transitive ones.
+///
+/// A direct dependency whose version is not one of those locked for its
+/// name could not be matched to the lockfile, so every locked version of
+/// that name is left out rather than guessed at.
+fn push_transitive<'a>(
+    dependencies: &mut Vec<Dependency>,
+    ecosystem: Ecosystem,
+    locked: impl IntoIterator<Item = (&'a str, &'a str)>,
+) {
+    let mut locked: Vec<_> = locked.into_iter().collect();
+    locked.sort_unstable();
+    locked.dedup();
+
+    let mut versions: HashMap<&str, Vec<&str>> = HashMap::new();
+    for &(name, version) in &locked {
+        versions.entry(name).or_default().push(version);
+    }
+    let mut resolved = HashSet::new();
+    let mut unresolved = HashSet::new();
+    for dep in dependencies.iter().filter(|d| d.ecosystem == ecosystem) {
+        let name = dep.name.as_str();
+        let version = dep.current_version.as_str();
+        if versions.get(name).is_some_and(|v| v.contains(&version)) {
+            resolved.insert((name, version));
+        } else {
+            unresolved.insert(name);
+        }
+    }
+
+    let transitive: Vec<_> = locked
+        .into_iter()
+        .filter(|pair| !resolved.contains(pair) && !unresolved.contains(pair.0))
+        .map(|(name, version)| Dependency {
+            name: name.to_owned(),
+            ecosystem,
+            kind: DependencyKind::Transitive,
+            current_version: version.to_owned(),
+            latest_version: None,
+            vulnerabilities: Vec::new(),
+        })
+        .collect();
+    dependencies.extend(transitive);
+}
+
+/// Adds the dependencies `extracted` for `ecosystem` to `dependencies`,
+/// or logs why they could not be extracted.
+fn push_extracted(
+    dependencies: &mut Vec<Dependency>,
+    ecosystem: Ecosystem,
+    extracted: Result<Vec<Dependency>, DepsError>,
+) {
+    match extracted {
+        Ok(extracted) => dependencies.extend(extracted),
+        Err(err) => tracing::warn!(
+            error = %err,
+            ecosystem = ecosystem.as_str(),
+            "failed to extract dependencies"
+        ),
+    }
+}
+
+/// Extracts the dependencies of every supported ecosystem found at the
+/// root of a repository.
+///
+/// An ecosystem whose files are malformed or cannot be read is logged and
+/// left out, keeping the dependencies of the others; only failing to list
+/// the repository root is an error.
+pub async fn extract(files: &dyn RepoFiles) -> Result<Vec<Dependency>, DepsError> {
+    let root = files.list("").await?.unwrap_or_default();
+
+    let mut dependencies = Vec::new();
+    if has_file(&root, "Cargo.toml") {
+        push_extracted(
+            &mut dependencies,
+            Ecosystem::Cargo,
+            cargo::extract(files).await,
+        );
+    }
+    if has_file(&root, "package.json") {
+        push_extracted(
+            &mut dependencies,
+            Ecosystem::Npm,
+            npm::extract(files, &root).await,
+        );
+    }
+    if has_file(&root, "pyproject.toml") || root.iter().any(python::is_requirements) {
+        push_extracted(
+            &mut dependencies,
+            Ecosystem::PyPI,
+            python::extract(files, &root).await,
+        );
+    }
+    if ["go.mod", "go.work", "dagger.json"]
+        .iter()
+        .any(|name| has_file(&root, name))
+    {
+        push_extracted(
+            &mut dependencies,
+            Ecosystem::Go,
+            go::extract(files, &root).await,
+        );
+    }
+    Ok(dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Repository files held in memory, by path.
+    pub(super) struct MemoryFiles(HashMap<String, String>);
+
+    impl MemoryFiles {
+        pub(super) fn new(files: &[(&str, &str)]) -> Self {
+            Self(
+                files
+                    .iter()
+                    .map(|&(path, text)| (path.to_owned(), text.to_owned()))
+                    .collect(),
+            )
+        }
+    }
+
+    #[async_trait]
+    impl RepoFiles for MemoryFiles {
+        async fn read(&self, path: &str) -> Result<Option<String>, DepsError> {
+            Ok(self.0.get(path).cloned())
+        }
+
+        async fn list(&self, path: &str) -> Result<Option<Vec<DirEntry>>, DepsError> {
+            let prefix = if path.is_empty() {
+                String::new()
+            } else {
+                format!("{path}/")
+            };
+            let mut entries: Vec<_> = self
+                .0
+                .keys()
+                .filter_map(|file| file.strip_prefix(&prefix))
+                .map(|rest| match rest.split_once('/') {
+                    Some((dir, _)) => DirEntry {
+                        name: dir.to_owned(),
+                        is_dir: true,
+                    },
+                    None => DirEntry {
+                        name: rest.to_owned(),
+                        is_dir: false,
+                    },
+                })
+                .collect();
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            entries.dedup();
+            Ok((path.is_empty() || !entries.is_empty()).then_some(entries))
+        }
+    }
+
+    /// Returns the name, version and kind of each dependency.
+    pub(super) fn summary(dependencies: &[Dependency]) -> Vec<(&str, &str, DependencyKind)> {
+        dependencies
+            .iter()
+            .map(|d| (d.name.as_str(), d.current_version.as_str(), d.kind))
+            .collect()
+    }
+
+    #[test]
+    fn glob_matches_literals_stars_and_question_marks() {
+        for (pattern, name, expected) in [
+            ("crates", "crates", true),
+            ("crates", "crate", false),
+            ("*", "anything", true),
+            ("*", "", true),
+            ("app-*", "app-web", true),
+            ("app-*", "lib-web", false),
+            ("*-cli", "hubdash-cli", true),
+            ("v?", "v1", true),
+            ("v?", "v10", false),
+            ("?", "", false),
+        ] {
+            assert_eq!(glob_matches(pattern, name), expected, "{pattern} ~ {name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn member_dirs_expand_globs_and_leave_out_excluded_ones() {
+        let files = MemoryFiles::new(&[
+            ("crates/a/Cargo.toml", ""),
+            ("crates/b/Cargo.toml", ""),
+            ("crates/old/Cargo.toml", ""),
+            ("tools/cli/Cargo.toml", ""),
+        ]);
+        let dirs = member_dirs(&files, ["crates/*", "tools/cli", "."], ["crates/old"])
+            .await
+            .unwrap();
+        assert_eq!(dirs, ["crates/a", "crates/b", "tools/cli"]);
+    }
+
+    #[test]
+    fn push_transitive_skips_direct_dependencies_and_unresolved_names() {
+        let direct = |name: &str, version: &str| Dependency {
+            name: name.to_owned(),
+            ecosystem: Ecosystem::Cargo,
+            kind: DependencyKind::Direct,
+            current_version: version.to_owned(),
+            latest_version: None,
+            vulnerabilities: Vec::new(),
+        };
+        let mut dependencies = vec![direct("serde", "1.0.0"), direct("rand", "^0.8")];
+        push_transitive(
+            &mut dependencies,
+            Ecosystem::Cargo,
+            [
+                ("serde", "1.0.0"),
+                ("itoa", "1.0.1"),
+                ("itoa", "1.0.1"),
+                ("rand", "0.8.5"),
+                ("rand", "0.7.3"),
+            ],
+        );
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("serde", "1.0.0", DependencyKind::Direct),
+                ("rand", "^0.8", DependencyKind::Direct),
+                ("itoa", "1.0.1", DependencyKind::Transitive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn extract_keeps_other_ecosystems_when_one_is_malformed() {
+        let files = MemoryFiles::new(&[
+            ("Cargo.toml", "[dependencies]\nserde = \"=1.0.0\"\n"),
+            ("package.json", "{ not json"),
+            ("requirements.txt", "requests==2.31.0\n"),
+        ]);
+        let dependencies = extract(&files).await.unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("serde", "1.0.0", DependencyKind::Direct),
+                ("requests", "2.31.0", DependencyKind::Direct),
+            ]
+        );
+    }
+}
@@ -0,0 +1,444 @@
+//! Rust dependencies from `Cargo.toml` manifests and `Cargo.lock`.
+//!
+//! Direct dependencies are those declared by the root package or any
+//! workspace member, with `workspace = true` entries resolved against the
+//! root's `[workspace.dependencies]`. Everything else the lockfile pulls in
+//! from a registry or git is transitive. Without a lockfile, direct
+//! dependencies carry the requirement they are declared with instead of a
+//! version.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Deserialize;
+
+use super::{DepsError, RepoFiles, join, member_dirs, push_transitive, read_toml};
+use crate::dashboard::{Dependency, DependencyKind, Ecosystem};
+
+type DepTable = BTreeMap<String, DepSpec>;
+
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    package: Option<Package>,
+    workspace: Option<Workspace>,
+    #[serde(flatten)]
+    tables: DepTables,
+    #[serde(default)]
+    target: BTreeMap<String, DepTables>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DepTables {
+    #[serde(default)]
+    dependencies: DepTable,
+    #[serde(default, alias = "dev_dependencies")]
+    dev_dependencies: DepTable,
+    #[serde(default, alias = "build_dependencies")]
+    build_dependencies: DepTable,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Workspace {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    dependencies: DepTable,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DepSpec {
+    Version(String),
+    Detailed(DetailedDep),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DetailedDep {
+    version: Option<String>,
+    package: Option<String>,
+    path: Option<String>,
+    #[serde(default)]
+    workspace: bool,
+}
+
+impl DepSpec {
+    fn detailed(&self) -> Option<&DetailedDep> {
+        match self {
+            Self::Version(_) => None,
+            Self::Detailed(detailed) => Some(detailed),
+        }
+    }
+
+    fn version(&self) -> Option<&str> {
+        match self {
+            Self::Version(version) => Some(version),
+            Self::Detailed(detailed) => detailed.version.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockPackage {
+    name: String,
+    version: String,
+    /// Where the package comes from; `None` for packages in the workspace.
+    source: Option<String>,
+    /// Other packages this one depends on, as `name` or `name version`
+    /// when the name alone is ambiguous.
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// A dependency as declared in a manifest.
+struct Declared<'a> {
+    name: &'a str,
+    requirement: Option<&'a str>,
+    kind: DependencyKind,
+    local: bool,
+}
+
+impl Manifest {
+    fn package_name(&self) -> Option<&str> {
+        self.package.as_ref().map(|p| p.name.as_str())
+    }
+
+    /// Returns every dependency the manifest declares, including
+    /// target-specific ones.
+    fn declared<'a>(&'a self, inherited: &'a DepTable) -> Vec<Declared<'a>> {
+        let mut declared = Vec::new();
+        for tables in std::iter::once(&self.tables).chain(self.target.values()) {
+            for (kind, table) in [
+                (DependencyKind::Direct, &tables.dependencies),
+                (DependencyKind::Dev, &tables.dev_dependencies),
+                (DependencyKind::Build, &tables.build_dependencies),
+            ] {
+                for (key, spec) in table {
+                    let from_workspace = spec
+                        .detailed()
+                        .filter(|d| d.workspace)
+                        .and_then(|_| inherited.get(key));
+                    let resolved = from_workspace.unwrap_or(spec);
+                    let rename = |spec: &'a DepSpec| spec.detailed()?.package.as_deref();
+                    declared.push(Declared {
+                        name: rename(spec)
+                            .or_else(|| from_workspace.and_then(rename))
+                            .unwrap_or(key),
+                        requirement: resolved.version(),
+                        kind,
+                        local: resolved.detailed().is_some_and(|d| d.path.is_some()),
+                    });
+                }
+            }
+        }
+        declared
+    }
+}
+
+/// Returns the first two words of a lockfile dependency entry, the name
+/// and, if present, the version.
+fn split_lock_entry(entry: &str) -> (&str, Option<&str>) {
+    let mut words = entry.split_whitespace();
+    (words.next().unwrap_or_default(), words.next())
+}
+
+/// Returns what to show as the current version of a dependency that is not
+/// locked.
+///
+/// Only an exact requirement such as `=1.2.3` pins a version; anything
+/// else is shown as the requirement it is, with Cargo's implicit caret
+/// spelled out, so that `0.8.8` is not mistaken for a pinned version when
+/// `0.8.9` satisfies it just as well.
+fn unlocked_version(requirement: &str) -> String {
+    let requirement = requirement.trim();
+    if let Some(exact) = requirement.strip_prefix('=')
+        && !exact.contains(',')
+    {
+        return exact.trim().to_owned();
+    }
+    if requirement.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("^{requirement}");
+    }
+    requirement.to_owned()
+}
+
+struct DirectDep<'a> {
+    kind: DependencyKind,
+    requirement: Option<&'a str>,
+}
+
+/// Combines the declared dependencies of `manifests` with the versions
+/// locked in `lock`.
+///
+/// Members that lock a dependency at different versions each contribute
+/// their own version as a direct dependency.
+fn resolve(manifests: &[Manifest], lock: Option<&Lockfile>) -> Vec<Dependency> {
+    let no_inherited = DepTable::new();
+    let inherited = manifests
+        .first()
+        .and_then(|root| root.workspace.as_ref())
+        .map_or(&no_inherited, |w| &w.dependencies);
+    let members: HashSet<_> = manifests
+        .iter()
+        .filter_map(Manifest::package_name)
+        .collect();
+
+    let locked = lock.map_or(&[][..], |lock| lock.package.as_slice());
+    let mut external: HashMap<&str, Vec<&str>> = HashMap::new();
+    for package in locked.iter().filter(|p| p.source.is_some()) {
+        external
+            .entry(&package.name)
+            .or_default()
+            .push(&package.version);
+    }
+    // The version a member locked `name` at, when several are locked.
+    let locked_for = |member: &str, name: &str| {
+        locked
+            .iter()
+            .filter(|p| p.source.is_none() && p.name == member)
+            .flat_map(|p| p.dependencies.iter().map(|d| split_lock_entry(d)))
+            .find_map(|(dep, version)| (dep == name).then_some(version).flatten())
+    };
+
+    let mut direct: BTreeMap<(&str, Option<&str>), DirectDep> = BTreeMap::new();
+    for manifest in manifests {
+        for declared in manifest.declared(inherited) {
+            if declared.local || members.contains(declared.name) {
+                continue;
+            }
+            let version = match external.get(declared.name).map(Vec::as_slice) {
+                Some([only]) => Some(*only),
+                Some(_) => manifest
+                    .package_name()
+                    .and_then(|member| locked_for(member, declared.name)),
+                None => None,
+            };
+            let entry = direct.entry((declared.name, version)).or_insert(DirectDep {
+                kind: declared.kind,
+                requirement: declared.requirement,
+            });
+            entry.kind = entry.kind.min(declared.kind);
+            entry.requirement = entry.requirement.or(declared.requirement);
+        }
+    }
+
+    let mut dependencies: Vec<_> = direct
+        .into_iter()
+        .map(|((name, version), dep)| Dependency {
+            name: name.to_owned(),
+            ecosystem: Ecosystem::Cargo,
+            kind: dep.kind,
+            current_version: match version {
+                Some(version) => version.to_owned(),
+                None => unlocked_version(dep.requirement.unwrap_or("*")),
+            },
+            latest_version: None,
+            vulnerabilities: Vec::new(),
+        })
+        .collect();
+    push_transitive(
+        &mut dependencies,
+        Ecosystem::Cargo,
+        locked
+            .iter()
+            .filter(|p| p.source.is_some())
+            .map(|p| (p.name.as_str(), p.version.as_str())),
+    );
+    dependencies
+}
+
+/// Extracts the dependencies of the Rust package or workspace at the root
+/// of a repository.
+pub(super) async fn extract(files: &dyn RepoFiles) -> Result<Vec<Dependency>, DepsError> {
+    let Some(root) = read_toml::<Manifest>(files, "Cargo.toml").await? else {
+        return Ok(Vec::new());
+    };
+    let mut manifests = Vec::new();
+    if let Some(workspace) = &root.workspace {
+        let include = workspace.members.iter().map(String::as_str);
+        let exclude = workspace.exclude.iter().map(String::as_str);
+        for dir in member_dirs(files, include, exclude).await? {
+            let path = join(&dir, "Cargo.toml");
+            match read_toml::<Manifest>(files, &path).await? {
+                Some(member) => manifests.push(member),
+                None => tracing::debug!(path, "workspace member has no manifest"),
+            }
+        }
+    }
+    manifests.insert(0, root);
+
+    let lock = read_toml::<Lockfile>(files, "Cargo.lock").await?;
+    Ok(resolve(&manifests, lock.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deps::tests::{MemoryFiles, summary};
+
+    const WORKSPACE: &str = r#"
+[workspace]
+members = ["crates/*"]
+exclude = ["crates/scratch"]
+
+[workspace.dependencies]
+serde = { version = "1.0", features = ["derive"] }
+log = "0.4"
+"#;
+
+    const APP: &str = r#"
+[package]
+name = "app"
+
+[dependencies]
+core = { path = "../core" }
+serde = { workspace = true }
+rand = "0.7"
+json = { package = "serde_json", version = "1" }
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[dev-dependencies]
+log = { workspace = true }
+rand = "0.7"
+
+[build-dependencies]
+cc = "1"
+"#;
+
+    const CORE: &str = r#"
+[package]
+name = "core"
+
+[dependencies]
+rand = "0.8"
+app = "0.1"
+"#;
+
+    const LOCK: &str = r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["cc", "core", "libc", "log", "rand 0.7.3", "serde", "serde_json"]
+
+[[package]]
+name = "core"
+version = "0.1.0"
+dependencies = ["rand 0.8.5"]
+
+[[package]]
+name = "cc"
+version = "1.0.83"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "libc"
+version = "0.2.150"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "log"
+version = "0.4.20"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "rand"
+version = "0.8.5"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.193"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde_json"
+version = "1.0.108"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "itoa"
+version = "1.0.9"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    #[tokio::test]
+    async fn resolves_a_workspace_against_its_lockfile() {
+        let files = MemoryFiles::new(&[
+            ("Cargo.toml", WORKSPACE),
+            ("Cargo.lock", LOCK),
+            ("crates/app/Cargo.toml", APP),
+            ("crates/core/Cargo.toml", CORE),
+            (
+                "crates/scratch/Cargo.toml",
+                "[dependencies]\nleft-pad = \"1\"\n",
+            ),
+        ]);
+        let dependencies = extract(&files).await.unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("cc", "1.0.83", DependencyKind::Build),
+                ("libc", "0.2.150", DependencyKind::Direct),
+                ("log", "0.4.20", DependencyKind::Dev),
+                // Locked at a different version by each member.
+                ("rand", "0.7.3", DependencyKind::Direct),
+                ("rand", "0.8.5", DependencyKind::Direct),
+                ("serde", "1.0.193", DependencyKind::Direct),
+                ("serde_json", "1.0.108", DependencyKind::Direct),
+                ("itoa", "1.0.9", DependencyKind::Transitive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_requirements_without_a_lockfile() {
+        let files = MemoryFiles::new(&[(
+            "Cargo.toml",
+            r#"
+[dependencies]
+rand = "0.8.8"
+serde = "=1.0.193"
+log = ">=0.4, <0.5"
+tokio = "~1.40"
+git = { git = "https://example.com/git.git" }
+"#,
+        )]);
+        let dependencies = extract(&files).await.unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("git", "*", DependencyKind::Direct),
+                ("log", ">=0.4, <0.5", DependencyKind::Direct),
+                ("rand", "^0.8.8", DependencyKind::Direct),
+                ("serde", "1.0.193", DependencyKind::Direct),
+                ("tokio", "~1.40", DependencyKind::Direct),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_malformed_manifests() {
+        let files = MemoryFiles::new(&[("Cargo.toml", "[dependencies\n")]);
+        let err = extract(&files).await.unwrap_err();
+        assert!(matches!(err, DepsError::Parse { path, .. } if path == "Cargo.toml"));
+    }
+}
@@ -0,0 +1,467 @@
+//! Go dependencies from `go.mod` and `go.sum`.
+//!
+//! Modules are looked for at the root of a repository, in the directories
+//! a `go.work` file uses, and in the source directory of a Dagger module
+//! described by `dagger.json`. Requirements marked `// indirect` are
+//! transitive. Since Go 1.17 a module's `go.mod` lists every module its
+//! build needs; for older modules the rest are taken from `go.sum`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Deserialize;
+
+use super::{DepsError, DirEntry, RepoFiles, has_file, join};
+use crate::dashboard::{Dependency, DependencyKind, Ecosystem};
+use crate::versions::semver::Version;
+
+/// A directive in a `go.mod` or `go.work` file, with any block it is in
+/// unwrapped.
+struct Directive<'a> {
+    verb: &'a str,
+    args: Vec<&'a str>,
+    /// The text of a trailing `//` comment.
+    comment: &'a str,
+}
+
+/// Splits a `go.mod` or `go.work` file into directives, so that
+/// `require ( a v1 \n b v2 )` becomes two `require` directives.
+fn directives(text: &str) -> Vec<Directive<'_>> {
+    let mut directives = Vec::new();
+    let mut block = None;
+    for line in text.lines() {
+        let (code, comment) = line.split_once("//").unwrap_or((line, ""));
+        let mut args: Vec<_> = code
+            .split_whitespace()
+            .map(|arg| arg.trim_matches(['"', '`']))
+            .collect();
+        if let Some(verb) = block {
+            if args == [")"] {
+                block = None;
+            } else if !args.is_empty() {
+                directives.push(Directive {
+                    verb,
+                    args,
+                    comment: comment.trim(),
+                });
+            }
+            continue;
+        }
+        if args.is_empty() {
+            continue;
+        }
+        let verb = args.remove(0);
+        if args == ["("] {
+            block = Some(verb);
+            continue;
+        }
+        directives.push(Directive {
+            verb,
+            args,
+            comment: comment.trim(),
+        });
+    }
+    directives
+}
+
+struct Require<'a> {
+    path: &'a str,
+    version: &'a str,
+    indirect: bool,
+}
+
+/// What a `replace` directive substitutes for a module.
+enum Replacement<'a> {
+    Module {
+        path: &'a str,
+        version: &'a str,
+    },
+    /// A directory in the repository.
+    Local,
+}
+
+struct Replace<'a> {
+    path: &'a str,
+    /// The only version replaced, or `None` for all of them.
+    version: Option<&'a str>,
+    with: Replacement<'a>,
+}
+
+#[derive(Default)]
+struct GoMod<'a> {
+    module: Option<&'a str>,
+    go: Option<&'a str>,
+    requires: Vec<Require<'a>>,
+    replaces: Vec<Replace<'a>>,
+    excludes: HashSet<(&'a str, &'a str)>,
+}
+
+/// Whether a module path in a `replace` directive is a directory rather
+/// than a module.
+fn is_local_path(path: &str) -> bool {
+    path.starts_with("./") || path.starts_with("../") || path.starts_with('/')
+}
+
+fn parse_go_mod(text: &str) -> Result<GoMod<'_>, String> {
+    let mut go_mod = GoMod::default();
+    for directive in directives(text) {
+        let args = directive.args.as_slice();
+        match (directive.verb, args) {
+            ("module", [path]) => go_mod.module = Some(path),
+            ("go", [version]) => go_mod.go = Some(version),
+            ("require", [path, version]) => go_mod.requires.push(Require {
+                path,
+                version,
+                indirect: directive.comment.starts_with("indirect"),
+            }),
+            ("exclude", [path, version]) => {
+                go_mod.excludes.insert((path, version));
+            }
+            ("replace", _) => {
+                let arrow = args
+                    .iter()
+                    .position(|&arg| arg == "=>")
+                    .ok_or("`replace` without `=>`")?;
+                let (path, version) = match args[..arrow] {
+                    [path] => (path, None),
+                    [path, version] => (path, Some(version)),
+                    _ => return Err("malformed `replace`".to_owned()),
+                };
+                let with = match args[arrow + 1..] {
+                    [dir] if is_local_path(dir) => Replacement::Local,
+                    [path, version] => Replacement::Module { path, version },
+                    _ => return Err(format!("malformed `replace` of `{path}`")),
+                };
+                go_mod.replaces.push(Replace {
+                    path,
+                    version,
+                    with,
+                });
+            }
+            ("module" | "go" | "require" | "exclude", _) => {
+                return Err(format!("malformed `{}`", directive.verb));
+            }
+            // `toolchain`, `retract`, `tool` and `godebug` do not affect
+            // which modules are used.
+            _ => {}
+        }
+    }
+    Ok(go_mod)
+}
+
+impl GoMod<'_> {
+    /// Whether the `go` directive is at least 1.17, from which on `go.mod`
+    /// lists every module needed to build the main module.
+    fn lists_all_requirements(&self) -> bool {
+        let mut numbers = self
+            .go
+            .unwrap_or_default()
+            .split('.')
+            .map(|n| n.parse::<u64>().unwrap_or(0));
+        let major = numbers.next().unwrap_or(0);
+        let minor = numbers.next().unwrap_or(0);
+        (major, minor) >= (1, 17)
+    }
+
+    /// Returns the module actually used for `path` at `version`, or `None`
+    /// if it is replaced by a directory or excluded.
+    fn resolve<'a>(&'a self, path: &'a str, version: &'a str) -> Option<(&'a str, &'a str)> {
+        if self.excludes.contains(&(path, version)) {
+            return None;
+        }
+        let replace = self
+            .replaces
+            .iter()
+            .find(|r| r.path == path && r.version == Some(version))
+            .or_else(|| {
+                self.replaces
+                    .iter()
+                    .find(|r| r.path == path && r.version.is_none())
+            });
+        match replace.map(|r| &r.with) {
+            None => Some((path, version)),
+            Some(Replacement::Module { path, version }) => Some((path, version)),
+            Some(Replacement::Local) => None,
+        }
+    }
+}
+
+/// Returns the modules in a `go.sum` whose contents, not just `go.mod`,
+/// were verified, with the highest version of each.
+///
+/// Pseudo-versions are pre-releases of the version after the tag they
+/// follow, so they sort among tagged versions as they should.
+fn go_sum_modules(text: &str) -> BTreeMap<&str, (&str, Option<Version>)> {
+    let mut modules: BTreeMap<&str, (&str, Option<Version>)> = BTreeMap::new();
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        let (Some(path), Some(version)) = (words.next(), words.next()) else {
+            continue;
+        };
+        if version.ends_with("/go.mod") {
+            continue;
+        }
+        let parsed = version.parse::<Version>().ok();
+        let highest = modules.entry(path).or_insert((version, None));
+        if parsed > highest.1 {
+            *highest = (version, parsed);
+        }
+    }
+    modules
+}
+
+/// Returns the dependencies of a module from its `go.mod` and, for
+/// modules that need it, its `go.sum`.
+fn module_dependencies(go_mod: &GoMod<'_>, go_sum: Option<&str>) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    let mut push = |(path, version): (&str, &str), kind| {
+        dependencies.push(Dependency {
+            name: path.to_owned(),
+            ecosystem: Ecosystem::Go,
+            kind,
+            current_version: version.to_owned(),
+            latest_version: None,
+            vulnerabilities: Vec::new(),
+        });
+    };
+
+    for require in &go_mod.requires {
+        let kind = if require.indirect {
+            DependencyKind::Transitive
+        } else {
+            DependencyKind::Direct
+        };
+        if let Some(module) = go_mod.resolve(require.path, require.version) {
+            push(module, kind);
+        }
+    }
+    if let Some(go_sum) = go_sum {
+        let required: HashSet<_> = go_mod.requires.iter().map(|r| r.path).collect();
+        for (path, (version, _)) in go_sum_modules(go_sum) {
+            if required.contains(path) || go_mod.module == Some(path) {
+                continue;
+            }
+            if let Some(module) = go_mod.resolve(path, version) {
+                push(module, DependencyKind::Transitive);
+            }
+        }
+    }
+    dependencies
+}
+
+#[derive(Debug, Deserialize)]
+struct DaggerJson {
+    source: Option<String>,
+}
+
+/// Normalizes a directory from `go.work` or `dagger.json` into a
+/// repository path.
+fn normalize_dir(dir: &str) -> String {
+    let dir = dir.trim_start_matches("./").trim_end_matches('/');
+    if dir == "." { "" } else { dir }.to_owned()
+}
+
+/// Extracts the dependencies of the Go modules in a repository, whose
+/// root directory has the entries `root_dir`.
+pub(super) async fn extract(
+    files: &dyn RepoFiles,
+    root_dir: &[DirEntry],
+) -> Result<Vec<Dependency>, DepsError> {
+    let mut dirs = Vec::new();
+    if has_file(root_dir, "go.mod") {
+        dirs.push(String::new());
+    }
+    if has_file(root_dir, "go.work") {
+        let text = files.read("go.work").await?.unwrap_or_default();
+        for directive in directives(&text) {
+            if directive.verb == "use" {
+                dirs.extend(directive.args.iter().map(|dir| normalize_dir(dir)));
+            }
+        }
+    }
+    if has_file(root_dir, "dagger.json") {
+        let text = files.read("dagger.json").await?.unwrap_or_default();
+        let dagger: DaggerJson =
+            serde_json::from_str(&text).map_err(|err| DepsError::parse("dagger.json", err))?;
+        dirs.extend(dagger.source.as_deref().map(normalize_dir));
+    }
+
+    let mut seen = HashSet::new();
+    let mut dependencies = Vec::new();
+    for dir in dirs {
+        if !seen.insert(dir.clone()) {
+            continue;
+        }
+        let path = join(&dir, "go.mod");
+        let Some(text) = files.read(&path).await? else {
+            tracing::debug!(path, "Go module has no go.mod");
+            continue;
+        };
+        let go_mod = parse_go_mod(&text).map_err(|message| DepsError::parse(&path, message))?;
+        let go_sum = if go_mod.lists_all_requirements() {
+            None
+        } else {
+            files.read(&join(&dir, "go.sum")).await?
+        };
+        dependencies.extend(module_dependencies(&go_mod, go_sum.as_deref()));
+    }
+
+    // Modules in the same repository often share dependencies.
+    dependencies.sort_by(|a, b| {
+        (&a.name, &a.current_version, a.kind).cmp(&(&b.name, &b.current_version, b.kind))
+    });
+    dependencies.dedup_by(|a, b| a.name == b.name && a.current_version == b.current_version);
+    Ok(dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deps::tests::{MemoryFiles, summary};
+
+    const SERVICE: &str = r#"
+module example.com/svc
+
+go 1.21
+
+require (
+	github.com/a/a v1.2.0
+	github.com/b/b v0.3.0 // indirect
+	github.com/old/old v0.1.0
+	github.com/local/local v0.0.0
+	github.com/x/x v1.0.0
+)
+
+replace github.com/old/old => github.com/new/new v1.0.0
+
+replace github.com/local/local => ../local
+
+exclude github.com/x/x v1.0.0
+"#;
+
+    #[test]
+    fn directives_unwrap_blocks_and_keep_comments() {
+        let text =
+            "module \"example.com/m\"\n\nrequire (\n\tfoo v1.0.0 // indirect\n\n\tbar v2.0.0\n)\n";
+        let parsed: Vec<_> = directives(text)
+            .iter()
+            .map(|d| (d.verb, d.args.clone(), d.comment))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("module", vec!["example.com/m"], ""),
+                ("require", vec!["foo", "v1.0.0"], "indirect"),
+                ("require", vec!["bar", "v2.0.0"], ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_go_mod_rejects_malformed_directives() {
+        for text in [
+            "require foo",
+            "module a b",
+            "replace foo v1.0.0",
+            "replace foo => bar",
+            "replace a b c => d v1.0.0",
+        ] {
+            assert!(parse_go_mod(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn lists_all_requirements_from_go_1_17() {
+        for (go, expected) in [
+            (None, false),
+            (Some("1.16"), false),
+            (Some("1.17"), true),
+            (Some("1.21.5"), true),
+            (Some("2.0"), true),
+        ] {
+            let go_mod = GoMod {
+                go,
+                ..GoMod::default()
+            };
+            assert_eq!(go_mod.lists_all_requirements(), expected, "{go:?}");
+        }
+    }
+
+    #[test]
+    fn resolve_applies_excludes_and_the_most_specific_replace() {
+        let go_mod = parse_go_mod(
+            "replace foo => bar v2.0.0\nreplace foo v1.0.0 => baz v1.1.0\nreplace dir => ./dir\nexclude qux v0.1.0\n",
+        )
+        .unwrap();
+        for ((path, version), expected) in [
+            (("foo", "v1.0.0"), Some(("baz", "v1.1.0"))),
+            (("foo", "v1.5.0"), Some(("bar", "v2.0.0"))),
+            (("dir", "v0.0.0"), None),
+            (("qux", "v0.1.0"), None),
+            (("qux", "v0.2.0"), Some(("qux", "v0.2.0"))),
+        ] {
+            assert_eq!(go_mod.resolve(path, version), expected, "{path} {version}");
+        }
+    }
+
+    #[test]
+    fn go_sum_modules_keeps_the_highest_version_including_pseudo_versions() {
+        let go_sum = "\
+golang.org/x/sys v0.1.0 h1:a=
+golang.org/x/sys v0.1.0/go.mod h1:b=
+golang.org/x/sys v0.1.1-0.20220101000000-0123456789ab h1:c=
+golang.org/x/net v0.2.0/go.mod h1:d=
+golang.org/x/text v0.3.0 h1:e=
+golang.org/x/text v0.0.0-20170915032832-14c0d48ead0c h1:f=
+";
+        let modules: Vec<_> = go_sum_modules(go_sum)
+            .into_iter()
+            .map(|(path, (version, _))| (path, version))
+            .collect();
+        assert_eq!(
+            modules,
+            [
+                ("golang.org/x/sys", "v0.1.1-0.20220101000000-0123456789ab"),
+                ("golang.org/x/text", "v0.3.0"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn extract_follows_go_work_and_dagger_modules() {
+        let files = MemoryFiles::new(&[
+            ("go.work", "go 1.21\n\nuse (\n\t./svc\n\t./tools\n)\n"),
+            ("dagger.json", r#"{ "name": "ci", "source": "./ci" }"#),
+            ("svc/go.mod", SERVICE),
+            (
+                "tools/go.mod",
+                "module example.com/tools\n\ngo 1.16\n\nrequire github.com/a/a v1.2.0\n",
+            ),
+            (
+                "tools/go.sum",
+                "\
+example.com/tools v0.0.1 h1:a=
+github.com/a/a v1.2.0 h1:b=
+github.com/c/c v1.0.0 h1:c=
+github.com/c/c v1.1.0 h1:d=
+github.com/d/d v1.0.0/go.mod h1:e=
+",
+            ),
+            (
+                "ci/go.mod",
+                "module dagger/ci\n\ngo 1.22\n\nrequire dagger.io/dagger v0.9.0\n",
+            ),
+        ]);
+        let root_dir = files.list("").await.unwrap().unwrap();
+        let dependencies = extract(&files, &root_dir).await.unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("dagger.io/dagger", "v0.9.0", DependencyKind::Direct),
+                ("github.com/a/a", "v1.2.0", DependencyKind::Direct),
+                ("github.com/b/b", "v0.3.0", DependencyKind::Transitive),
+                ("github.com/c/c", "v1.1.0", DependencyKind::Transitive),
+                ("github.com/new/new", "v1.0.0", DependencyKind::Direct),
+            ]
+        );
+    }
+}
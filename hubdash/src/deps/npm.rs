@@ -0,0 +1,752 @@
+//! JavaScript dependencies from `package.json` and whichever of
+//! `package-lock.json`, `pnpm-lock.yaml` or `yarn.lock` is present.
+//!
+//! Direct dependencies are those declared by the root package or any
+//! workspace member, from `workspaces` in `package.json` or from
+//! `pnpm-workspace.yaml`. Everything else the lockfile installs from
+//! outside the repository is transitive.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+
+use super::{DepsError, DirEntry, RepoFiles, has_file, join, member_dirs, push_transitive};
+use crate::dashboard::{Dependency, DependencyKind, Ecosystem};
+
+type DepTable = BTreeMap<String, String>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageJson {
+    name: Option<String>,
+    #[serde(default)]
+    dependencies: DepTable,
+    #[serde(default)]
+    dev_dependencies: DepTable,
+    #[serde(default)]
+    optional_dependencies: DepTable,
+    workspaces: Option<Workspaces>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Workspaces {
+    Globs(Vec<String>),
+    Detailed {
+        #[serde(default)]
+        packages: Vec<String>,
+    },
+}
+
+impl Workspaces {
+    fn globs(self) -> Vec<String> {
+        match self {
+            Self::Globs(globs) | Self::Detailed { packages: globs } => globs,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PnpmWorkspace {
+    #[serde(default)]
+    packages: Vec<String>,
+}
+
+/// A package in the repository, with the directory it lives in.
+struct Member {
+    dir: String,
+    package: PackageJson,
+}
+
+/// Whether a dependency range points inside the repository rather than at
+/// a registry.
+fn is_local(range: &str) -> bool {
+    ["workspace:", "link:", "portal:", "file:"]
+        .iter()
+        .any(|prefix| range.starts_with(prefix))
+}
+
+/// Splits a `name@range` descriptor, where the name may be scoped.
+fn split_descriptor(descriptor: &str) -> Option<(&str, &str)> {
+    let at = descriptor.get(1..)?.find('@')? + 1;
+    Some((&descriptor[..at], &descriptor[at + 1..]))
+}
+
+/// Drops the peer dependency suffix pnpm appends to versions, as in
+/// `1.2.3(react@18.2.0)` or, in older lockfiles, `1.2.3_react@18.2.0`.
+fn strip_peers(version: &str) -> &str {
+    version.split(['(', '_']).next().unwrap_or(version)
+}
+
+/// What a lockfile says was installed.
+#[derive(Debug, Default)]
+struct Lock {
+    /// Every installed package from outside the repository, by name and
+    /// version.
+    packages: Vec<(String, String)>,
+    /// Versions installed for members' dependencies, by the member's
+    /// directory and the dependency's name.
+    installed: HashMap<(String, String), String>,
+    /// Versions that `name@range` descriptors resolved to.
+    descriptors: HashMap<String, String>,
+}
+
+impl Lock {
+    /// Returns the version installed for a dependency on `name` matching
+    /// `range` declared by the member in `dir`.
+    fn version(&self, dir: &str, name: &str, range: &str) -> Option<&str> {
+        let installed = |dir: &str| self.installed.get(&(dir.to_owned(), name.to_owned()));
+        let described = |range: &str| self.descriptors.get(&format!("{name}@{range}"));
+        if let Some(version) = installed(dir)
+            .or_else(|| installed(""))
+            .or_else(|| described(range))
+            .or_else(|| described(&format!("npm:{range}")))
+        {
+            return Some(version);
+        }
+        let mut versions = self
+            .packages
+            .iter()
+            .filter(|(locked, _)| locked == name)
+            .map(|(_, version)| version.as_str());
+        let first = versions.next()?;
+        versions.all(|version| version == first).then_some(first)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageLock {
+    /// Installed packages by path, from lockfile version 2 on.
+    #[serde(default)]
+    packages: BTreeMap<String, PackageLockEntry>,
+    /// The tree of installed packages in lockfile version 1.
+    #[serde(default)]
+    dependencies: BTreeMap<String, PackageLockDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageLockEntry {
+    /// The real name of a package installed under an alias.
+    name: Option<String>,
+    version: Option<String>,
+    /// Whether this is a symlink to a workspace member.
+    #[serde(default)]
+    link: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageLockDependency {
+    version: String,
+    #[serde(default)]
+    dependencies: BTreeMap<String, PackageLockDependency>,
+}
+
+impl PackageLock {
+    fn into_lock(self) -> Lock {
+        let mut lock = Lock::default();
+        if self.packages.is_empty() {
+            Self::walk_v1(&mut lock, self.dependencies, true);
+            return lock;
+        }
+        for (path, entry) in self.packages {
+            // Paths outside `node_modules` are the workspace members.
+            let Some((dir, alias)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            let (Some(version), false) = (entry.version, entry.link) else {
+                continue;
+            };
+            let name = entry.name.unwrap_or_else(|| alias.to_owned());
+            let dir = dir.trim_end_matches('/');
+            if !dir.contains("node_modules") {
+                lock.installed
+                    .insert((dir.to_owned(), name.clone()), version.clone());
+            }
+            lock.packages.push((name, version));
+        }
+        lock
+    }
+
+    fn walk_v1(lock: &mut Lock, dependencies: BTreeMap<String, PackageLockDependency>, top: bool) {
+        for (name, dep) in dependencies {
+            if is_local(&dep.version) {
+                continue;
+            }
+            if top {
+                lock.installed
+                    .insert((String::new(), name.clone()), dep.version.clone());
+            }
+            lock.packages.push((name, dep.version));
+            Self::walk_v1(lock, dep.dependencies, false);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PnpmLock {
+    /// The dependencies of each package in a workspace, by directory.
+    #[serde(default)]
+    importers: BTreeMap<String, PnpmImporter>,
+    /// The dependencies of the only package, before lockfile version 6
+    /// outside of workspaces.
+    #[serde(flatten)]
+    root: PnpmImporter,
+    /// Installed packages, keyed `name@version` or, in older lockfiles,
+    /// `/name/version`.
+    #[serde(default)]
+    packages: BTreeMap<String, IgnoredAny>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PnpmImporter {
+    #[serde(default)]
+    dependencies: BTreeMap<String, PnpmDependency>,
+    #[serde(default)]
+    dev_dependencies: BTreeMap<String, PnpmDependency>,
+    #[serde(default)]
+    optional_dependencies: BTreeMap<String, PnpmDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PnpmDependency {
+    Version(String),
+    Detailed { version: String },
+}
+
+impl PnpmDependency {
+    fn version(&self) -> &str {
+        match self {
+            Self::Version(version) | Self::Detailed { version } => version,
+        }
+    }
+}
+
+/// Splits a pnpm package key into the package's name and version.
+fn pnpm_package(key: &str) -> Option<(&str, &str)> {
+    let key = key.trim_start_matches('/');
+    // The name ends at the first `@` or `/` after its scope, if any; the
+    // peer suffix may hold either.
+    let scope = if key.starts_with('@') {
+        key.find('/')? + 1
+    } else {
+        0
+    };
+    let end = scope + key[scope..].find(['@', '/'])?;
+    Some((&key[..end], strip_peers(&key[end + 1..])))
+}
+
+impl PnpmLock {
+    fn into_lock(self) -> Lock {
+        let mut lock = Lock::default();
+        let importers = self
+            .importers
+            .into_iter()
+            .chain([(".".to_owned(), self.root)]);
+        for (dir, importer) in importers {
+            let dir = if dir == "." { "" } else { dir.as_str() };
+            let tables = [
+                importer.dependencies,
+                importer.dev_dependencies,
+                importer.optional_dependencies,
+            ];
+            for (name, dep) in tables.into_iter().flatten() {
+                if !is_local(dep.version()) {
+                    let version = strip_peers(dep.version()).to_owned();
+                    lock.installed.insert((dir.to_owned(), name), version);
+                }
+            }
+        }
+        lock.packages = self
+            .packages
+            .keys()
+            .filter_map(|key| pnpm_package(key))
+            .map(|(name, version)| (name.to_owned(), version.to_owned()))
+            .collect();
+        lock
+    }
+}
+
+/// Parses a `yarn.lock`, in either the classic format or the YAML one
+/// Yarn 2 and later write, both of which have a header line of
+/// descriptors followed by an indented `version` for each package.
+fn parse_yarn_lock(text: &str) -> Lock {
+    let mut lock = Lock::default();
+    let mut descriptors = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            descriptors = line
+                .trim_end_matches(':')
+                .split(", ")
+                .filter_map(|d| split_descriptor(d.trim_matches('"')))
+                .map(|(name, range)| (name.to_owned(), range.to_owned()))
+                .collect();
+            continue;
+        }
+        let Some(version) = line
+            .strip_prefix("  version")
+            .map(|rest| rest.trim_start_matches(':').trim().trim_matches('"'))
+        else {
+            continue;
+        };
+        if descriptors.iter().any(|(_, range)| is_local(range)) {
+            descriptors.clear();
+            continue;
+        }
+        for (name, range) in descriptors.drain(..) {
+            lock.descriptors
+                .insert(format!("{name}@{range}"), version.to_owned());
+            lock.packages.push((name, version.to_owned()));
+        }
+    }
+    lock
+}
+
+/// Combines the declared dependencies of `members` with the versions
+/// installed according to `lock`.
+fn resolve(members: &[Member], lock: &Lock) -> Vec<Dependency> {
+    let names: HashSet<_> = members
+        .iter()
+        .filter_map(|member| member.package.name.as_deref())
+        .collect();
+
+    let mut direct: BTreeMap<&str, (DependencyKind, &str)> = BTreeMap::new();
+    for member in members {
+        let package = &member.package;
+        for (kind, table) in [
+            (DependencyKind::Direct, &package.dependencies),
+            (DependencyKind::Direct, &package.optional_dependencies),
+            (DependencyKind::Dev, &package.dev_dependencies),
+        ] {
+            for (name, range) in table {
+                if is_local(range) || names.contains(name.as_str()) {
+                    continue;
+                }
+                let version = lock.version(&member.dir, name, range).unwrap_or(range);
+                let entry = direct.entry(name).or_insert((kind, version));
+                entry.0 = entry.0.min(kind);
+            }
+        }
+    }
+
+    let mut dependencies: Vec<_> = direct
+        .into_iter()
+        .map(|(name, (kind, version))| Dependency {
+            name: name.to_owned(),
+            ecosystem: Ecosystem::Npm,
+            kind,
+            current_version: version.to_owned(),
+            latest_version: None,
+            vulnerabilities: Vec::new(),
+        })
+        .collect();
+    push_transitive(
+        &mut dependencies,
+        Ecosystem::Npm,
+        lock.packages.iter().map(|(n, v)| (n.as_str(), v.as_str())),
+    );
+    dependencies
+}
+
+async fn read_json<T: for<'de> Deserialize<'de>>(
+    files: &dyn RepoFiles,
+    path: &str,
+) -> Result<Option<T>, DepsError> {
+    let Some(text) = files.read(path).await? else {
+        return Ok(None);
+    };
+    serde_json::from_str(&text)
+        .map(Some)
+        .map_err(|err| DepsError::parse(path, err))
+}
+
+async fn read_yaml<T: for<'de> Deserialize<'de>>(
+    files: &dyn RepoFiles,
+    path: &str,
+) -> Result<Option<T>, DepsError> {
+    let Some(text) = files.read(path).await? else {
+        return Ok(None);
+    };
+    serde_yaml_ng::from_str(&text)
+        .map(Some)
+        .map_err(|err| DepsError::parse(path, err))
+}
+
+/// Extracts the dependencies of the JavaScript package or workspace at
+/// the root of a repository, whose entries are `root_dir`.
+pub(super) async fn extract(
+    files: &dyn RepoFiles,
+    root_dir: &[DirEntry],
+) -> Result<Vec<Dependency>, DepsError> {
+    let has = |name: &str| has_file(root_dir, name);
+    let Some(mut root) = read_json::<PackageJson>(files, "package.json").await? else {
+        return Ok(Vec::new());
+    };
+    let mut patterns = root
+        .workspaces
+        .take()
+        .map(Workspaces::globs)
+        .unwrap_or_default();
+    if has("pnpm-workspace.yaml") {
+        let workspace = read_yaml::<PnpmWorkspace>(files, "pnpm-workspace.yaml").await?;
+        patterns.extend(workspace.unwrap_or_default().packages);
+    }
+
+    let mut members = vec![Member {
+        dir: String::new(),
+        package: root,
+    }];
+    let (exclude, include): (Vec<_>, Vec<_>) = patterns.iter().partition(|p| p.starts_with('!'));
+    let exclude = exclude.into_iter().map(|p| &p[1..]);
+    let include = include.into_iter().map(String::as_str);
+    for dir in member_dirs(files, include, exclude).await? {
+        let path = join(&dir, "package.json");
+        match read_json::<PackageJson>(files, &path).await? {
+            Some(package) => members.push(Member { dir, package }),
+            None => tracing::debug!(path, "workspace member has no package.json"),
+        }
+    }
+
+    let lock = if has("package-lock.json") {
+        read_json::<PackageLock>(files, "package-lock.json")
+            .await?
+            .map(PackageLock::into_lock)
+    } else if has("pnpm-lock.yaml") {
+        read_yaml::<PnpmLock>(files, "pnpm-lock.yaml")
+            .await?
+            .map(PnpmLock::into_lock)
+    } else if has("yarn.lock") {
+        files
+            .read("yarn.lock")
+            .await?
+            .as_deref()
+            .map(parse_yarn_lock)
+    } else {
+        None
+    };
+    Ok(resolve(&members, &lock.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deps::tests::{MemoryFiles, summary};
+
+    async fn extract_files(files: &[(&str, &str)]) -> Vec<Dependency> {
+        let files = MemoryFiles::new(files);
+        let root_dir = files.list("").await.unwrap().unwrap();
+        extract(&files, &root_dir).await.unwrap()
+    }
+
+    #[test]
+    fn split_descriptor_handles_scoped_names() {
+        for (descriptor, expected) in [
+            ("lodash@^4.17.0", Some(("lodash", "^4.17.0"))),
+            ("@types/node@^20", Some(("@types/node", "^20"))),
+            ("chalk@npm:^4.1.0", Some(("chalk", "npm:^4.1.0"))),
+            ("lodash", None),
+            ("@types/node", None),
+            ("", None),
+        ] {
+            assert_eq!(split_descriptor(descriptor), expected, "{descriptor}");
+        }
+    }
+
+    #[test]
+    fn pnpm_package_reads_both_key_formats_and_drops_peers() {
+        for (key, expected) in [
+            ("react@18.2.0", Some(("react", "18.2.0"))),
+            (
+                "react-dom@18.2.0(react@18.2.0)",
+                Some(("react-dom", "18.2.0")),
+            ),
+            ("@babel/core@7.23.0", Some(("@babel/core", "7.23.0"))),
+            ("/lodash/4.17.21", Some(("lodash", "4.17.21"))),
+            ("/@babel/core/7.23.0", Some(("@babel/core", "7.23.0"))),
+            (
+                "/styled-components/5.3.11_react@18.2.0",
+                Some(("styled-components", "5.3.11")),
+            ),
+            ("lodash", None),
+        ] {
+            assert_eq!(pnpm_package(key), expected, "{key}");
+        }
+    }
+
+    #[tokio::test]
+    async fn package_lock_resolves_workspace_members() {
+        let dependencies = extract_files(&[
+            (
+                "package.json",
+                r#"{
+                    "name": "root",
+                    "workspaces": ["packages/*"],
+                    "dependencies": { "react": "^18.2.0" },
+                    "devDependencies": { "typescript": "~5.3.0", "react": "^18.2.0" }
+                }"#,
+            ),
+            (
+                "packages/web/package.json",
+                r#"{
+                    "name": "web",
+                    "dependencies": { "lodash": "^3.10.0", "root": "*", "ui": "file:../ui" }
+                }"#,
+            ),
+            (
+                "package-lock.json",
+                r#"{
+                    "lockfileVersion": 3,
+                    "packages": {
+                        "": { "name": "root" },
+                        "packages/web": { "name": "web", "version": "1.0.0" },
+                        "node_modules/web": { "resolved": "packages/web", "link": true },
+                        "node_modules/react": { "version": "18.2.0" },
+                        "node_modules/loose-envify": { "version": "1.4.0" },
+                        "node_modules/lodash": { "version": "4.17.21" },
+                        "node_modules/typescript": { "version": "5.3.3" },
+                        "packages/web/node_modules/lodash": { "version": "3.10.1" }
+                    }
+                }"#,
+            ),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("lodash", "3.10.1", DependencyKind::Direct),
+                ("react", "18.2.0", DependencyKind::Direct),
+                ("typescript", "5.3.3", DependencyKind::Dev),
+                ("lodash", "4.17.21", DependencyKind::Transitive),
+                ("loose-envify", "1.4.0", DependencyKind::Transitive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn package_lock_version_1_walks_the_dependency_tree() {
+        let dependencies = extract_files(&[
+            (
+                "package.json",
+                r#"{
+                    "dependencies": { "express": "^4.18.0", "local": "file:./local" },
+                    "devDependencies": { "jest": "^29.0.0" }
+                }"#,
+            ),
+            (
+                "package-lock.json",
+                r#"{
+                    "lockfileVersion": 1,
+                    "dependencies": {
+                        "debug": { "version": "4.3.4" },
+                        "express": {
+                            "version": "4.18.2",
+                            "dependencies": { "debug": { "version": "2.6.9" } }
+                        },
+                        "jest": { "version": "29.7.0" },
+                        "local": { "version": "file:local" }
+                    }
+                }"#,
+            ),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("express", "4.18.2", DependencyKind::Direct),
+                ("jest", "29.7.0", DependencyKind::Dev),
+                ("debug", "2.6.9", DependencyKind::Transitive),
+                ("debug", "4.3.4", DependencyKind::Transitive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn pnpm_lock_resolves_importers() {
+        let dependencies = extract_files(&[
+            (
+                "package.json",
+                r#"{ "name": "root", "devDependencies": { "typescript": "^5.3.0" } }"#,
+            ),
+            ("pnpm-workspace.yaml", "packages:\n  - apps/*\n"),
+            (
+                "apps/web/package.json",
+                r#"{
+                    "name": "web",
+                    "dependencies": {
+                        "react": "^18.2.0",
+                        "react-dom": "^18.2.0",
+                        "shared": "workspace:*"
+                    }
+                }"#,
+            ),
+            (
+                "pnpm-lock.yaml",
+                "\
+lockfileVersion: '9.0'
+importers:
+  .:
+    devDependencies:
+      typescript:
+        specifier: ^5.3.0
+        version: 5.3.3
+  apps/web:
+    dependencies:
+      react:
+        specifier: ^18.2.0
+        version: 18.2.0
+      react-dom:
+        specifier: ^18.2.0
+        version: 18.2.0(react@18.2.0)
+      shared:
+        specifier: workspace:*
+        version: link:../../packages/shared
+packages:
+  loose-envify@1.4.0: {}
+  react@18.2.0: {}
+  react-dom@18.2.0: {}
+  typescript@5.3.3: {}
+",
+            ),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("react", "18.2.0", DependencyKind::Direct),
+                ("react-dom", "18.2.0", DependencyKind::Direct),
+                ("typescript", "5.3.3", DependencyKind::Dev),
+                ("loose-envify", "1.4.0", DependencyKind::Transitive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn pnpm_lock_before_version_6_has_a_single_root() {
+        let dependencies = extract_files(&[
+            (
+                "package.json",
+                r#"{
+                    "dependencies": { "lodash": "^4.17.0" },
+                    "devDependencies": { "styled-components": "^5.3.0" }
+                }"#,
+            ),
+            (
+                "pnpm-lock.yaml",
+                "\
+lockfileVersion: 5.4
+specifiers:
+  lodash: ^4.17.0
+  styled-components: ^5.3.0
+dependencies:
+  lodash: 4.17.21
+devDependencies:
+  styled-components: 5.3.11_react@18.2.0
+packages:
+  /@babel/core/7.23.0:
+    dev: true
+  /lodash/4.17.21:
+    dev: false
+  /styled-components/5.3.11_react@18.2.0:
+    dev: true
+",
+            ),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("lodash", "4.17.21", DependencyKind::Direct),
+                ("styled-components", "5.3.11", DependencyKind::Dev),
+                ("@babel/core", "7.23.0", DependencyKind::Transitive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn yarn_classic_lock_resolves_descriptors() {
+        let dependencies = extract_files(&[
+            (
+                "package.json",
+                r#"{
+                    "dependencies": {
+                        "@babel/code-frame": "^7.22.13",
+                        "chalk": "^4.1.0",
+                        "ui": "link:./ui"
+                    }
+                }"#,
+            ),
+            (
+                "yarn.lock",
+                r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@babel/code-frame@^7.0.0", "@babel/code-frame@^7.22.13":
+  version "7.22.13"
+  resolved "https://registry.yarnpkg.com/@babel/code-frame/-/code-frame-7.22.13.tgz"
+
+chalk@^2.4.2:
+  version "2.4.2"
+
+chalk@^4.1.0:
+  version "4.1.2"
+  dependencies:
+    ansi-styles "^4.1.0"
+
+"ui@link:./ui":
+  version "0.0.0"
+"#,
+            ),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("@babel/code-frame", "7.22.13", DependencyKind::Direct),
+                ("chalk", "4.1.2", DependencyKind::Direct),
+                ("chalk", "2.4.2", DependencyKind::Transitive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn yarn_berry_lock_resolves_npm_descriptors_and_keeps_unlocked_ranges() {
+        let dependencies = extract_files(&[
+            (
+                "package.json",
+                r#"{
+                    "dependencies": { "chalk": "^4.1.0" },
+                    "devDependencies": { "prettier": "^3.0.0" }
+                }"#,
+            ),
+            (
+                "yarn.lock",
+                r#"__metadata:
+  version: 6
+  cacheKey: 8
+
+"chalk@npm:^4.1.0":
+  version: 4.1.2
+  resolution: "chalk@npm:4.1.2"
+
+"web@workspace:packages/web":
+  version: 0.0.0-use.local
+  resolution: "web@workspace:packages/web"
+"#,
+            ),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("chalk", "4.1.2", DependencyKind::Direct),
+                ("prettier", "^3.0.0", DependencyKind::Dev),
+            ]
+        );
+    }
+}
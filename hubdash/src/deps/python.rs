@@ -0,0 +1,697 @@
+//! Python dependencies from `requirements*.txt`, `pyproject.toml` and
+//! whichever of `uv.lock` or `poetry.lock` is present.
+//!
+//! `pyproject.toml` may declare dependencies the PEP 621 way, under
+//! `[project]`, or Poetry's, under `[tool.poetry]`; members of a uv
+//! workspace are followed too. Package names are normalized as PEP 503
+//! describes, so `Typing_Extensions` and `typing-extensions` are the same
+//! package.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Deserialize;
+
+use super::{
+    DepsError, DirEntry, RepoFiles, has_file, join, member_dirs, push_transitive, read_toml,
+};
+use crate::dashboard::{Dependency, DependencyKind, Ecosystem};
+use crate::versions::pep440::{Specifiers, Version};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyProject {
+    project: Option<Project>,
+    /// PEP 735 groups of development dependencies, whose entries are
+    /// requirements or `{ include-group = "..." }` tables.
+    #[serde(default)]
+    dependency_groups: BTreeMap<String, Vec<toml::Value>>,
+    #[serde(default)]
+    tool: Tools,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Project {
+    name: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default)]
+    optional_dependencies: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Tools {
+    poetry: Option<Poetry>,
+    uv: Option<Uv>,
+}
+
+type PoetryTable = BTreeMap<String, PoetrySpec>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Poetry {
+    name: Option<String>,
+    #[serde(default)]
+    dependencies: PoetryTable,
+    /// Development dependencies before Poetry 1.2 introduced groups.
+    #[serde(default)]
+    dev_dependencies: PoetryTable,
+    #[serde(default)]
+    group: BTreeMap<String, PoetryGroup>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoetryGroup {
+    #[serde(default)]
+    dependencies: PoetryTable,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PoetrySpec {
+    Version(String),
+    Detailed(PoetryDetailed),
+    /// Different constraints for different environments.
+    Multiple(Vec<PoetryDetailed>),
+}
+
+#[derive(Debug, Deserialize)]
+struct PoetryDetailed {
+    version: Option<String>,
+    path: Option<String>,
+}
+
+impl PoetrySpec {
+    fn detailed(&self) -> Option<&PoetryDetailed> {
+        match self {
+            Self::Version(_) => None,
+            Self::Detailed(detailed) => Some(detailed),
+            Self::Multiple(alternatives) => alternatives.first(),
+        }
+    }
+
+    fn version(&self) -> Option<&str> {
+        match self {
+            Self::Version(version) => Some(version),
+            _ => self.detailed()?.version.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Uv {
+    #[serde(default)]
+    dev_dependencies: Vec<String>,
+    /// Where packages come from, when not the index; a table or, for
+    /// different environments, an array of tables.
+    #[serde(default)]
+    sources: BTreeMap<String, toml::Value>,
+    workspace: Option<UvWorkspace>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UvWorkspace {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockPackage {
+    name: String,
+    version: Option<String>,
+    /// Where the package comes from, as an inline table in `uv.lock` and
+    /// a `type`d table in `poetry.lock`.
+    source: Option<toml::Value>,
+}
+
+/// Whether a uv or Poetry source points inside the repository.
+fn is_local_source(source: &toml::Value) -> bool {
+    match source {
+        toml::Value::Table(table) => {
+            ["editable", "virtual", "directory", "path"]
+                .iter()
+                .any(|key| table.contains_key(*key))
+                || table.get("workspace").and_then(toml::Value::as_bool) == Some(true)
+                || matches!(
+                    table.get("type").and_then(toml::Value::as_str),
+                    Some("directory" | "file")
+                )
+        }
+        toml::Value::Array(alternatives) => alternatives.iter().any(is_local_source),
+        _ => false,
+    }
+}
+
+/// Normalizes a package name as PEP 503 describes: lowercase, with each
+/// run of `-`, `_` and `.` replaced by a single `-`.
+pub(crate) fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+/// Normalizes a version if it is a valid PEP 440 one, so that versions
+/// written differently compare equal.
+fn normalize_version(version: &str) -> String {
+    version
+        .parse::<Version>()
+        .map_or_else(|_| version.to_owned(), |version| version.to_string())
+}
+
+/// A dependency as declared in a manifest.
+struct Declared {
+    name: String,
+    requirement: Option<String>,
+    kind: DependencyKind,
+}
+
+/// Parses a PEP 508 requirement such as
+/// `pandas[excel] >= 2.1; python_version >= "3.9"` into the normalized
+/// package name and its version specifiers, if any.
+///
+/// Returns `None` for anything that does not start with a package name,
+/// such as a bare URL.
+fn parse_requirement(requirement: &str) -> Option<(String, Option<String>)> {
+    let requirement = requirement.split(';').next()?.trim();
+    let end = requirement
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(requirement.len());
+    let name = &requirement[..end];
+    let mut rest = requirement[end..].trim_start();
+    if rest.starts_with('[') {
+        rest = rest.split_once(']')?.1.trim_start();
+    }
+    if name.is_empty()
+        || !(rest.is_empty() || rest.starts_with(['@', '(', '<', '>', '=', '!', '~']))
+    {
+        return None;
+    }
+    // A direct reference, `name @ url`, has no version to speak of.
+    let specifiers: String = if rest.starts_with('@') {
+        String::new()
+    } else {
+        rest.trim_start_matches('(')
+            .trim_end_matches(')')
+            .split_whitespace()
+            .collect()
+    };
+    Some((
+        normalize_name(name),
+        (!specifiers.is_empty()).then_some(specifiers),
+    ))
+}
+
+/// Parses the requirements in a pip requirements file, skipping options,
+/// editable installs and paths.
+fn parse_requirements(text: &str) -> Vec<(String, Option<String>)> {
+    let text = text.replace("\\\r\n", " ").replace("\\\n", " ");
+    text.lines()
+        .filter_map(|line| {
+            let line = line.split(" #").next()?.trim();
+            if line.starts_with(['#', '-', '.', '/']) {
+                return None;
+            }
+            // Per-requirement options such as `--hash`.
+            parse_requirement(line.split(" --").next()?)
+        })
+        .collect()
+}
+
+/// Whether a file at the root of a repository is a pip requirements file.
+pub(super) fn is_requirements(entry: &DirEntry) -> bool {
+    !entry.is_dir && entry.name.starts_with("requirements") && entry.name.ends_with(".txt")
+}
+
+impl PyProject {
+    fn name(&self) -> Option<&str> {
+        let poetry = self.tool.poetry.as_ref();
+        self.project
+            .as_ref()
+            .and_then(|project| project.name.as_deref())
+            .or_else(|| poetry?.name.as_deref())
+    }
+
+    /// Returns the names of packages this project takes from inside the
+    /// repository.
+    fn local_packages(&self) -> impl Iterator<Item = String> + '_ {
+        let uv = self.tool.uv.iter().flat_map(|uv| &uv.sources);
+        let uv = uv
+            .filter(|(_, source)| is_local_source(source))
+            .map(|(name, _)| name);
+        let poetry = self.tool.poetry.iter().flat_map(|poetry| {
+            let groups = poetry.group.values().map(|group| &group.dependencies);
+            [&poetry.dependencies, &poetry.dev_dependencies]
+                .into_iter()
+                .chain(groups)
+                .flatten()
+        });
+        let poetry = poetry
+            .filter(|(_, spec)| spec.detailed().is_some_and(|d| d.path.is_some()))
+            .map(|(name, _)| name);
+        uv.chain(poetry).map(|name| normalize_name(name))
+    }
+
+    /// Returns every dependency the project declares.
+    fn declared(&self) -> Vec<Declared> {
+        let mut requirements: Vec<(DependencyKind, &str)> = Vec::new();
+        if let Some(project) = &self.project {
+            let optional = project.optional_dependencies.values().flatten();
+            for requirement in project.dependencies.iter().chain(optional) {
+                requirements.push((DependencyKind::Direct, requirement));
+            }
+        }
+        let groups = self.dependency_groups.values().flatten();
+        for requirement in groups.filter_map(toml::Value::as_str) {
+            requirements.push((DependencyKind::Dev, requirement));
+        }
+        for requirement in self.tool.uv.iter().flat_map(|uv| &uv.dev_dependencies) {
+            requirements.push((DependencyKind::Dev, requirement));
+        }
+
+        let mut declared: Vec<_> = requirements
+            .into_iter()
+            .filter_map(|(kind, requirement)| {
+                let (name, requirement) = parse_requirement(requirement)?;
+                Some(Declared {
+                    name,
+                    requirement,
+                    kind,
+                })
+            })
+            .collect();
+        if let Some(poetry) = &self.tool.poetry {
+            let groups = poetry.group.values().map(|group| &group.dependencies);
+            let tables = [
+                (DependencyKind::Direct, &poetry.dependencies),
+                (DependencyKind::Dev, &poetry.dev_dependencies),
+            ]
+            .into_iter()
+            .chain(groups.map(|table| (DependencyKind::Dev, table)));
+            for (kind, table) in tables {
+                for (name, spec) in table {
+                    // Poetry declares the supported Python versions as a
+                    // dependency too.
+                    if name == "python" {
+                        continue;
+                    }
+                    declared.push(Declared {
+                        name: normalize_name(name),
+                        requirement: spec.version().map(str::to_owned),
+                        kind,
+                    });
+                }
+            }
+        }
+        declared
+    }
+}
+
+/// Picks the locked version of a package among `locked`, using the
+/// declared `requirement` to choose when there are several.
+fn locked_version(locked: &[&str], requirement: Option<&str>) -> Option<String> {
+    let specifiers = requirement.and_then(|r| r.parse::<Specifiers>().ok());
+    match locked {
+        [] => specifiers?.pinned().map(ToString::to_string),
+        [only] => Some((*only).to_owned()),
+        several => several
+            .iter()
+            .find(|version| {
+                let version = version.parse::<Version>();
+                specifiers
+                    .as_ref()
+                    .zip(version.ok())
+                    .is_some_and(|(specifiers, version)| specifiers.contains(&version))
+            })
+            .map(|version| (*version).to_owned()),
+    }
+}
+
+/// Combines `declared` dependencies with the packages in `lock`, leaving
+/// out the `local` ones.
+fn resolve(
+    declared: Vec<Declared>,
+    local: &HashSet<String>,
+    lock: Option<&Lockfile>,
+) -> Vec<Dependency> {
+    let mut direct: BTreeMap<String, (DependencyKind, Option<String>)> = BTreeMap::new();
+    for dep in declared {
+        if local.contains(&dep.name) {
+            continue;
+        }
+        let entry = direct.entry(dep.name).or_insert((dep.kind, None));
+        entry.0 = entry.0.min(dep.kind);
+        entry.1 = entry.1.take().or(dep.requirement);
+    }
+
+    let locked: Vec<_> = lock
+        .map_or(&[][..], |lock| lock.package.as_slice())
+        .iter()
+        .filter(|package| !package.source.as_ref().is_some_and(is_local_source))
+        .filter(|package| !local.contains(&normalize_name(&package.name)))
+        .filter_map(|package| {
+            let version = package.version.as_deref()?;
+            Some((normalize_name(&package.name), normalize_version(version)))
+        })
+        .collect();
+
+    let mut dependencies: Vec<_> = direct
+        .into_iter()
+        .map(|(name, (kind, requirement))| {
+            let versions: Vec<_> = locked
+                .iter()
+                .filter(|(locked, _)| *locked == name)
+                .map(|(_, version)| version.as_str())
+                .collect();
+            let version = locked_version(&versions, requirement.as_deref())
+                .or(requirement)
+                .unwrap_or_else(|| "*".to_owned());
+            Dependency {
+                name,
+                ecosystem: Ecosystem::PyPI,
+                kind,
+                current_version: version,
+                latest_version: None,
+                vulnerabilities: Vec::new(),
+            }
+        })
+        .collect();
+    push_transitive(
+        &mut dependencies,
+        Ecosystem::PyPI,
+        locked
+            .iter()
+            .map(|(name, version)| (name.as_str(), version.as_str())),
+    );
+    dependencies
+}
+
+/// Extracts the dependencies of the Python project at the root of a
+/// repository, whose entries are `root_dir`.
+pub(super) async fn extract(
+    files: &dyn RepoFiles,
+    root_dir: &[DirEntry],
+) -> Result<Vec<Dependency>, DepsError> {
+    let mut declared = Vec::new();
+    let mut requirement_files: Vec<_> = root_dir.iter().filter(|e| is_requirements(e)).collect();
+    requirement_files.sort_by(|a, b| a.name.cmp(&b.name));
+    for entry in requirement_files {
+        let Some(text) = files.read(&entry.name).await? else {
+            continue;
+        };
+        let kind = if entry.name.contains("dev") || entry.name.contains("test") {
+            DependencyKind::Dev
+        } else {
+            DependencyKind::Direct
+        };
+        declared.extend(
+            parse_requirements(&text)
+                .into_iter()
+                .map(|(name, requirement)| Declared {
+                    name,
+                    requirement,
+                    kind,
+                }),
+        );
+    }
+
+    let mut projects = Vec::new();
+    if let Some(root) = read_toml::<PyProject>(files, "pyproject.toml").await? {
+        if let Some(workspace) = root.tool.uv.as_ref().and_then(|uv| uv.workspace.as_ref()) {
+            let include = workspace.members.iter().map(String::as_str);
+            let exclude = workspace.exclude.iter().map(String::as_str);
+            for dir in member_dirs(files, include, exclude).await? {
+                let path = join(&dir, "pyproject.toml");
+                match read_toml::<PyProject>(files, &path).await? {
+                    Some(member) => projects.push(member),
+                    None => tracing::debug!(path, "workspace member has no pyproject.toml"),
+                }
+            }
+        }
+        projects.insert(0, root);
+    }
+    let mut local: HashSet<_> = projects
+        .iter()
+        .filter_map(PyProject::name)
+        .map(normalize_name)
+        .collect();
+    for project in &projects {
+        local.extend(project.local_packages());
+        declared.extend(project.declared());
+    }
+
+    let lock = if has_file(root_dir, "uv.lock") {
+        read_toml::<Lockfile>(files, "uv.lock").await?
+    } else if has_file(root_dir, "poetry.lock") {
+        read_toml::<Lockfile>(files, "poetry.lock").await?
+    } else {
+        None
+    };
+    Ok(resolve(declared, &local, lock.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deps::tests::{MemoryFiles, summary};
+
+    async fn extract_files(files: &[(&str, &str)]) -> Vec<Dependency> {
+        let files = MemoryFiles::new(files);
+        let root_dir = files.list("").await.unwrap().unwrap();
+        extract(&files, &root_dir).await.unwrap()
+    }
+
+    #[test]
+    fn normalize_name_follows_pep_503() {
+        for (name, expected) in [
+            ("requests", "requests"),
+            ("Typing_Extensions", "typing-extensions"),
+            ("zope.interface", "zope-interface"),
+            ("Foo-_.Bar", "foo-bar"),
+        ] {
+            assert_eq!(normalize_name(name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn parse_requirement_reads_names_and_specifiers() {
+        for (requirement, expected) in [
+            ("requests", Some(("requests", None))),
+            ("Django==4.2.7", Some(("django", Some("==4.2.7")))),
+            (
+                r#"pandas[excel] >= 2.1; python_version >= "3.9""#,
+                Some(("pandas", Some(">=2.1"))),
+            ),
+            ("foo (>=1.0, <2)", Some(("foo", Some(">=1.0,<2")))),
+            ("pkg @ https://example.com/pkg.whl", Some(("pkg", None))),
+            ("https://example.com/pkg.whl", None),
+            ("", None),
+        ] {
+            let parsed = parse_requirement(requirement);
+            let parsed = parsed
+                .as_ref()
+                .map(|(name, specifiers)| (name.as_str(), specifiers.as_deref()));
+            assert_eq!(parsed, expected, "{requirement}");
+        }
+    }
+
+    #[test]
+    fn parse_requirements_skips_options_comments_and_paths() {
+        let text = "\
+# Pinned for production
+-r base.txt
+--index-url https://example.com/simple
+-e ./packages/local
+./wheels/local.whl
+Flask>=2.0  # web framework
+pytest==7.4.3 \\
+    --hash=sha256:abc \\
+    --hash=sha256:def
+";
+        assert_eq!(
+            parse_requirements(text),
+            [
+                ("flask".to_owned(), Some(">=2.0".to_owned())),
+                ("pytest".to_owned(), Some("==7.4.3".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn locked_version_uses_the_requirement_to_choose() {
+        for (locked, requirement, expected) in [
+            (&[][..], Some("==1.2.0"), Some("1.2.0")),
+            (&[][..], Some(">=1.2"), None),
+            (&[][..], None, None),
+            (&["1.0.0"][..], Some(">=2"), Some("1.0.0")),
+            (&["1.0.0", "2.1.0"][..], Some(">=2"), Some("2.1.0")),
+            (&["1.0.0", "2.1.0"][..], None, None),
+        ] {
+            assert_eq!(
+                locked_version(locked, requirement).as_deref(),
+                expected,
+                "{locked:?} {requirement:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn requirements_files_without_a_lock_keep_their_specifiers() {
+        let dependencies = extract_files(&[
+            ("requirements.txt", "Django==4.2.7\nflask>=2.0\n"),
+            ("requirements-dev.txt", "pytest==7.4.3\nflask\n"),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("django", "4.2.7", DependencyKind::Direct),
+                ("flask", ">=2.0", DependencyKind::Direct),
+                ("pytest", "7.4.3", DependencyKind::Dev),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn uv_workspace_leaves_out_local_packages() {
+        let dependencies = extract_files(&[
+            (
+                "pyproject.toml",
+                r#"
+[project]
+name = "app"
+dependencies = ["httpx>=0.25", "Shared_Lib"]
+
+[dependency-groups]
+dev = ["pytest>=7", { include-group = "lint" }]
+
+[tool.uv.sources]
+shared-lib = { workspace = true }
+
+[tool.uv.workspace]
+members = ["packages/*"]
+"#,
+            ),
+            (
+                "packages/shared-lib/pyproject.toml",
+                "[project]\nname = \"shared-lib\"\ndependencies = [\"pydantic~=2.0\"]\n",
+            ),
+            (
+                "uv.lock",
+                r#"
+version = 1
+
+[[package]]
+name = "anyio"
+version = "4.1.0"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "app"
+version = "0.1.0"
+source = { editable = "." }
+
+[[package]]
+name = "httpx"
+version = "0.25.2"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "pydantic"
+version = "2.5.0"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "pytest"
+version = "7.4.3"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "shared-lib"
+version = "0.1.0"
+source = { editable = "packages/shared-lib" }
+"#,
+            ),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("httpx", "0.25.2", DependencyKind::Direct),
+                ("pydantic", "2.5.0", DependencyKind::Direct),
+                ("pytest", "7.4.3", DependencyKind::Dev),
+                ("anyio", "4.1.0", DependencyKind::Transitive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn poetry_project_skips_python_and_path_dependencies() {
+        let dependencies = extract_files(&[
+            (
+                "pyproject.toml",
+                r#"
+[tool.poetry]
+name = "svc"
+
+[tool.poetry.dependencies]
+python = "^3.11"
+requests = "^2.31"
+local-lib = { path = "../lib", develop = true }
+
+[tool.poetry.group.dev.dependencies]
+black = "^23.0"
+"#,
+            ),
+            (
+                "poetry.lock",
+                r#"
+[[package]]
+name = "black"
+version = "23.11.0"
+
+[[package]]
+name = "local-lib"
+version = "0.1.0"
+
+[package.source]
+type = "directory"
+url = "../lib"
+
+[[package]]
+name = "requests"
+version = "2.31.0"
+
+[[package]]
+name = "urllib3"
+version = "2.1.0"
+"#,
+            ),
+        ])
+        .await;
+        assert_eq!(
+            summary(&dependencies),
+            [
+                ("black", "23.11.0", DependencyKind::Dev),
+                ("requests", "2.31.0", DependencyKind::Direct),
+                ("urllib3", "2.1.0", DependencyKind::Transitive),
+            ]
+        );
+    }
+}
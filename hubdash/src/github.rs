@@ -1 +1,298 @@
+//! Client for the GitHub API.
 
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use jiff::Timestamp;
+use reqwest::{
+    Method, RequestBuilder, StatusCode,
+    header::{ACCEPT, AUTHORIZATION, HeaderMap, HeaderValue, USER_AGENT},
+};
+use serde::{Deserialize, de::DeserializeOwned};
+use url::Url;
+
+use crate::source::SourceError;
+
+mod app;
+mod conditional;
+mod contents;
+mod graphql;
+mod ratelimit;
+mod repos;
+mod rest;
+
+pub use app::AppCredentials;
+pub use contents::ContentEntry;
+pub use graphql::OrgRepos;
+pub use ratelimit::{Budget, RateLimits};
+pub use repos::{GitHubRepos, RepoRef};
+pub use rest::{Job, Repository, RepositoryOwner, RunQuery, Step, User, Workflow, WorkflowRun};
+pub(crate) use rest::{event_trigger, pipeline_status};
+
+use conditional::ResponseCache;
+
+/// The public GitHub REST API endpoint.
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+const API_VERSION: &str = "2022-11-28";
+
+/// How long the API has to accept a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the API has to answer a request in full, which for a GraphQL
+/// query over a large organization can take a while.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Entries fetched per page of a list, the most GitHub allows.
+const PAGE_SIZE: u32 = 100;
+
+/// Repositories summarized at once when listing them.
+const CONCURRENT_SUMMARIES: usize = 4;
+
+/// A page of a list endpoint that reports the length of the whole list.
+trait Page: DeserializeOwned {
+    type Item;
+
+    /// Returns the length of the whole list and the entries on this page.
+    fn into_parts(self) -> (usize, Vec<Self::Item>);
+}
+
+/// Errors returned by the GitHub client.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The request could not be sent or the response could not be decoded.
+    #[error("request to GitHub failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// GitHub answered with a non-success status code.
+    #[error("GitHub returned {status}: {message}")]
+    Status {
+        /// The HTTP status code.
+        status: reqwest::StatusCode,
+        /// The error message from the response body.
+        message: String,
+    },
+    /// The response body was not the JSON that was expected.
+    #[error("could not decode GitHub response: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// A GitHub App token could not be signed.
+    #[error("GitHub App authentication failed: {0}")]
+    AppAuth(#[from] jsonwebtoken::errors::Error),
+    /// The rate limit is exhausted, or requests are paused after hitting a
+    /// secondary rate limit.
+    #[error("GitHub rate limit exceeded until {until}")]
+    RateLimited {
+        /// When requests may resume.
+        until: Timestamp,
+    },
+    /// A GraphQL query was rejected or partially failed.
+    #[error("GitHub GraphQL query failed: {0}")]
+    GraphQl(String),
+}
+
+impl From<Error> for SourceError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::RateLimited { until } => SourceError::RateLimited { until },
+            err => SourceError::Upstream(Box::new(err)),
+        }
+    }
+}
+
+/// The JSON body GitHub sends alongside error responses.
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+}
+
+/// How the client authenticates its requests.
+#[derive(Debug, Clone)]
+enum Credentials {
+    Anonymous,
+    Token(Arc<str>),
+    App(Arc<app::Installation>),
+}
+
+/// A typed client for the GitHub API.
+#[derive(Debug, Clone)]
+pub struct GitHubClient {
+    http: reqwest::Client,
+    api_url: Url,
+    credentials: Credentials,
+    limits: RateLimits,
+    responses: ResponseCache,
+}
+
+impl GitHubClient {
+    /// Creates an unauthenticated client for the API rooted at `api_url`.
+    ///
+    /// The URL may carry a path prefix, as GitHub Enterprise Server does
+    /// with `/api/v3`.
+    pub fn new(api_url: Url) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/vnd.github+json"),
+        );
+        headers.insert(
+            "X-GitHub-Api-Version",
+            HeaderValue::from_static(API_VERSION),
+        );
+        headers.insert(USER_AGENT, HeaderValue::from_static("hubdash"));
+
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("valid HTTP client configuration");
+
+        Self {
+            http,
+            api_url,
+            credentials: Credentials::Anonymous,
+            limits: RateLimits::default(),
+            responses: ResponseCache::default(),
+        }
+    }
+
+    /// Authenticates every request with the given token.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.credentials = Credentials::Token(token.into().into());
+        self.limits = RateLimits::default();
+        self.responses = ResponseCache::default();
+        self
+    }
+
+    /// Authenticates as an installation of a GitHub App.
+    ///
+    /// Installation tokens are minted on first use and refreshed shortly
+    /// before they expire.
+    pub fn with_app(mut self, credentials: AppCredentials) -> Self {
+        self.credentials = Credentials::App(Arc::new(app::Installation::new(credentials)));
+        self.limits = RateLimits::default();
+        self.responses = ResponseCache::default();
+        self
+    }
+
+    /// Returns a handle to the rate limits tracked for this client's
+    /// credentials.
+    pub fn rate_limits(&self) -> RateLimits {
+        self.limits.clone()
+    }
+
+    /// Whether the client authenticates as a GitHub App installation.
+    pub fn is_app_installation(&self) -> bool {
+        matches!(self.credentials, Credentials::App(_))
+    }
+
+    /// Builds an API URL by appending path segments to the base URL.
+    fn endpoint(&self, segments: &[&str]) -> Url {
+        let mut url = self.api_url.clone();
+        url.path_segments_mut()
+            .expect("cannot be base")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    /// Starts a request to `url`, authenticated with the client's credentials.
+    async fn request(&self, method: Method, url: Url) -> Result<RequestBuilder, Error> {
+        let request = self.http.request(method, url);
+        let token = match &self.credentials {
+            Credentials::Anonymous => return Ok(request),
+            Credentials::Token(token) => token.clone(),
+            Credentials::App(installation) => self.installation_token(installation).await?,
+        };
+        Ok(request.header(AUTHORIZATION, format!("Bearer {token}")))
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: Url) -> Result<T, Error> {
+        self.send_json(self.request(Method::GET, url).await?).await
+    }
+
+    /// Fetches the list at `url` page by page until all of it is read.
+    async fn get_all<P: Page>(&self, url: Url) -> Result<Vec<P::Item>, Error> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let mut url = url.clone();
+            url.query_pairs_mut()
+                .append_pair("per_page", &PAGE_SIZE.to_string())
+                .append_pair("page", &page.to_string());
+
+            let (total, batch) = self.get_json::<P>(url).await?.into_parts();
+            let received = batch.len();
+            items.extend(batch);
+            if received == 0 || items.len() >= total {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Sends a request and decodes the JSON response.
+    async fn send_json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, Error> {
+        let body = self.send(request).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Sends a request once the rate limit allows it and returns the
+    /// response body.
+    ///
+    /// `GET` requests are made conditional on the previous response to the
+    /// same URL and media type, whose body is reused if GitHub reports it
+    /// unchanged.
+    async fn send(&self, request: RequestBuilder) -> Result<Bytes, Error> {
+        let mut request = request.build()?;
+        let key = conditional::Key::of(&request);
+        let resource = if *request.url() == self.graphql_endpoint() {
+            "graphql"
+        } else {
+            "core"
+        };
+        let conditional = request.method() == Method::GET;
+        let unconditional = if conditional {
+            let unconditional = request.try_clone();
+            self.responses.prepare(&key, request.headers_mut());
+            unconditional
+        } else {
+            None
+        };
+        self.limits.acquire(resource).await?;
+
+        let mut response = self.http.execute(request).await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(body) = self.responses.cached(&key) {
+                self.limits.observe(response.status(), response.headers());
+                return Ok(body);
+            }
+            // The body was evicted since the request was made conditional,
+            // so ask for it again in full.
+            if let Some(request) = unconditional {
+                self.limits.observe(response.status(), response.headers());
+                tracing::debug!(url = %request.url(), "cached response gone, requesting it again");
+                self.limits.acquire(resource).await?;
+                response = self.http.execute(request).await?;
+            }
+        }
+        let status = response.status();
+        if let Some(until) = self.limits.observe(status, response.headers()) {
+            tracing::warn!(%until, "GitHub rate limit hit");
+            return Err(Error::RateLimited { until });
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<ErrorBody>(&body)
+                .map(|e| e.message)
+                .unwrap_or(body);
+            return Err(Error::Status { status, message });
+        }
+
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        if conditional {
+            self.responses.store(key, &headers, body.clone());
+        }
+        Ok(body)
+    }
+}
@@ -0,0 +1,241 @@
+//! Authentication as a GitHub App installation.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use futures_util::lock::Mutex as AsyncMutex;
+use jiff::{SignedDuration, Timestamp};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+
+use super::{Credentials, Error, GitHubClient, Page, Repository};
+
+/// How far into the past the JWT's issue time is set, to absorb clock skew.
+const CLOCK_SKEW: SignedDuration = SignedDuration::from_secs(60);
+
+/// Lifetime of the JWT; GitHub rejects anything above ten minutes.
+const JWT_LIFETIME: SignedDuration = SignedDuration::from_mins(9);
+
+/// Installation tokens are replaced once they get this close to expiring.
+const REFRESH_MARGIN: SignedDuration = SignedDuration::from_mins(5);
+
+/// The identity and private key of a GitHub App installation.
+#[derive(Clone)]
+pub struct AppCredentials {
+    app_id: u64,
+    installation_id: u64,
+    key: EncodingKey,
+}
+
+impl fmt::Debug for AppCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppCredentials")
+            .field("app_id", &self.app_id)
+            .field("installation_id", &self.installation_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Serialize)]
+struct Claims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+impl AppCredentials {
+    /// Loads credentials from the PEM-encoded RSA private key GitHub
+    /// generated for the app.
+    pub fn from_pem(app_id: u64, installation_id: u64, pem: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            app_id,
+            installation_id,
+            key: EncodingKey::from_rsa_pem(pem)?,
+        })
+    }
+
+    /// Signs a short-lived JWT identifying the app itself.
+    fn jwt(&self) -> Result<String, Error> {
+        let now = Timestamp::now();
+        let claims = Claims {
+            iat: (now - CLOCK_SKEW).as_second(),
+            exp: (now + JWT_LIFETIME).as_second(),
+            iss: self.app_id.to_string(),
+        };
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &self.key,
+        )?)
+    }
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+    #[serde(deserialize_with = "deserialize_arc_str")]
+    token: Arc<str>,
+    expires_at: Timestamp,
+}
+
+fn deserialize_arc_str<'de, D: serde::Deserializer<'de>>(de: D) -> Result<Arc<str>, D::Error> {
+    String::deserialize(de).map(Arc::from)
+}
+
+impl fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessToken")
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Deserialize)]
+struct InstallationRepositories {
+    total_count: usize,
+    repositories: Vec<Repository>,
+}
+
+impl Page for InstallationRepositories {
+    type Item = Repository;
+
+    fn into_parts(self) -> (usize, Vec<Repository>) {
+        (self.total_count, self.repositories)
+    }
+}
+
+/// The repositories an installation was granted, as of when its current
+/// token was minted.
+#[derive(Debug)]
+struct Granted {
+    repositories: Vec<Repository>,
+    expires_at: Timestamp,
+}
+
+/// An installation's credentials together with its current access token.
+#[derive(Debug)]
+pub(super) struct Installation {
+    credentials: AppCredentials,
+    token: Mutex<Option<AccessToken>>,
+    /// Held while a token is minted, so concurrent requests share one.
+    minting: AsyncMutex<()>,
+    /// Granted repositories are kept for as long as the token they were
+    /// listed with.
+    granted: Mutex<Option<Granted>>,
+}
+
+impl Installation {
+    pub(super) fn new(credentials: AppCredentials) -> Self {
+        Self {
+            credentials,
+            token: Mutex::new(None),
+            minting: AsyncMutex::new(()),
+            granted: Mutex::new(None),
+        }
+    }
+
+    fn cached_token(&self) -> Option<Arc<str>> {
+        let token = self.token.lock().expect("installation token poisoned");
+        token
+            .as_ref()
+            .filter(|t| t.expires_at - REFRESH_MARGIN > Timestamp::now())
+            .map(|t| t.token.clone())
+    }
+
+    /// Looks up `owner/repo` among the granted repositories, if they were
+    /// listed with the current token.
+    fn granted_repo(&self, owner: &str, repo: &str) -> Option<Option<Repository>> {
+        let granted = self.granted.lock().expect("granted repositories poisoned");
+        let granted = granted
+            .as_ref()
+            .filter(|g| g.expires_at > Timestamp::now())?;
+        Some(
+            granted
+                .repositories
+                .iter()
+                .find(|r| r.is(owner, repo))
+                .cloned(),
+        )
+    }
+
+    fn store_granted(&self, repositories: &[Repository]) {
+        let expires_at = self
+            .token
+            .lock()
+            .expect("installation token poisoned")
+            .as_ref()
+            .map(|t| t.expires_at - REFRESH_MARGIN);
+        *self.granted.lock().expect("granted repositories poisoned") =
+            expires_at.map(|expires_at| Granted {
+                repositories: repositories.to_vec(),
+                expires_at,
+            });
+    }
+}
+
+impl GitHubClient {
+    /// Returns a valid installation token, minting a new one if needed.
+    pub(super) async fn installation_token(
+        &self,
+        installation: &Installation,
+    ) -> Result<Arc<str>, Error> {
+        if let Some(token) = installation.cached_token() {
+            return Ok(token);
+        }
+        let _minting = installation.minting.lock().await;
+        // Another request may have minted a token while this one waited.
+        if let Some(token) = installation.cached_token() {
+            return Ok(token);
+        }
+
+        let installation_id = installation.credentials.installation_id.to_string();
+        let url = self.endpoint(&["app", "installations", &installation_id, "access_tokens"]);
+        let request = self
+            .http
+            .post(url)
+            .bearer_auth(installation.credentials.jwt()?);
+        let fresh: AccessToken = self.send_json(request).await?;
+        tracing::debug!(expires_at = %fresh.expires_at, "minted GitHub App installation token");
+
+        let token = fresh.token.clone();
+        *installation
+            .token
+            .lock()
+            .expect("installation token poisoned") = Some(fresh);
+        Ok(token)
+    }
+
+    /// Lists every repository the app installation can access.
+    ///
+    /// When authenticated as the installation, the list is remembered for
+    /// [`installation_repo`](Self::installation_repo).
+    pub async fn list_installation_repos(&self) -> Result<Vec<Repository>, Error> {
+        let url = self.endpoint(&["installation", "repositories"]);
+        let repositories = self.get_all::<InstallationRepositories>(url).await?;
+        if let Credentials::App(installation) = &self.credentials {
+            installation.store_granted(&repositories);
+        }
+        Ok(repositories)
+    }
+
+    /// Returns `owner/repo` if the app installation was granted it.
+    ///
+    /// Installation tokens can read any public repository, so fetching a
+    /// repository directly does not tell whether it is one of them. The
+    /// installation's repositories are only listed again once the
+    /// token they were last listed with is replaced.
+    pub async fn installation_repo(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<Repository>, Error> {
+        if let Credentials::App(installation) = &self.credentials
+            && let Some(granted) = installation.granted_repo(owner, repo)
+        {
+            return Ok(granted);
+        }
+        let repositories = self.list_installation_repos().await?;
+        Ok(repositories.into_iter().find(|r| r.is(owner, repo)))
+    }
+}
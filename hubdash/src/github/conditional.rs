@@ -0,0 +1,230 @@
+//! Conditional requests using the `ETag` and `Last-Modified` validators.
+//!
+//! GitHub answers a conditional request for unchanged data with
+//! `304 Not Modified`, which does not count against the rate limit. The
+//! validators and body of every cacheable response are kept per URL and
+//! media type so later requests can be made conditional and answered from
+//! memory, up to a limit on the bytes kept, dropping the least recently
+//! used responses first.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use reqwest::Request;
+use reqwest::header::{
+    ACCEPT, ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use url::Url;
+
+/// Identifies a cached response by URL and the media type requested, as
+/// the same URL can return JSON or raw content.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct Key {
+    url: Url,
+    accept: Option<HeaderValue>,
+}
+
+impl Key {
+    pub(super) fn of(request: &Request) -> Self {
+        Self {
+            url: request.url().clone(),
+            accept: request.headers().get(ACCEPT).cloned(),
+        }
+    }
+}
+
+/// Bytes of response bodies kept by default.
+const DEFAULT_CAPACITY: usize = 32 * 1024 * 1024;
+
+struct Validated {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    body: Bytes,
+    /// When the entry was last used, as a tick of [`Entries::clock`].
+    used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_key: HashMap<Key, Validated>,
+    /// Keys by when they were last used, least recently used first.
+    recency: BTreeMap<u64, Key>,
+    clock: u64,
+    bytes: usize,
+}
+
+impl Entries {
+    /// Returns the entry for `key`, marking it as the most recently used.
+    fn touch(&mut self, key: &Key) -> Option<&Validated> {
+        let entry = self.by_key.get_mut(key)?;
+        self.recency.remove(&entry.used);
+        self.clock += 1;
+        entry.used = self.clock;
+        self.recency.insert(entry.used, key.clone());
+        Some(entry)
+    }
+
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.by_key.remove(key) {
+            self.recency.remove(&entry.used);
+            self.bytes -= entry.body.len();
+        }
+    }
+
+    fn insert(&mut self, key: Key, mut entry: Validated) {
+        self.remove(&key);
+        self.clock += 1;
+        entry.used = self.clock;
+        self.bytes += entry.body.len();
+        self.recency.insert(entry.used, key.clone());
+        self.by_key.insert(key, entry);
+    }
+
+    /// Drops the least recently used entries until at most `capacity`
+    /// bytes are kept.
+    fn evict(&mut self, capacity: usize) {
+        while self.bytes > capacity {
+            let Some((_, key)) = self.recency.pop_first() else {
+                return;
+            };
+            if let Some(entry) = self.by_key.remove(&key) {
+                self.bytes -= entry.body.len();
+            }
+        }
+    }
+}
+
+/// Response bodies of earlier requests.
+#[derive(Clone)]
+pub(super) struct ResponseCache {
+    entries: Arc<Mutex<Entries>>,
+    capacity: usize,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries.lock().expect("response cache poisoned");
+        f.debug_struct("ResponseCache")
+            .field("entries", &entries.by_key.len())
+            .field("bytes", &entries.bytes)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl ResponseCache {
+    /// Creates a cache keeping at most `capacity` bytes of response bodies.
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Arc::default(),
+            capacity,
+        }
+    }
+
+    /// Adds the validators stored for `key`, if any, to a request.
+    pub(super) fn prepare(&self, key: &Key, headers: &mut HeaderMap) {
+        let mut entries = self.entries.lock().expect("response cache poisoned");
+        let Some(entry) = entries.touch(key) else {
+            return;
+        };
+        if let Some(etag) = &entry.etag {
+            headers.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+    }
+
+    /// Returns the body stored for `key` after a `304 Not Modified`.
+    pub(super) fn cached(&self, key: &Key) -> Option<Bytes> {
+        let mut entries = self.entries.lock().expect("response cache poisoned");
+        entries.touch(key).map(|entry| entry.body.clone())
+    }
+
+    /// Stores a successful response if it carries any validators and fits
+    /// in the cache.
+    pub(super) fn store(&self, key: Key, headers: &HeaderMap, body: Bytes) {
+        let etag = headers.get(ETAG).cloned();
+        let last_modified = headers.get(LAST_MODIFIED).cloned();
+        let mut entries = self.entries.lock().expect("response cache poisoned");
+        if (etag.is_none() && last_modified.is_none()) || body.len() > self.capacity {
+            entries.remove(&key);
+            return;
+        }
+        entries.insert(
+            key,
+            Validated {
+                etag,
+                last_modified,
+                body,
+                used: 0,
+            },
+        );
+        entries.evict(self.capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str) -> Key {
+        Key {
+            url: Url::parse("https://api.github.com/")
+                .unwrap()
+                .join(path)
+                .unwrap(),
+            accept: None,
+        }
+    }
+
+    fn store(cache: &ResponseCache, path: &str, body: &'static [u8]) {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+        cache.store(key(path), &headers, Bytes::from_static(body));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_bodies() {
+        let cache = ResponseCache::with_capacity(10);
+        store(&cache, "a", b"aaaa");
+        store(&cache, "b", b"bbbb");
+        // Requesting `a` again keeps it over `b`.
+        cache.prepare(&key("a"), &mut HeaderMap::new());
+        store(&cache, "c", b"cccc");
+
+        assert_eq!(cache.cached(&key("a")).as_deref(), Some(&b"aaaa"[..]));
+        assert_eq!(cache.cached(&key("b")), None);
+        assert_eq!(cache.cached(&key("c")).as_deref(), Some(&b"cccc"[..]));
+    }
+
+    #[test]
+    fn replacing_a_body_counts_only_the_new_one() {
+        let cache = ResponseCache::with_capacity(10);
+        store(&cache, "a", b"aaaa");
+        store(&cache, "a", b"aaaaaa");
+        store(&cache, "b", b"bbbb");
+
+        assert_eq!(cache.cached(&key("a")).as_deref(), Some(&b"aaaaaa"[..]));
+        assert_eq!(cache.cached(&key("b")).as_deref(), Some(&b"bbbb"[..]));
+    }
+
+    #[test]
+    fn skips_bodies_larger_than_the_cache() {
+        let cache = ResponseCache::with_capacity(10);
+        store(&cache, "a", b"aaaa");
+        store(&cache, "b", b"bbbbbbbbbbbb");
+
+        assert_eq!(cache.cached(&key("a")).as_deref(), Some(&b"aaaa"[..]));
+        assert_eq!(cache.cached(&key("b")), None);
+        assert!(format!("{cache:?}").contains("bytes: 4"));
+    }
+}
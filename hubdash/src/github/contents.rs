@@ -0,0 +1,208 @@
+//! Reading files from a repository's default branch.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use reqwest::{
+    Method, StatusCode,
+    header::{ACCEPT, HeaderValue},
+};
+use serde::Deserialize;
+use url::Url;
+
+use super::{Error, GitHubClient};
+use crate::dashboard::Dependency;
+use crate::deps::{self, DepsError, DirEntry, RepoFiles};
+use crate::workflows::{self, WORKFLOWS_DIR, WorkflowTriggers};
+
+/// Media type returning file contents as-is rather than base64 in JSON,
+/// which also works for files over 1 MB.
+const RAW: HeaderValue = HeaderValue::from_static("application/vnd.github.raw+json");
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentEntry {
+    /// The entry's name within its directory.
+    pub name: String,
+    /// The entry's path from the repository root.
+    pub path: String,
+    /// `file`, `dir`, `symlink` or `submodule`.
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// Files and directory listings of a repository's default branch that
+/// were read ahead of time, such as by the GraphQL query of
+/// [`OrgRepos`](super::OrgRepos).
+///
+/// Anything not read ahead is read through the contents API.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Prefetched {
+    /// File contents by path, `None` for files known not to exist.
+    files: HashMap<String, Option<String>>,
+    /// Directory listings by path, `None` for directories known not to
+    /// exist.
+    dirs: HashMap<String, Option<Vec<DirEntry>>>,
+}
+
+impl Prefetched {
+    /// Records the contents of the file at `path`, or that there is none.
+    pub(crate) fn insert_file(&mut self, path: impl Into<String>, text: Option<String>) {
+        self.files.insert(path.into(), text);
+    }
+
+    /// Records the listing of the directory at `path`, or that there is
+    /// none.
+    pub(crate) fn insert_dir(&mut self, path: impl Into<String>, entries: Option<Vec<DirEntry>>) {
+        self.dirs.insert(path.into(), entries);
+    }
+}
+
+fn not_found(err: &Error) -> bool {
+    matches!(err, Error::Status { status, .. } if *status == StatusCode::NOT_FOUND)
+}
+
+impl GitHubClient {
+    fn contents_endpoint(&self, owner: &str, repo: &str, path: &str) -> Url {
+        let mut url = self.endpoint(&["repos", owner, repo, "contents"]);
+        url.path_segments_mut()
+            .expect("cannot be base")
+            .extend(path.split('/').filter(|s| !s.is_empty()));
+        url
+    }
+
+    /// Reads a file from a repository's default branch, or `None` if there
+    /// is no such file.
+    pub async fn get_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+    ) -> Result<Option<String>, Error> {
+        let url = self.contents_endpoint(owner, repo, path);
+        let request = self.request(Method::GET, url).await?.header(ACCEPT, RAW);
+        match self.send(request).await {
+            Ok(body) => Ok(Some(String::from_utf8_lossy(&body).into_owned())),
+            Err(err) if not_found(&err) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Lists a directory on a repository's default branch, or returns
+    /// `None` if there is no such directory.
+    pub async fn list_directory(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+    ) -> Result<Option<Vec<ContentEntry>>, Error> {
+        let url = self.contents_endpoint(owner, repo, path);
+        match self.get_json::<Vec<ContentEntry>>(url).await {
+            Ok(entries) => Ok(Some(entries)),
+            Err(err) if not_found(&err) => Ok(None),
+            // A file where a directory was expected.
+            Err(Error::Decode(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Extracts a repository's dependencies from the manifests and
+    /// lockfiles on its default branch, reading those not in `prefetched`.
+    pub(crate) async fn fetch_dependencies(
+        &self,
+        owner: &str,
+        repo: &str,
+        prefetched: &Prefetched,
+    ) -> Result<Vec<Dependency>, DepsError> {
+        deps::extract(&RepoContents {
+            client: self,
+            owner,
+            repo,
+            prefetched,
+        })
+        .await
+    }
+
+    /// Reads the triggers of every workflow on a repository's default
+    /// branch, which is called `default_branch`, reading the workflow
+    /// files not in `prefetched`.
+    ///
+    /// Workflow files that are not valid YAML are skipped, as GitHub
+    /// skips running them.
+    pub(crate) async fn fetch_workflow_triggers(
+        &self,
+        owner: &str,
+        repo: &str,
+        default_branch: &str,
+        prefetched: &Prefetched,
+    ) -> Result<Vec<WorkflowTriggers>, DepsError> {
+        let files = RepoContents {
+            client: self,
+            owner,
+            repo,
+            prefetched,
+        };
+        let entries = files.list(WORKFLOWS_DIR).await?.unwrap_or_default();
+        let mut workflows = Vec::new();
+        for entry in entries {
+            if entry.is_dir || !workflows::is_workflow_file(&entry.name) {
+                continue;
+            }
+            let path = format!("{WORKFLOWS_DIR}/{}", entry.name);
+            let Some(text) = files.read(&path).await? else {
+                continue;
+            };
+            match workflows::parse(&path, &text, default_branch) {
+                Ok(workflow) => workflows.push(workflow),
+                Err(err) => {
+                    tracing::warn!(error = %err, owner, repo, path, "invalid workflow file")
+                }
+            }
+        }
+        Ok(workflows)
+    }
+}
+
+impl From<Error> for DepsError {
+    fn from(err: Error) -> Self {
+        DepsError::Fetch(Box::new(err))
+    }
+}
+
+/// The files of a repository, read through the contents API unless they
+/// were prefetched.
+struct RepoContents<'a> {
+    client: &'a GitHubClient,
+    owner: &'a str,
+    repo: &'a str,
+    prefetched: &'a Prefetched,
+}
+
+#[async_trait]
+impl RepoFiles for RepoContents<'_> {
+    async fn read(&self, path: &str) -> Result<Option<String>, DepsError> {
+        if let Some(text) = self.prefetched.files.get(path) {
+            return Ok(text.clone());
+        }
+        Ok(self.client.get_file(self.owner, self.repo, path).await?)
+    }
+
+    async fn list(&self, path: &str) -> Result<Option<Vec<DirEntry>>, DepsError> {
+        if let Some(entries) = self.prefetched.dirs.get(path) {
+            return Ok(entries.clone());
+        }
+        let entries = self
+            .client
+            .list_directory(self.owner, self.repo, path)
+            .await?;
+        Ok(entries.map(|entries| {
+            entries
+                .into_iter()
+                .map(|entry| DirEntry {
+                    is_dir: entry.kind == "dir",
+                    name: entry.name,
+                })
+                .collect()
+        }))
+    }
+}
@@ -0,0 +1,710 @@
+//! Batched repository summaries from the GitHub GraphQL API.
+//!
+//! A single paginated query fetches repository metadata, the check suites
+//! on each default branch's head commit, the rollup status of recent
+//! default-branch commits, and the workflow files and root manifests on
+//! the default branch, which is far cheaper than the per-repository REST
+//! calls made by [`GitHubRepos`](super::GitHubRepos).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures_util::{StreamExt, stream};
+use jiff::Timestamp;
+use reqwest::Method;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::json;
+use url::Url;
+
+use super::contents::Prefetched;
+use super::rest::{event_trigger, pipeline_status, success_rate};
+use super::{CONCURRENT_SUMMARIES, Error, GitHubClient};
+use crate::advisories::AdvisoryDb;
+use crate::dashboard::{JobSummary, PipelineStatus, PipelineSummary, RepoSummary, Triggers};
+use crate::deps::{DirEntry, ROOT_FILES};
+use crate::history::{Recorder, RunHistory, RunRecord};
+use crate::registry::Registries;
+use crate::source::{RepoSource, SourceError};
+use crate::workflows::WORKFLOWS_DIR;
+
+/// Repositories fetched per page of the organization query.
+const PAGE_SIZE: u32 = 50;
+
+/// Default-branch commits considered when computing success rates.
+const HISTORY_DEPTH: u32 = 20;
+
+/// Jobs read per check suite to time its run.
+const CHECK_RUNS: u32 = 50;
+
+const REPOSITORY_FIELDS: &str = r#"
+fragment RepositoryFields on Repository {
+  name
+  owner { login }
+  description
+  defaultBranchRef {
+    name
+    target {
+      ... on Commit {
+        oid
+        checkSuites(last: 20) {
+          nodes {
+            status
+            conclusion
+            checkRuns(first: $checkRuns) {
+              nodes { startedAt completedAt }
+            }
+            workflowRun {
+              databaseId
+              url
+              event
+              createdAt
+              updatedAt
+              workflow { name }
+            }
+          }
+        }
+        history(first: $history) {
+          nodes {
+            statusCheckRollup { state }
+          }
+        }
+      }
+    }
+  }
+"#;
+
+/// Returns the `RepositoryFields` fragment the queries select.
+///
+/// Besides [`REPOSITORY_FIELDS`], it lists the root directory and reads
+/// the workflow files and each of [`ROOT_FILES`], the latter through
+/// aliases `rootFile0`, `rootFile1` and so on.
+fn repository_fields() -> String {
+    let mut fields = REPOSITORY_FIELDS.to_owned();
+    fields.push_str(
+        r#"  rootTree: object(expression: "HEAD:") {
+    ... on Tree { entries { name type } }
+  }
+"#,
+    );
+    fields.push_str(&format!(
+        r#"  workflows: object(expression: "HEAD:{WORKFLOWS_DIR}") {{
+    ... on Tree {{ entries {{ name type object {{ ...BlobText }} }} }}
+  }}
+"#
+    ));
+    for (i, path) in ROOT_FILES.iter().enumerate() {
+        fields.push_str(&format!(
+            "  rootFile{i}: object(expression: \"HEAD:{path}\") {{ ...BlobText }}\n"
+        ));
+    }
+    fields.push_str("}\n\nfragment BlobText on Blob { text isTruncated }\n");
+    fields
+}
+
+const ORG_QUERY: &str = r#"
+query($org: String!, $cursor: String, $pageSize: Int!, $history: Int!, $checkRuns: Int!) {
+  organization(login: $org) {
+    repositories(first: $pageSize, after: $cursor, isArchived: false, orderBy: {field: NAME, direction: ASC}) {
+      pageInfo { hasNextPage endCursor }
+      nodes { ...RepositoryFields }
+    }
+  }
+}
+"#;
+
+const REPO_QUERY: &str = r#"
+query($owner: String!, $name: String!, $history: Int!, $checkRuns: Int!) {
+  repository(owner: $owner, name: $name) { ...RepositoryFields }
+}
+"#;
+
+#[derive(Serialize)]
+struct Request {
+    query: String,
+    variables: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct OrgData {
+    organization: Option<Organization>,
+}
+
+#[derive(Deserialize)]
+struct Organization {
+    repositories: Connection<RepositoryNode>,
+}
+
+#[derive(Deserialize)]
+struct RepoData {
+    repository: Option<RepositoryNode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    #[serde(default)]
+    page_info: Option<PageInfo>,
+    nodes: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RepositoryNode {
+    name: String,
+    owner: OwnerNode,
+    description: Option<String>,
+    default_branch_ref: Option<BranchRef>,
+    #[serde(default)]
+    root_tree: Option<TreeNode>,
+    #[serde(default)]
+    workflows: Option<TreeNode>,
+    /// The `rootFile` aliases, by alias.
+    #[serde(flatten)]
+    root_files: HashMap<String, Option<BlobNode>>,
+}
+
+#[derive(Deserialize)]
+struct TreeNode {
+    #[serde(default)]
+    entries: Vec<TreeEntryNode>,
+}
+
+#[derive(Deserialize)]
+struct TreeEntryNode {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    object: Option<BlobNode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlobNode {
+    /// The file contents, `None` for binary files.
+    text: Option<String>,
+    #[serde(default)]
+    is_truncated: bool,
+}
+
+impl BlobNode {
+    /// Returns the file contents if the query returned all of them.
+    fn into_text(self) -> Option<String> {
+        self.text.filter(|_| !self.is_truncated)
+    }
+}
+
+impl TreeEntryNode {
+    fn to_dir_entry(&self) -> DirEntry {
+        DirEntry {
+            name: self.name.clone(),
+            is_dir: self.kind == "tree",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OwnerNode {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct BranchRef {
+    name: String,
+    target: Option<CommitNode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitNode {
+    oid: Option<String>,
+    check_suites: Option<Connection<CheckSuiteNode>>,
+    history: Option<Connection<HistoryNode>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckSuiteNode {
+    status: String,
+    conclusion: Option<String>,
+    #[serde(default)]
+    check_runs: Option<Connection<CheckRunNode>>,
+    workflow_run: Option<WorkflowRunNode>,
+}
+
+/// One of the jobs in a check suite.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckRunNode {
+    started_at: Option<Timestamp>,
+    completed_at: Option<Timestamp>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkflowRunNode {
+    database_id: Option<u64>,
+    url: Url,
+    event: String,
+    created_at: Timestamp,
+    updated_at: Timestamp,
+    workflow: WorkflowNode,
+}
+
+#[derive(Deserialize)]
+struct WorkflowNode {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryNode {
+    status_check_rollup: Option<RollupNode>,
+}
+
+#[derive(Deserialize)]
+struct RollupNode {
+    state: String,
+}
+
+impl CheckSuiteNode {
+    fn pipeline_status(&self) -> PipelineStatus {
+        let status = self.status.to_ascii_lowercase();
+        let conclusion = self.conclusion.as_deref().map(str::to_ascii_lowercase);
+        pipeline_status(Some(&status), conclusion.as_deref())
+    }
+
+    fn check_runs(&self) -> &[CheckRunNode] {
+        self.check_runs
+            .as_ref()
+            .map(|c| c.nodes.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns when the suite's first job started, as REST's
+    /// `run_started_at` would, leaving out the time spent queued.
+    ///
+    /// Runs without any started job fall back to when they were created.
+    fn started_at(&self, run: &WorkflowRunNode) -> Timestamp {
+        self.check_runs()
+            .iter()
+            .filter_map(|r| r.started_at)
+            .min()
+            .unwrap_or(run.created_at)
+    }
+
+    /// Returns when the suite's last job completed, if the suite has.
+    ///
+    /// A run's `updatedAt` moves on after it completes, e.g. when it is
+    /// re-run, so it is only used when no job reports completing.
+    fn finished_at(&self, run: &WorkflowRunNode) -> Option<Timestamp> {
+        (self.status == "COMPLETED").then(|| {
+            self.check_runs()
+                .iter()
+                .filter_map(|r| r.completed_at)
+                .max()
+                .unwrap_or(run.updated_at)
+        })
+    }
+
+    fn to_pipeline_summary(&self, run: &WorkflowRunNode) -> PipelineSummary {
+        let status = self.pipeline_status();
+        let started_at = self.started_at(run);
+        PipelineSummary {
+            name: run.workflow.name.clone(),
+            status,
+            run_time: self
+                .finished_at(run)
+                .map(|finished_at| finished_at.duration_since(started_at)),
+            github_url: run.url.clone(),
+            triggers: Triggers::empty(),
+            run_id: run.database_id,
+        }
+    }
+}
+
+impl RollupNode {
+    fn pipeline_status(&self) -> PipelineStatus {
+        match self.state.as_str() {
+            "SUCCESS" => PipelineStatus::Success,
+            "FAILURE" | "ERROR" => PipelineStatus::Failure,
+            _ => PipelineStatus::Pending,
+        }
+    }
+}
+
+impl RepositoryNode {
+    /// Takes the files the query read, leaving truncated and binary ones
+    /// to be read through the contents API.
+    fn take_prefetched(&mut self) -> Prefetched {
+        let mut prefetched = Prefetched::default();
+        let root = self.root_tree.take().map(|tree| tree.entries);
+        prefetched.insert_dir(
+            "",
+            root.as_ref()
+                .map(|entries| entries.iter().map(TreeEntryNode::to_dir_entry).collect()),
+        );
+        for (i, path) in ROOT_FILES.into_iter().enumerate() {
+            match self.root_files.remove(&format!("rootFile{i}")) {
+                Some(Some(blob)) => {
+                    if let Some(text) = blob.into_text() {
+                        prefetched.insert_file(path, Some(text));
+                    }
+                }
+                // Asked for but not there.
+                Some(None) => prefetched.insert_file(path, None),
+                None => {}
+            }
+        }
+
+        let Some(workflows) = self.workflows.take() else {
+            prefetched.insert_dir(WORKFLOWS_DIR, None);
+            return prefetched;
+        };
+        prefetched.insert_dir(
+            WORKFLOWS_DIR,
+            Some(
+                workflows
+                    .entries
+                    .iter()
+                    .map(TreeEntryNode::to_dir_entry)
+                    .collect(),
+            ),
+        );
+        for entry in workflows.entries {
+            if let Some(text) = entry.object.and_then(BlobNode::into_text) {
+                prefetched.insert_file(format!("{WORKFLOWS_DIR}/{}", entry.name), Some(text));
+            }
+        }
+        prefetched
+    }
+
+    /// Returns the workflow runs on the default branch's head commit.
+    fn run_records(&self) -> Vec<RunRecord> {
+        let Some(branch) = &self.default_branch_ref else {
+            return Vec::new();
+        };
+        let Some(head) = &branch.target else {
+            return Vec::new();
+        };
+        let suites = head
+            .check_suites
+            .as_ref()
+            .map(|c| c.nodes.as_slice())
+            .unwrap_or_default();
+        suites
+            .iter()
+            .filter_map(|suite| {
+                let run = suite.workflow_run.as_ref()?;
+                Some(RunRecord {
+                    owner: self.owner.login.clone(),
+                    repo: self.name.clone(),
+                    run_id: run.database_id?,
+                    workflow: run.workflow.name.clone(),
+                    status: suite.pipeline_status(),
+                    started_at: suite.started_at(run),
+                    finished_at: suite.finished_at(run),
+                    head_sha: head.oid.clone()?,
+                    branch: Some(branch.name.clone()),
+                    url: run.url.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn into_repo_summary(self) -> RepoSummary {
+        let head = self.default_branch_ref.and_then(|b| b.target);
+        let suites = head
+            .as_ref()
+            .and_then(|c| c.check_suites.as_ref())
+            .map(|c| c.nodes.as_slice())
+            .unwrap_or_default();
+        let history = head
+            .as_ref()
+            .and_then(|c| c.history.as_ref())
+            .map(|c| c.nodes.as_slice())
+            .unwrap_or_default();
+
+        // Check suites come oldest first; keep the newest run per workflow.
+        let mut seen = HashSet::new();
+        let mut pipelines: Vec<_> = suites
+            .iter()
+            .rev()
+            .filter_map(|suite| Some((suite, suite.workflow_run.as_ref()?)))
+            .filter(|(_, run)| seen.insert(run.workflow.name.clone()))
+            .map(|(suite, run)| suite.to_pipeline_summary(run))
+            .collect();
+        pipelines.reverse();
+
+        let triggers = suites
+            .iter()
+            .filter_map(|suite| suite.workflow_run.as_ref())
+            .map(|run| event_trigger(&run.event, true))
+            .fold(Triggers::empty(), |acc, t| acc | t);
+
+        let last_status = history
+            .first()
+            .and_then(|c| c.status_check_rollup.as_ref())
+            .map(RollupNode::pipeline_status)
+            .unwrap_or(PipelineStatus::Pending);
+
+        RepoSummary {
+            owner: self.owner.login,
+            repo: self.name,
+            description: self.description.unwrap_or_default(),
+            success_rate: success_rate(
+                history
+                    .iter()
+                    .filter_map(|c| c.status_check_rollup.as_ref())
+                    .map(RollupNode::pipeline_status),
+            ),
+            last_status,
+            triggers,
+            deps_total: 0,
+            deps_up_to_date: 0,
+            vulnerabilities: 0,
+            pipelines,
+            dependencies: Vec::new(),
+            fetched_at: Timestamp::now(),
+        }
+    }
+}
+
+impl GitHubClient {
+    /// Returns the GraphQL endpoint matching the configured REST API URL.
+    ///
+    /// GitHub Enterprise Server serves REST under `/api/v3` but GraphQL
+    /// under `/api/graphql`; elsewhere GraphQL lives at `/graphql`.
+    pub(super) fn graphql_endpoint(&self) -> Url {
+        let mut url = self.api_url.clone();
+        if url.path().trim_end_matches('/').ends_with("/api/v3") {
+            url.path_segments_mut()
+                .expect("cannot be base")
+                .pop_if_empty()
+                .pop()
+                .push("graphql");
+            url
+        } else {
+            self.endpoint(&["graphql"])
+        }
+    }
+
+    async fn graphql<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T, Error> {
+        let body = Request {
+            query: format!("{query}\n{}", repository_fields()),
+            variables,
+        };
+        let request = self
+            .request(Method::POST, self.graphql_endpoint())
+            .await?
+            .json(&body);
+        let response: Response<T> = self.send_json(request).await?;
+
+        if !response.errors.is_empty() {
+            let messages: Vec<_> = response.errors.into_iter().map(|e| e.message).collect();
+            return Err(Error::GraphQl(messages.join("; ")));
+        }
+        response
+            .data
+            .ok_or_else(|| Error::GraphQl("response contained no data".into()))
+    }
+
+    /// Summarizes every non-archived repository in an organization.
+    ///
+    /// Repositories are fetched in pages of [`PAGE_SIZE`], so an
+    /// organization of 200 repositories costs four requests.
+    pub async fn fetch_org_repos(&self, org: &str) -> Result<Vec<RepoSummary>, Error> {
+        let nodes = self.org_repositories(org).await?;
+        Ok(nodes
+            .into_iter()
+            .map(RepositoryNode::into_repo_summary)
+            .collect())
+    }
+
+    async fn org_repositories(&self, org: &str) -> Result<Vec<RepositoryNode>, Error> {
+        let mut nodes = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let variables = json!({
+                "org": org,
+                "cursor": cursor,
+                "pageSize": PAGE_SIZE,
+                "history": HISTORY_DEPTH,
+                "checkRuns": CHECK_RUNS,
+            });
+            let data: OrgData = self.graphql(ORG_QUERY, variables).await?;
+            let repositories = data
+                .organization
+                .ok_or_else(|| Error::GraphQl(format!("organization `{org}` not found")))?
+                .repositories;
+
+            nodes.extend(repositories.nodes);
+            match repositories.page_info {
+                Some(PageInfo {
+                    has_next_page: true,
+                    end_cursor: Some(next),
+                }) => cursor = Some(next),
+                _ => return Ok(nodes),
+            }
+        }
+    }
+
+    /// Summarizes a single repository using the GraphQL API.
+    pub async fn fetch_repo_summary(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<RepoSummary>, Error> {
+        let node = self.repository(owner, repo).await?;
+        Ok(node.map(RepositoryNode::into_repo_summary))
+    }
+
+    async fn repository(&self, owner: &str, repo: &str) -> Result<Option<RepositoryNode>, Error> {
+        let variables = json!({
+            "owner": owner,
+            "name": repo,
+            "history": HISTORY_DEPTH,
+            "checkRuns": CHECK_RUNS,
+        });
+        let data: RepoData = self.graphql(REPO_QUERY, variables).await?;
+        Ok(data.repository)
+    }
+}
+
+/// Serves every repository in a GitHub organization via GraphQL.
+#[derive(Debug, Clone)]
+pub struct OrgRepos {
+    client: GitHubClient,
+    org: String,
+    history: Recorder,
+    registries: Registries,
+    advisories: Arc<AdvisoryDb>,
+}
+
+impl OrgRepos {
+    /// Creates a source summarizing the repositories of `org`.
+    pub fn new(client: GitHubClient, org: impl Into<String>) -> Self {
+        Self {
+            client,
+            org: org.into(),
+            history: Recorder::default(),
+            registries: Registries::default(),
+            advisories: Arc::default(),
+        }
+    }
+
+    /// Records every run this source fetches in `history`.
+    pub fn with_history(mut self, history: Arc<dyn RunHistory>) -> Self {
+        self.history = Recorder::new(history);
+        self
+    }
+
+    /// Looks up the latest version of dependencies in `registries`.
+    pub fn with_registries(mut self, registries: Registries) -> Self {
+        self.registries = registries;
+        self
+    }
+
+    /// Matches dependencies against the advisories in `advisories`.
+    pub fn with_advisories(mut self, advisories: Arc<AdvisoryDb>) -> Self {
+        self.advisories = advisories;
+        self
+    }
+
+    async fn summarize(&self, nodes: Vec<RepositoryNode>) -> Vec<RepoSummary> {
+        let records: Vec<_> = nodes.iter().flat_map(RepositoryNode::run_records).collect();
+        self.history.record(&records).await;
+        stream::iter(nodes)
+            .map(|node| self.summarize_one(node))
+            .buffered(CONCURRENT_SUMMARIES)
+            .collect()
+            .await
+    }
+
+    async fn summarize_one(&self, mut node: RepositoryNode) -> RepoSummary {
+        let default_branch = node.default_branch_ref.as_ref().map(|b| b.name.clone());
+        let prefetched = node.take_prefetched();
+        let mut summary = node.into_repo_summary();
+        let (owner, repo) = (summary.owner.clone(), summary.repo.clone());
+        // The query read the workflow files and root manifests; only
+        // workspace members and files too large for the query are read
+        // through the contents API.
+        if let Some(default_branch) = default_branch {
+            match (self.client)
+                .fetch_workflow_triggers(&owner, &repo, &default_branch, &prefetched)
+                .await
+            {
+                Ok(workflows) => summary.set_workflow_triggers(&workflows),
+                Err(err) => {
+                    tracing::warn!(error = %err, %owner, %repo, "failed to read workflow files")
+                }
+            }
+        }
+        match (self.client)
+            .fetch_dependencies(&owner, &repo, &prefetched)
+            .await
+        {
+            Ok(mut dependencies) => {
+                self.registries.fill(&mut dependencies).await;
+                self.advisories.annotate(&mut dependencies);
+                summary.set_dependencies(dependencies);
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, %owner, %repo, "failed to extract dependencies")
+            }
+        }
+        summary
+    }
+}
+
+#[async_trait]
+impl RepoSource for OrgRepos {
+    async fn list_repos(&self) -> Result<Vec<RepoSummary>, SourceError> {
+        let nodes = self.client.org_repositories(&self.org).await?;
+        Ok(self.summarize(nodes).await)
+    }
+
+    async fn find_repo(&self, owner: &str, repo: &str) -> Result<Option<RepoSummary>, SourceError> {
+        if !owner.eq_ignore_ascii_case(&self.org) {
+            return Ok(None);
+        }
+        let node = self.client.repository(owner, repo).await?;
+        Ok(self.summarize(node.into_iter().collect()).await.pop())
+    }
+
+    async fn list_jobs(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<Vec<JobSummary>, SourceError> {
+        if !owner.eq_ignore_ascii_case(&self.org) {
+            return Ok(Vec::new());
+        }
+        Ok(self.client.job_summaries(owner, repo, run_id).await?)
+    }
+}
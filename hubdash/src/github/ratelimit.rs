@@ -0,0 +1,192 @@
+//! Tracking of GitHub's rate limits and throttling of outgoing requests.
+//!
+//! Every response updates the budget of the resource it counted against
+//! (`core`, `graphql`, ...). Requests are spaced out evenly over the time
+//! left until the budget resets once it runs low, and are held back
+//! entirely while a secondary rate limit's `Retry-After` is in effect.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use jiff::{SignedDuration, Timestamp};
+use reqwest::{StatusCode, header::HeaderMap};
+
+use super::Error;
+
+/// Below this many remaining requests, calls are spread out until reset.
+const LOW_WATERMARK: u32 = 100;
+
+/// Longest a request is queued before failing with
+/// [`Error::RateLimited`] instead.
+const MAX_WAIT: SignedDuration = SignedDuration::from_secs(30);
+
+/// Secondary limits without a `Retry-After` are waited out this long.
+const DEFAULT_RETRY_AFTER: SignedDuration = SignedDuration::from_mins(1);
+
+/// The rate limit budget of one GitHub API resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Budget {
+    /// The resource the budget applies to, e.g. `core` or `graphql`.
+    pub resource: String,
+    /// Requests allowed per window.
+    pub limit: u32,
+    /// Requests left in the current window.
+    pub remaining: u32,
+    /// When the current window ends and the budget is replenished.
+    pub reset: Timestamp,
+}
+
+#[derive(Default)]
+struct State {
+    budgets: BTreeMap<String, Budget>,
+    /// Set while a secondary rate limit is in effect.
+    paused_until: Option<Timestamp>,
+    /// Earliest time the next queued request may be sent.
+    next_slot: Option<Timestamp>,
+}
+
+/// Shared view of a client's rate limits.
+///
+/// Cloning is cheap and yields a handle to the same state, so the budget
+/// can be shown elsewhere while the client keeps updating it.
+#[derive(Clone, Default)]
+pub struct RateLimits {
+    state: Arc<Mutex<State>>,
+}
+
+impl std::fmt::Debug for RateLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimits")
+            .field("budgets", &self.budgets())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Waits for `duration`; without a runtime to provide timers, requests are
+/// sent right away and only failed once the budget is gone.
+async fn sleep(duration: SignedDuration) {
+    #[cfg(feature = "tokio")]
+    tokio::time::sleep(duration.unsigned_abs()).await;
+    #[cfg(not(feature = "tokio"))]
+    let _ = duration;
+}
+
+fn header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+impl RateLimits {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("rate limit state poisoned")
+    }
+
+    /// Returns the last known budget of every resource used so far.
+    pub fn budgets(&self) -> Vec<Budget> {
+        self.state().budgets.values().cloned().collect()
+    }
+
+    /// Returns when requests may resume if a resource's budget is used up,
+    /// a secondary rate limit is in effect or the queue is too long to wait
+    /// for.
+    pub fn limited_until(&self) -> Option<Timestamp> {
+        let state = self.state();
+        let now = Timestamp::now();
+        let exhausted = state
+            .budgets
+            .values()
+            .filter(|b| b.remaining == 0 && b.reset > now)
+            .map(|b| b.reset);
+        let queued = state
+            .next_slot
+            .filter(|slot| slot.duration_since(now) > MAX_WAIT);
+        exhausted
+            .chain(state.paused_until.filter(|until| *until > now))
+            .chain(queued)
+            .max()
+    }
+
+    /// Reserves a slot for a request against `resource`, waiting for it if
+    /// the wait is short and failing otherwise.
+    pub(super) async fn acquire(&self, resource: &str) -> Result<(), Error> {
+        let slot = {
+            let mut state = self.state();
+            let now = Timestamp::now();
+            let mut earliest = state.paused_until.unwrap_or(now).max(now);
+            let mut spacing = SignedDuration::ZERO;
+            if let Some(budget) = state.budgets.get(resource).filter(|b| b.reset > now) {
+                if budget.remaining == 0 {
+                    earliest = earliest.max(budget.reset);
+                } else if budget.remaining < LOW_WATERMARK {
+                    spacing = budget.reset.duration_since(now)
+                        / i32::try_from(budget.remaining).unwrap_or(i32::MAX);
+                }
+            }
+            let slot = state.next_slot.unwrap_or(now).max(earliest);
+            if slot.duration_since(now) > MAX_WAIT {
+                return Err(Error::RateLimited { until: slot });
+            }
+            state.next_slot = Some(slot + spacing);
+            slot
+        };
+
+        let wait = slot.duration_since(Timestamp::now());
+        if wait.is_positive() {
+            tracing::debug!(%resource, wait = %wait, "throttling GitHub request");
+            sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    /// Records the rate limit headers of a response.
+    ///
+    /// Returns the time requests may resume if the response reports that a
+    /// primary or secondary rate limit was hit.
+    pub(super) fn observe(&self, status: StatusCode, headers: &HeaderMap) -> Option<Timestamp> {
+        let mut state = self.state();
+        let now = Timestamp::now();
+
+        let budget = match (
+            header::<String>(headers, "X-RateLimit-Resource"),
+            header::<u32>(headers, "X-RateLimit-Limit"),
+            header::<u32>(headers, "X-RateLimit-Remaining"),
+            header::<i64>(headers, "X-RateLimit-Reset")
+                .and_then(|s| Timestamp::from_second(s).ok()),
+        ) {
+            (resource, Some(limit), Some(remaining), Some(reset)) => {
+                let budget = Budget {
+                    resource: resource.unwrap_or_else(|| "core".into()),
+                    limit,
+                    remaining,
+                    reset,
+                };
+                state
+                    .budgets
+                    .insert(budget.resource.clone(), budget.clone());
+                Some(budget)
+            }
+            _ => None,
+        };
+
+        if !matches!(
+            status,
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+        ) {
+            return None;
+        }
+        if let Some(retry_after) = header::<i64>(headers, "Retry-After") {
+            let until = now + SignedDuration::from_secs(retry_after);
+            state.paused_until = Some(until);
+            return Some(until);
+        }
+        match budget {
+            Some(budget) if budget.remaining == 0 => Some(budget.reset),
+            // Secondary limits do not always say how long to back off.
+            _ if status == StatusCode::TOO_MANY_REQUESTS => {
+                let until = now + DEFAULT_RETRY_AFTER;
+                state.paused_until = Some(until);
+                Some(until)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! A [`RepoSource`] backed by the GitHub REST API.
+
+use std::{fmt, str::FromStr, sync::Arc};
+
+use async_trait::async_trait;
+use futures_util::{StreamExt, stream};
+use reqwest::StatusCode;
+
+use super::contents::Prefetched;
+use super::{CONCURRENT_SUMMARIES, Error, GitHubClient, Repository, RunQuery, WorkflowRun};
+use crate::advisories::AdvisoryDb;
+use crate::dashboard::{JobSummary, RepoSummary};
+use crate::history::{Recorder, RunHistory};
+use crate::registry::Registries;
+use crate::source::{RepoSource, SourceError};
+
+/// Number of recent runs used to summarize a repository.
+const RECENT_RUNS: u8 = 50;
+
+/// An `owner/repo` pair identifying a GitHub repository.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RepoRef {
+    /// The user or organization owning the repository.
+    pub owner: String,
+    /// The repository name.
+    pub repo: String,
+}
+
+impl RepoRef {
+    /// Whether this names `owner/repo`, ignoring case as GitHub does.
+    fn is(&self, owner: &str, repo: &str) -> bool {
+        self.owner.eq_ignore_ascii_case(owner) && self.repo.eq_ignore_ascii_case(repo)
+    }
+}
+
+impl fmt::Display for RepoRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.repo)
+    }
+}
+
+impl FromStr for RepoRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() && !repo.contains('/') => {
+                Ok(Self {
+                    owner: owner.into(),
+                    repo: repo.into(),
+                })
+            }
+            _ => Err(format!("expected `owner/repo`, got `{s}`")),
+        }
+    }
+}
+
+/// Which repositories a [`GitHubRepos`] source shows.
+#[derive(Debug, Clone)]
+enum Selection {
+    Fixed(Vec<RepoRef>),
+    Installation,
+}
+
+/// Serves repositories from the GitHub REST API.
+#[derive(Debug, Clone)]
+pub struct GitHubRepos {
+    client: GitHubClient,
+    selection: Selection,
+    history: Recorder,
+    registries: Registries,
+    advisories: Arc<AdvisoryDb>,
+}
+
+impl GitHubRepos {
+    /// Creates a source summarizing `repos` through `client`.
+    pub fn new(client: GitHubClient, repos: Vec<RepoRef>) -> Self {
+        Self {
+            client,
+            selection: Selection::Fixed(repos),
+            history: Recorder::default(),
+            registries: Registries::default(),
+            advisories: Arc::default(),
+        }
+    }
+
+    /// Creates a source summarizing every repository the client's GitHub
+    /// App installation can access.
+    pub fn installation(client: GitHubClient) -> Self {
+        Self {
+            client,
+            selection: Selection::Installation,
+            history: Recorder::default(),
+            registries: Registries::default(),
+            advisories: Arc::default(),
+        }
+    }
+
+    /// Records every run this source fetches in `history`.
+    pub fn with_history(mut self, history: Arc<dyn RunHistory>) -> Self {
+        self.history = Recorder::new(history);
+        self
+    }
+
+    /// Looks up the latest version of dependencies in `registries`.
+    pub fn with_registries(mut self, registries: Registries) -> Self {
+        self.registries = registries;
+        self
+    }
+
+    /// Matches dependencies against the advisories in `advisories`.
+    pub fn with_advisories(mut self, advisories: Arc<AdvisoryDb>) -> Self {
+        self.advisories = advisories;
+        self
+    }
+
+    async fn recent_runs(&self, owner: &str, repo: &str) -> Result<Vec<WorkflowRun>, Error> {
+        let query = RunQuery {
+            per_page: Some(RECENT_RUNS),
+            ..RunQuery::default()
+        };
+        self.client.list_workflow_runs(owner, repo, &query).await
+    }
+
+    /// Whether `owner/repo` is one of the repositories this source shows.
+    async fn selects(&self, owner: &str, repo: &str) -> Result<bool, Error> {
+        match &self.selection {
+            Selection::Fixed(repos) => Ok(repos.iter().any(|r| r.is(owner, repo))),
+            Selection::Installation => {
+                Ok(self.client.installation_repo(owner, repo).await?.is_some())
+            }
+        }
+    }
+
+    async fn summarize(&self, repository: &Repository) -> Result<RepoSummary, Error> {
+        let (owner, repo) = (&repository.owner.login, &repository.name);
+        let runs = self.recent_runs(owner, repo).await?;
+        let records: Vec<_> = runs
+            .iter()
+            .map(|run| run.to_run_record(owner, repo))
+            .collect();
+        self.history.record(&records).await;
+        let mut summary = repository.to_repo_summary(&runs);
+        // Nothing is read ahead; every file comes from the contents API.
+        let no_files = Prefetched::default();
+        match (self.client)
+            .fetch_workflow_triggers(owner, repo, &repository.default_branch, &no_files)
+            .await
+        {
+            Ok(workflows) => summary.set_workflow_triggers(&workflows),
+            Err(err) => tracing::warn!(error = %err, owner, repo, "failed to read workflow files"),
+        }
+        match self.client.fetch_dependencies(owner, repo, &no_files).await {
+            Ok(mut dependencies) => {
+                self.registries.fill(&mut dependencies).await;
+                self.advisories.annotate(&mut dependencies);
+                summary.set_dependencies(dependencies);
+            }
+            Err(err) => tracing::warn!(error = %err, owner, repo, "failed to extract dependencies"),
+        }
+        Ok(summary)
+    }
+
+    async fn fetch_and_summarize(&self, r: RepoRef) -> (RepoRef, Result<RepoSummary, Error>) {
+        let summary = match self.client.get_repository(&r.owner, &r.repo).await {
+            Ok(repository) => self.summarize(&repository).await,
+            Err(err) => Err(err),
+        };
+        (r, summary)
+    }
+}
+
+/// Keeps the repositories that could be summarized, logging the rest.
+///
+/// Being rate limited fails the whole list, as does every repository
+/// failing, so the summaries listed last keep being served instead.
+fn keep_summarized(
+    results: Vec<(RepoRef, Result<RepoSummary, Error>)>,
+) -> Result<Vec<RepoSummary>, SourceError> {
+    let mut summaries = Vec::with_capacity(results.len());
+    let mut last_error = None;
+    for (repo_ref, result) in results {
+        match result {
+            Ok(summary) => summaries.push(summary),
+            Err(Error::RateLimited { until }) => return Err(SourceError::RateLimited { until }),
+            Err(err) => {
+                tracing::warn!(error = %err, repo = %repo_ref, "failed to summarize repository");
+                last_error = Some(err);
+            }
+        }
+    }
+    match last_error {
+        Some(err) if summaries.is_empty() => Err(err.into()),
+        _ => Ok(summaries),
+    }
+}
+
+#[async_trait]
+impl RepoSource for GitHubRepos {
+    async fn list_repos(&self) -> Result<Vec<RepoSummary>, SourceError> {
+        let results = match &self.selection {
+            Selection::Fixed(repos) => {
+                stream::iter(repos.iter().cloned())
+                    .map(|r| self.fetch_and_summarize(r))
+                    .buffered(CONCURRENT_SUMMARIES)
+                    .collect()
+                    .await
+            }
+            Selection::Installation => {
+                let repositories = self.client.list_installation_repos().await?;
+                stream::iter(repositories)
+                    .map(|r| async move {
+                        let repo_ref = RepoRef {
+                            owner: r.owner.login.clone(),
+                            repo: r.name.clone(),
+                        };
+                        (repo_ref, self.summarize(&r).await)
+                    })
+                    .buffered(CONCURRENT_SUMMARIES)
+                    .collect()
+                    .await
+            }
+        };
+        keep_summarized(results)
+    }
+
+    async fn find_repo(&self, owner: &str, repo: &str) -> Result<Option<RepoSummary>, SourceError> {
+        let repository = match &self.selection {
+            Selection::Fixed(repos) if !repos.iter().any(|r| r.is(owner, repo)) => return Ok(None),
+            Selection::Fixed(_) => match self.client.get_repository(owner, repo).await {
+                Ok(repository) => repository,
+                Err(Error::Status { status, .. }) if status == StatusCode::NOT_FOUND => {
+                    return Ok(None);
+                }
+                Err(err) => return Err(err.into()),
+            },
+            Selection::Installation => match self.client.installation_repo(owner, repo).await? {
+                Some(repository) => repository,
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(self.summarize(&repository).await?))
+    }
+
+    async fn list_jobs(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<Vec<JobSummary>, SourceError> {
+        if !self.selects(owner, repo).await? {
+            return Ok(Vec::new());
+        }
+        Ok(self.client.job_summaries(owner, repo, run_id).await?)
+    }
+}
@@ -0,0 +1,544 @@
+//! GitHub Actions REST endpoints and their mapping onto dashboard types.
+
+use std::collections::HashSet;
+
+use jiff::{SignedDuration, Timestamp};
+use serde::Deserialize;
+use url::Url;
+
+use super::{Error, GitHubClient, Page};
+use crate::dashboard::{
+    JobSummary, PipelineStatus, PipelineSummary, RepoSummary, StepSummary, Triggers,
+};
+use crate::history::RunRecord;
+
+/// A repository as returned by `GET /repos/{owner}/{repo}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Repository {
+    /// The repository name.
+    pub name: String,
+    /// The account owning the repository.
+    pub owner: RepositoryOwner,
+    /// The repository description, if one is set.
+    pub description: Option<String>,
+    /// The branch pushes to "main" are measured against.
+    pub default_branch: String,
+    /// Link to the repository on GitHub.
+    pub html_url: Url,
+}
+
+/// The owner of a [`Repository`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct RepositoryOwner {
+    /// The user or organization login.
+    pub login: String,
+}
+
+/// The authenticated user as returned by `GET /user`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    /// The user's login.
+    pub login: String,
+    /// The user's display name, if one is set.
+    pub name: Option<String>,
+}
+
+/// A workflow definition.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Workflow {
+    /// The workflow ID.
+    pub id: u64,
+    /// The workflow name.
+    pub name: String,
+    /// Path of the workflow file within the repository.
+    pub path: String,
+    /// Whether the workflow is `active` or disabled.
+    pub state: String,
+    /// Link to the workflow file on GitHub.
+    pub html_url: Url,
+}
+
+#[derive(Deserialize)]
+struct WorkflowList {
+    total_count: usize,
+    workflows: Vec<Workflow>,
+}
+
+impl Page for WorkflowList {
+    type Item = Workflow;
+
+    fn into_parts(self) -> (usize, Vec<Workflow>) {
+        (self.total_count, self.workflows)
+    }
+}
+
+/// A single run of a workflow.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkflowRun {
+    /// The run ID.
+    pub id: u64,
+    /// The workflow name at the time of the run.
+    pub name: Option<String>,
+    /// The ID of the workflow this run belongs to.
+    pub workflow_id: u64,
+    /// The branch the run was triggered for.
+    pub head_branch: Option<String>,
+    /// The commit the run was triggered for.
+    pub head_sha: String,
+    /// The event that triggered the run.
+    pub event: String,
+    /// The run's lifecycle status, e.g. `queued` or `completed`.
+    pub status: Option<String>,
+    /// The run's outcome once completed, e.g. `success` or `failure`.
+    pub conclusion: Option<String>,
+    /// Link to the run on GitHub.
+    pub html_url: Url,
+    /// When the run was created.
+    pub created_at: Timestamp,
+    /// When the run was last updated.
+    pub updated_at: Timestamp,
+    /// When the current attempt of the run started.
+    pub run_started_at: Option<Timestamp>,
+}
+
+#[derive(Deserialize)]
+struct WorkflowRunList {
+    workflow_runs: Vec<WorkflowRun>,
+}
+
+/// A job within a workflow run.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Job {
+    /// The job ID.
+    pub id: u64,
+    /// The ID of the run this job belongs to.
+    pub run_id: u64,
+    /// Name of the workflow this job belongs to.
+    pub workflow_name: Option<String>,
+    /// The job name.
+    pub name: String,
+    /// The job's lifecycle status.
+    pub status: String,
+    /// The job's outcome once completed.
+    pub conclusion: Option<String>,
+    /// When the job started.
+    pub started_at: Option<Timestamp>,
+    /// When the job finished.
+    pub completed_at: Option<Timestamp>,
+    /// Link to the job on GitHub.
+    pub html_url: Option<Url>,
+    /// Labels used to select the runner.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Name of the runner that picked up the job.
+    pub runner_name: Option<String>,
+    /// The job's steps.
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+/// A step within a [`Job`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Step {
+    /// The step's position within the job, starting at 1.
+    pub number: u32,
+    /// The step name.
+    pub name: String,
+    /// The step's lifecycle status.
+    pub status: String,
+    /// The step's outcome once completed.
+    pub conclusion: Option<String>,
+    /// When the step started.
+    pub started_at: Option<Timestamp>,
+    /// When the step finished.
+    pub completed_at: Option<Timestamp>,
+}
+
+#[derive(Deserialize)]
+struct JobList {
+    total_count: usize,
+    jobs: Vec<Job>,
+}
+
+impl Page for JobList {
+    type Item = Job;
+
+    fn into_parts(self) -> (usize, Vec<Job>) {
+        (self.total_count, self.jobs)
+    }
+}
+
+/// Filters for listing workflow runs.
+#[derive(Debug, Clone, Default)]
+pub struct RunQuery {
+    /// Only return runs for this branch.
+    pub branch: Option<String>,
+    /// Number of runs per page, at most 100.
+    pub per_page: Option<u8>,
+}
+
+/// Maps a GitHub status and conclusion pair onto a [`PipelineStatus`].
+///
+/// Runs, jobs and check suites share these values; unknown statuses are
+/// treated as pending and unknown conclusions as failures.
+pub(crate) fn pipeline_status(status: Option<&str>, conclusion: Option<&str>) -> PipelineStatus {
+    match status {
+        None | Some("completed") => {}
+        Some("requested" | "pending") => return PipelineStatus::Pending,
+        Some("queued") => return PipelineStatus::Queued,
+        Some("in_progress") => return PipelineStatus::InProgress,
+        Some("waiting") => return PipelineStatus::Waiting,
+        Some(status) => {
+            tracing::debug!(status, "unknown GitHub run status");
+            return PipelineStatus::Pending;
+        }
+    }
+    match conclusion {
+        Some("success") => PipelineStatus::Success,
+        Some("failure") => PipelineStatus::Failure,
+        Some("cancelled") => PipelineStatus::Cancelled,
+        Some("skipped") => PipelineStatus::Skipped,
+        Some("neutral") => PipelineStatus::Neutral,
+        Some("stale") => PipelineStatus::Stale,
+        Some("timed_out") => PipelineStatus::TimedOut,
+        Some("action_required") => PipelineStatus::ActionRequired,
+        Some("startup_failure") => PipelineStatus::StartupFailure,
+        Some(conclusion) => {
+            tracing::debug!(conclusion, "unknown GitHub run conclusion");
+            PipelineStatus::Failure
+        }
+        None => PipelineStatus::Pending,
+    }
+}
+
+impl WorkflowRun {
+    /// Returns the dashboard status of this run.
+    pub fn pipeline_status(&self) -> PipelineStatus {
+        pipeline_status(self.status.as_deref(), self.conclusion.as_deref())
+    }
+
+    /// Returns how long the run took, if it has completed.
+    pub fn run_time(&self) -> Option<SignedDuration> {
+        if self.status.as_deref() != Some("completed") {
+            return None;
+        }
+        let started = self.run_started_at.unwrap_or(self.created_at);
+        Some(self.updated_at.duration_since(started))
+    }
+
+    /// Converts this run into a record for the run history of
+    /// `owner/repo`.
+    pub fn to_run_record(&self, owner: &str, repo: &str) -> RunRecord {
+        RunRecord {
+            owner: owner.to_owned(),
+            repo: repo.to_owned(),
+            run_id: self.id,
+            workflow: self.name.clone().unwrap_or_else(|| "Workflow".into()),
+            status: self.pipeline_status(),
+            started_at: self.run_started_at.unwrap_or(self.created_at),
+            finished_at: (self.status.as_deref() == Some("completed")).then_some(self.updated_at),
+            head_sha: self.head_sha.clone(),
+            branch: self.head_branch.clone(),
+            url: self.html_url.clone(),
+        }
+    }
+
+    /// Converts this run into a pipeline summary.
+    pub fn to_pipeline_summary(&self) -> PipelineSummary {
+        PipelineSummary {
+            name: self.name.clone().unwrap_or_else(|| "Workflow".into()),
+            status: self.pipeline_status(),
+            run_time: self.run_time(),
+            github_url: self.html_url.clone(),
+            triggers: Triggers::empty(),
+            run_id: Some(self.id),
+        }
+    }
+}
+
+/// Returns how long something took that started at `started` and, if it
+/// has, completed at `completed`.
+fn elapsed(started: Option<Timestamp>, completed: Option<Timestamp>) -> Option<SignedDuration> {
+    Some(completed?.duration_since(started?))
+}
+
+impl Job {
+    /// Returns the dashboard status of this job.
+    pub fn pipeline_status(&self) -> PipelineStatus {
+        pipeline_status(Some(&self.status), self.conclusion.as_deref())
+    }
+
+    /// Converts this job and its steps into a job summary.
+    pub fn to_job_summary(&self) -> JobSummary {
+        JobSummary {
+            name: self.name.clone(),
+            status: self.pipeline_status(),
+            run_time: elapsed(self.started_at, self.completed_at),
+            runner_labels: self.labels.clone(),
+            runner_name: self.runner_name.clone(),
+            github_url: self.html_url.clone(),
+            steps: self.steps.iter().map(Step::to_step_summary).collect(),
+        }
+    }
+}
+
+impl Step {
+    /// Converts this step into a step summary.
+    pub fn to_step_summary(&self) -> StepSummary {
+        StepSummary {
+            number: self.number,
+            name: self.name.clone(),
+            status: pipeline_status(Some(&self.status), self.conclusion.as_deref()),
+            run_time: elapsed(self.started_at, self.completed_at),
+        }
+    }
+}
+
+/// Computes the percentage of finished runs that succeeded.
+///
+/// Only runs that passed or failed count, as [`PipelineStatus::passed`]
+/// decides; with no such runs the rate is reported as 100%.
+pub(crate) fn success_rate(statuses: impl IntoIterator<Item = PipelineStatus>) -> u8 {
+    let (successes, finished) = statuses
+        .into_iter()
+        .fold((0u32, 0u32), |(ok, total), status| match status.passed() {
+            Some(true) => (ok + 1, total + 1),
+            Some(false) => (ok, total + 1),
+            None => (ok, total),
+        });
+    if finished == 0 {
+        return 100;
+    }
+    ((successes as f64 / finished as f64) * 100.0).round() as u8
+}
+
+/// Maps the event that triggered a run onto the trigger it represents.
+///
+/// A run does not say whether a push was of a branch or a tag, so pushes
+/// off the default branch count as branch pushes.
+pub(crate) fn event_trigger(event: &str, on_default_branch: bool) -> Triggers {
+    match event {
+        "push" if on_default_branch => Triggers::MAIN,
+        "push" => Triggers::BRANCH,
+        "pull_request" | "pull_request_target" => Triggers::PR,
+        "merge_group" => Triggers::MERGE_GROUP,
+        "schedule" => Triggers::SCHEDULED,
+        "workflow_dispatch" => Triggers::MANUAL,
+        "release" => Triggers::RELEASE,
+        "repository_dispatch" => Triggers::DISPATCH,
+        _ => Triggers::empty(),
+    }
+}
+
+impl Repository {
+    /// Whether this is `owner/repo`, ignoring case as GitHub does.
+    pub(super) fn is(&self, owner: &str, repo: &str) -> bool {
+        self.owner.login.eq_ignore_ascii_case(owner) && self.name.eq_ignore_ascii_case(repo)
+    }
+
+    /// Builds a dashboard summary from this repository and its recent runs.
+    ///
+    /// `runs` must be ordered newest first, as GitHub returns them.
+    pub fn to_repo_summary(&self, runs: &[WorkflowRun]) -> RepoSummary {
+        let on_default =
+            |run: &WorkflowRun| run.head_branch.as_deref() == Some(self.default_branch.as_str());
+
+        let mut seen = HashSet::new();
+        let pipelines = runs
+            .iter()
+            .filter(|run| seen.insert(run.workflow_id))
+            .map(WorkflowRun::to_pipeline_summary)
+            .collect();
+
+        let triggers = runs
+            .iter()
+            .map(|run| event_trigger(&run.event, on_default(run)))
+            .fold(Triggers::empty(), |acc, t| acc | t);
+
+        let success_rate = success_rate(
+            runs.iter()
+                .filter(|run| on_default(run))
+                .map(WorkflowRun::pipeline_status),
+        );
+
+        RepoSummary {
+            owner: self.owner.login.clone(),
+            repo: self.name.clone(),
+            description: self.description.clone().unwrap_or_default(),
+            success_rate,
+            last_status: runs
+                .first()
+                .map(WorkflowRun::pipeline_status)
+                .unwrap_or(PipelineStatus::Pending),
+            triggers,
+            deps_total: 0,
+            deps_up_to_date: 0,
+            vulnerabilities: 0,
+            pipelines,
+            dependencies: Vec::new(),
+            fetched_at: Timestamp::now(),
+        }
+    }
+}
+
+impl GitHubClient {
+    /// Fetches the user the client's token belongs to.
+    pub async fn get_authenticated_user(&self) -> Result<User, Error> {
+        self.get_json(self.endpoint(&["user"])).await
+    }
+
+    /// Revokes an OAuth access token issued to the given app.
+    pub async fn revoke_oauth_token(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        access_token: &str,
+    ) -> Result<(), Error> {
+        let url = self.endpoint(&["applications", client_id, "token"]);
+        let response = self
+            .http
+            .delete(url)
+            .basic_auth(client_id, Some(client_secret))
+            .json(&serde_json::json!({ "access_token": access_token }))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(Error::Status { status, message });
+        }
+        Ok(())
+    }
+
+    /// Fetches repository metadata.
+    pub async fn get_repository(&self, owner: &str, repo: &str) -> Result<Repository, Error> {
+        self.get_json(self.endpoint(&["repos", owner, repo])).await
+    }
+
+    /// Lists the workflows defined in a repository.
+    pub async fn list_workflows(&self, owner: &str, repo: &str) -> Result<Vec<Workflow>, Error> {
+        let url = self.endpoint(&["repos", owner, repo, "actions", "workflows"]);
+        self.get_all::<WorkflowList>(url).await
+    }
+
+    /// Lists recent workflow runs in a repository, newest first.
+    pub async fn list_workflow_runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        query: &RunQuery,
+    ) -> Result<Vec<WorkflowRun>, Error> {
+        let mut url = self.endpoint(&["repos", owner, repo, "actions", "runs"]);
+        let mut params = Vec::new();
+        if let Some(branch) = &query.branch {
+            params.push(("branch", branch.clone()));
+        }
+        if let Some(per_page) = query.per_page {
+            params.push(("per_page", per_page.to_string()));
+        }
+        if !params.is_empty() {
+            url.query_pairs_mut().extend_pairs(params);
+        }
+        let list: WorkflowRunList = self.get_json(url).await?;
+        Ok(list.workflow_runs)
+    }
+
+    /// Fetches a single workflow run.
+    pub async fn get_workflow_run(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<WorkflowRun, Error> {
+        let run_id = run_id.to_string();
+        let url = self.endpoint(&["repos", owner, repo, "actions", "runs", &run_id]);
+        self.get_json(url).await
+    }
+
+    /// Lists the jobs of the latest attempt of a workflow run.
+    pub async fn list_jobs(&self, owner: &str, repo: &str, run_id: u64) -> Result<Vec<Job>, Error> {
+        let run_id = run_id.to_string();
+        let url = self.endpoint(&["repos", owner, repo, "actions", "runs", &run_id, "jobs"]);
+        self.get_all::<JobList>(url).await
+    }
+
+    /// Lists the jobs of a workflow run as job summaries, with none for a
+    /// run that does not exist.
+    pub(crate) async fn job_summaries(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<Vec<JobSummary>, Error> {
+        match self.list_jobs(owner, repo, run_id).await {
+            Ok(jobs) => Ok(jobs.iter().map(Job::to_job_summary).collect()),
+            Err(Error::Status { status, .. }) if status == reqwest::StatusCode::NOT_FOUND => {
+                Ok(Vec::new())
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipeline_status_maps_every_status_and_conclusion() {
+        for (status, conclusion, expected) in [
+            (Some("requested"), None, PipelineStatus::Pending),
+            (Some("pending"), None, PipelineStatus::Pending),
+            (Some("queued"), None, PipelineStatus::Queued),
+            (Some("in_progress"), None, PipelineStatus::InProgress),
+            (Some("waiting"), None, PipelineStatus::Waiting),
+            // A conclusion only counts once the run has completed.
+            (
+                Some("in_progress"),
+                Some("failure"),
+                PipelineStatus::InProgress,
+            ),
+            (Some("completed"), Some("success"), PipelineStatus::Success),
+            (Some("completed"), Some("failure"), PipelineStatus::Failure),
+            (
+                Some("completed"),
+                Some("cancelled"),
+                PipelineStatus::Cancelled,
+            ),
+            (Some("completed"), Some("skipped"), PipelineStatus::Skipped),
+            (Some("completed"), Some("neutral"), PipelineStatus::Neutral),
+            (Some("completed"), Some("stale"), PipelineStatus::Stale),
+            (
+                Some("completed"),
+                Some("timed_out"),
+                PipelineStatus::TimedOut,
+            ),
+            (
+                Some("completed"),
+                Some("action_required"),
+                PipelineStatus::ActionRequired,
+            ),
+            (
+                Some("completed"),
+                Some("startup_failure"),
+                PipelineStatus::StartupFailure,
+            ),
+            (Some("completed"), None, PipelineStatus::Pending),
+            // Without a status, the conclusion decides.
+            (None, Some("success"), PipelineStatus::Success),
+            (None, None, PipelineStatus::Pending),
+            (Some("unheard_of"), None, PipelineStatus::Pending),
+            (
+                Some("completed"),
+                Some("unheard_of"),
+                PipelineStatus::Failure,
+            ),
+        ] {
+            assert_eq!(
+                pipeline_status(status, conclusion),
+                expected,
+                "{status:?} {conclusion:?}"
+            );
+        }
+    }
+}
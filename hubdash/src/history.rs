@@ -0,0 +1,384 @@
+//! Storage for the history of observed workflow runs.
+//!
+//! Sources and webhooks record every run they see, so that success rates
+//! and trends can be computed from real history rather than a snapshot.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use jiff::{SignedDuration, Timestamp};
+use url::Url;
+
+use crate::dashboard::PipelineStatus;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteHistory;
+
+/// A single observed workflow run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunRecord {
+    /// The user or organization owning the repository.
+    pub owner: String,
+    /// The repository name.
+    pub repo: String,
+    /// The GitHub ID of the run.
+    pub run_id: u64,
+    /// The name of the workflow the run belongs to.
+    pub workflow: String,
+    /// The run's status when it was last observed.
+    pub status: PipelineStatus,
+    /// When the run started.
+    pub started_at: Timestamp,
+    /// When the run finished, if it has.
+    pub finished_at: Option<Timestamp>,
+    /// The commit the run was triggered for.
+    pub head_sha: String,
+    /// The branch the run was triggered for.
+    pub branch: Option<String>,
+    /// Link to the run on GitHub.
+    pub url: Url,
+}
+
+/// Which of a repository's runs to return from a [`RunHistory`].
+#[derive(Debug, Clone, Default)]
+pub struct RunFilter {
+    /// Only runs that started at or after this time.
+    pub since: Option<Timestamp>,
+    /// Only runs of this workflow.
+    pub workflow: Option<String>,
+    /// Only runs on this branch.
+    pub branch: Option<String>,
+    /// At most this many of the most recent runs.
+    pub limit: Option<u32>,
+}
+
+impl RunFilter {
+    fn matches(&self, run: &RunRecord) -> bool {
+        self.since.is_none_or(|since| run.started_at >= since)
+            && self.workflow.as_ref().is_none_or(|w| *w == run.workflow)
+            && self
+                .branch
+                .as_ref()
+                .is_none_or(|b| run.branch.as_ref() == Some(b))
+    }
+}
+
+/// The span of history a success rate is computed over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Window {
+    /// Runs that started within this many days.
+    Days(u16),
+    /// This many of the most recent runs.
+    Runs(u32),
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self::Days(30)
+    }
+}
+
+impl Window {
+    /// Returns the filter selecting this window's runs as of `now`.
+    pub(crate) fn filter(self, now: Timestamp) -> RunFilter {
+        match self {
+            Self::Days(days) => RunFilter {
+                since: Some(now - SignedDuration::from_hours(i64::from(days) * 24)),
+                ..RunFilter::default()
+            },
+            Self::Runs(runs) => RunFilter {
+                limit: Some(runs),
+                ..RunFilter::default()
+            },
+        }
+    }
+
+    /// Describes the window for display, e.g. `last 30 days`.
+    pub(crate) fn label(self) -> String {
+        match self {
+            Self::Days(1) => "last day".into(),
+            Self::Days(days) => format!("last {days} days"),
+            Self::Runs(1) => "last run".into(),
+            Self::Runs(runs) => format!("last {runs} runs"),
+        }
+    }
+}
+
+/// Formats the window as accepted by [`Window::from_str`], e.g. `30d` or
+/// `20runs`.
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Days(days) => write!(f, "{days}d"),
+            Self::Runs(runs) => write!(f, "{runs}runs"),
+        }
+    }
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let window = if let Some(runs) = s.strip_suffix("runs") {
+            runs.parse().ok().map(Self::Runs)
+        } else if let Some(days) = s.strip_suffix('d') {
+            days.parse().ok().map(Self::Days)
+        } else {
+            None
+        };
+        match window {
+            Some(Self::Days(0) | Self::Runs(0)) | None => Err(format!(
+                "expected a window like `30d` or `20runs`, got `{s}`"
+            )),
+            Some(window) => Ok(window),
+        }
+    }
+}
+
+/// Error returned when run history cannot be read or written.
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    /// The underlying storage failed.
+    #[error("run history unavailable: {0}")]
+    Storage(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// A store of observed workflow runs.
+#[async_trait]
+pub trait RunHistory: Send + Sync {
+    /// Stores runs, replacing earlier observations of the same runs.
+    async fn record(&self, runs: &[RunRecord]) -> Result<(), HistoryError>;
+
+    /// Returns a repository's runs matching `filter`, newest first.
+    async fn runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        filter: &RunFilter,
+    ) -> Result<Vec<RunRecord>, HistoryError>;
+}
+
+/// Where sources and webhooks write the runs they observe, if anywhere.
+///
+/// Failing to record a run never fails the request that observed it; the
+/// error is logged instead.
+#[derive(Clone, Default)]
+pub(crate) struct Recorder(Option<Arc<dyn RunHistory>>);
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Recorder").field(&self.0.is_some()).finish()
+    }
+}
+
+impl Recorder {
+    pub(crate) fn new(history: Arc<dyn RunHistory>) -> Self {
+        Self(Some(history))
+    }
+
+    /// Returns the history runs are recorded in, if any.
+    pub(crate) fn history(&self) -> Option<&dyn RunHistory> {
+        self.0.as_deref()
+    }
+
+    pub(crate) async fn record(&self, runs: &[RunRecord]) {
+        let Some(history) = &self.0 else {
+            return;
+        };
+        if runs.is_empty() {
+            return;
+        }
+        if let Err(err) = history.record(runs).await {
+            tracing::warn!(error = %err, runs = runs.len(), "failed to record workflow runs");
+        }
+    }
+}
+
+/// Run history kept in memory and lost on restart.
+#[derive(Debug, Default)]
+pub struct MemoryHistory {
+    runs: Mutex<Vec<RunRecord>>,
+}
+
+impl MemoryHistory {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl From<Vec<RunRecord>> for MemoryHistory {
+    fn from(runs: Vec<RunRecord>) -> Self {
+        Self {
+            runs: Mutex::new(runs),
+        }
+    }
+}
+
+fn same_run(a: &RunRecord, b: &RunRecord) -> bool {
+    a.run_id == b.run_id
+        && a.owner.eq_ignore_ascii_case(&b.owner)
+        && a.repo.eq_ignore_ascii_case(&b.repo)
+}
+
+#[async_trait]
+impl RunHistory for MemoryHistory {
+    async fn record(&self, runs: &[RunRecord]) -> Result<(), HistoryError> {
+        let mut stored = self.runs.lock().expect("run history poisoned");
+        for run in runs {
+            match stored.iter_mut().find(|r| same_run(r, run)) {
+                Some(existing) => *existing = run.clone(),
+                None => stored.push(run.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    async fn runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        filter: &RunFilter,
+    ) -> Result<Vec<RunRecord>, HistoryError> {
+        let stored = self.runs.lock().expect("run history poisoned");
+        let mut runs: Vec<_> = stored
+            .iter()
+            .filter(|r| r.owner.eq_ignore_ascii_case(owner) && r.repo.eq_ignore_ascii_case(repo))
+            .filter(|r| filter.matches(r))
+            .cloned()
+            .collect();
+        runs.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        if let Some(limit) = filter.limit {
+            runs.truncate(limit as usize);
+        }
+        Ok(runs)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A run of `example/hubdash`'s `CI` workflow.
+    pub(crate) fn run(run_id: u64, status: PipelineStatus, started_at: Timestamp) -> RunRecord {
+        RunRecord {
+            owner: "example".to_owned(),
+            repo: "hubdash".to_owned(),
+            run_id,
+            workflow: "CI".to_owned(),
+            status,
+            started_at,
+            finished_at: None,
+            head_sha: "abc123".to_owned(),
+            branch: Some("main".to_owned()),
+            url: format!("https://github.com/example/hubdash/actions/runs/{run_id}")
+                .parse()
+                .unwrap(),
+        }
+    }
+
+    fn hours_ago(now: Timestamp, hours: i64) -> Timestamp {
+        now - SignedDuration::from_hours(hours)
+    }
+
+    #[test]
+    fn window_parses_days_and_runs() {
+        for (input, expected) in [
+            ("30d", Some(Window::Days(30))),
+            ("1d", Some(Window::Days(1))),
+            ("20runs", Some(Window::Runs(20))),
+            ("0d", None),
+            ("0runs", None),
+            ("d", None),
+            ("runs", None),
+            ("30", None),
+            ("-7d", None),
+            ("30D", None),
+            ("70000d", None),
+            ("twenty runs", None),
+            ("", None),
+        ] {
+            assert_eq!(input.parse::<Window>().ok(), expected, "{input}");
+        }
+        for window in [Window::Days(7), Window::Runs(20)] {
+            assert_eq!(window.to_string().parse::<Window>(), Ok(window));
+        }
+    }
+
+    #[test]
+    fn window_labels_read_naturally() {
+        for (window, expected) in [
+            (Window::Days(1), "last day"),
+            (Window::Days(30), "last 30 days"),
+            (Window::Runs(1), "last run"),
+            (Window::Runs(20), "last 20 runs"),
+        ] {
+            assert_eq!(window.label(), expected);
+        }
+    }
+
+    #[test]
+    fn window_filters_by_age_or_count() {
+        let now = Timestamp::now();
+
+        let days = Window::Days(7).filter(now);
+        assert_eq!(days.since, Some(hours_ago(now, 7 * 24)));
+        assert_eq!(days.limit, None);
+
+        let runs = Window::Runs(20).filter(now);
+        assert_eq!(runs.since, None);
+        assert_eq!(runs.limit, Some(20));
+    }
+
+    #[tokio::test]
+    async fn memory_history_returns_matching_runs_newest_first() {
+        let now = Timestamp::now();
+        let history = MemoryHistory::new();
+        let mut deploy = run(4, PipelineStatus::Success, hours_ago(now, 1));
+        deploy.workflow = "Deploy".to_owned();
+        history
+            .record(&[
+                run(1, PipelineStatus::Success, hours_ago(now, 72)),
+                run(2, PipelineStatus::InProgress, hours_ago(now, 2)),
+                run(3, PipelineStatus::Failure, hours_ago(now, 3)),
+                deploy,
+            ])
+            .await
+            .unwrap();
+        // A later observation of a run replaces the earlier one.
+        history
+            .record(&[run(2, PipelineStatus::Success, hours_ago(now, 2))])
+            .await
+            .unwrap();
+
+        let ids = |runs: Vec<RunRecord>| runs.iter().map(|r| r.run_id).collect::<Vec<_>>();
+        let all = history
+            .runs("Example", "HubDash", &RunFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(all[1].status, PipelineStatus::Success);
+        assert_eq!(ids(all), [4, 2, 3, 1]);
+
+        let ci = RunFilter {
+            workflow: Some("CI".to_owned()),
+            ..Window::Days(1).filter(now)
+        };
+        let recent = history.runs("example", "hubdash", &ci).await.unwrap();
+        assert_eq!(ids(recent), [2, 3]);
+
+        let last = Window::Runs(3).filter(now);
+        let last = history.runs("example", "hubdash", &last).await.unwrap();
+        assert_eq!(ids(last), [4, 2, 3]);
+
+        let other = history
+            .runs("example", "other", &RunFilter::default())
+            .await
+            .unwrap();
+        assert!(other.is_empty());
+    }
+}
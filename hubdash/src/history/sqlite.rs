@@ -0,0 +1,268 @@
+//! Run history persisted in a SQLite database.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use jiff::Timestamp;
+use rusqlite::{Connection, Row, params};
+
+use super::{HistoryError, RunFilter, RunHistory, RunRecord};
+use crate::dashboard::PipelineStatus;
+
+/// Schema migrations, applied in order.
+///
+/// The database's `user_version` records how many have been applied, so
+/// new migrations must only ever be appended.
+const MIGRATIONS: &[&str] = &[r#"
+CREATE TABLE runs (
+    owner TEXT NOT NULL COLLATE NOCASE,
+    repo TEXT NOT NULL COLLATE NOCASE,
+    run_id INTEGER NOT NULL,
+    workflow TEXT NOT NULL,
+    status TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER,
+    head_sha TEXT NOT NULL,
+    branch TEXT,
+    url TEXT NOT NULL,
+    PRIMARY KEY (owner, repo, run_id)
+);
+CREATE INDEX runs_by_start ON runs (owner, repo, started_at DESC);
+"#];
+
+impl From<rusqlite::Error> for HistoryError {
+    fn from(err: rusqlite::Error) -> Self {
+        HistoryError::Storage(Box::new(err))
+    }
+}
+
+impl From<tokio::task::JoinError> for HistoryError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        HistoryError::Storage(Box::new(err))
+    }
+}
+
+/// Returns the key a status is stored under, which unlike its display
+/// name must never change.
+fn status_key(status: PipelineStatus) -> &'static str {
+    match status {
+        PipelineStatus::Success => "success",
+        PipelineStatus::Failure => "failure",
+        PipelineStatus::Pending => "pending",
+        PipelineStatus::Queued => "queued",
+        PipelineStatus::InProgress => "in_progress",
+        PipelineStatus::Waiting => "waiting",
+        PipelineStatus::Cancelled => "cancelled",
+        PipelineStatus::Skipped => "skipped",
+        PipelineStatus::Neutral => "neutral",
+        PipelineStatus::Stale => "stale",
+        PipelineStatus::TimedOut => "timed_out",
+        PipelineStatus::ActionRequired => "action_required",
+        PipelineStatus::StartupFailure => "startup_failure",
+    }
+}
+
+fn parse_status(key: &str) -> PipelineStatus {
+    PipelineStatus::ALL
+        .into_iter()
+        .find(|&status| status_key(status) == key)
+        .unwrap_or_else(|| {
+            tracing::warn!(key, "unknown run status in history");
+            PipelineStatus::Pending
+        })
+}
+
+fn timestamp(seconds: i64) -> rusqlite::Result<Timestamp> {
+    Timestamp::from_second(seconds).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Integer, err.into())
+    })
+}
+
+fn run_from_row(row: &Row<'_>) -> rusqlite::Result<RunRecord> {
+    let url: String = row.get("url")?;
+    Ok(RunRecord {
+        owner: row.get("owner")?,
+        repo: row.get("repo")?,
+        // Run IDs are stored as SQLite's signed integers.
+        run_id: row.get::<_, i64>("run_id")? as u64,
+        workflow: row.get("workflow")?,
+        status: parse_status(&row.get::<_, String>("status")?),
+        started_at: timestamp(row.get("started_at")?)?,
+        finished_at: row
+            .get::<_, Option<i64>>("finished_at")?
+            .map(timestamp)
+            .transpose()?,
+        head_sha: row.get("head_sha")?,
+        branch: row.get("branch")?,
+        url: url.parse().map_err(|err: url::ParseError| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, err.into())
+        })?,
+    })
+}
+
+/// Run history stored in a SQLite database file.
+#[derive(Clone)]
+pub struct SqliteHistory {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl std::fmt::Debug for SqliteHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteHistory").finish_non_exhaustive()
+    }
+}
+
+impl SqliteHistory {
+    /// Opens or creates the database at `path` and brings its schema up to
+    /// date.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, HistoryError> {
+        Self::migrate(Connection::open(path)?)
+    }
+
+    /// Creates a database that only lives in memory.
+    pub fn open_in_memory() -> Result<Self, HistoryError> {
+        Self::migrate(Connection::open_in_memory()?)
+    }
+
+    fn migrate(mut conn: Connection) -> Result<Self, HistoryError> {
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        let applied: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        for (version, migration) in (0u32..).zip(MIGRATIONS).skip(applied as usize) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", version + 1)?;
+            tx.commit()?;
+            tracing::info!(version = version + 1, "migrated run history database");
+        }
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Runs `f` against the connection on the blocking thread pool.
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, HistoryError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().expect("run history connection poisoned");
+            f(&mut conn)
+        })
+        .await?;
+        Ok(result?)
+    }
+}
+
+#[async_trait]
+impl RunHistory for SqliteHistory {
+    async fn record(&self, runs: &[RunRecord]) -> Result<(), HistoryError> {
+        let runs = runs.to_vec();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut upsert = tx.prepare_cached(
+                    "INSERT INTO runs (owner, repo, run_id, workflow, status, started_at, \
+                     finished_at, head_sha, branch, url) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10) \
+                     ON CONFLICT (owner, repo, run_id) DO UPDATE SET \
+                     workflow = excluded.workflow, status = excluded.status, \
+                     started_at = excluded.started_at, finished_at = excluded.finished_at, \
+                     head_sha = excluded.head_sha, branch = excluded.branch, url = excluded.url",
+                )?;
+                for run in &runs {
+                    upsert.execute(params![
+                        run.owner,
+                        run.repo,
+                        run.run_id as i64,
+                        run.workflow,
+                        status_key(run.status),
+                        run.started_at.as_second(),
+                        run.finished_at.map(Timestamp::as_second),
+                        run.head_sha,
+                        run.branch,
+                        run.url.as_str(),
+                    ])?;
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        filter: &RunFilter,
+    ) -> Result<Vec<RunRecord>, HistoryError> {
+        let (owner, repo, filter) = (owner.to_owned(), repo.to_owned(), filter.clone());
+        self.with_conn(move |conn| {
+            let mut query = conn.prepare_cached(
+                "SELECT * FROM runs \
+                 WHERE owner = ?1 AND repo = ?2 \
+                 AND (?3 IS NULL OR started_at >= ?3) \
+                 AND (?4 IS NULL OR workflow = ?4) \
+                 AND (?5 IS NULL OR branch = ?5) \
+                 ORDER BY started_at DESC, run_id DESC \
+                 LIMIT ?6",
+            )?;
+            let rows = query.query_map(
+                params![
+                    owner,
+                    repo,
+                    filter.since.map(Timestamp::as_second),
+                    filter.workflow,
+                    filter.branch,
+                    filter.limit.map_or(-1, i64::from),
+                ],
+                run_from_row,
+            )?;
+            rows.collect()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(run_id: u64, status: PipelineStatus) -> RunRecord {
+        RunRecord {
+            owner: "example".to_owned(),
+            repo: "hubdash".to_owned(),
+            run_id,
+            workflow: "CI".to_owned(),
+            status,
+            started_at: Timestamp::from_second(1_700_000_000 + run_id as i64).unwrap(),
+            finished_at: None,
+            head_sha: "abc123".to_owned(),
+            branch: Some("main".to_owned()),
+            url: "https://github.com/example/hubdash/actions/runs/1"
+                .parse()
+                .unwrap(),
+        }
+    }
+
+    async fn statuses(history: &SqliteHistory) -> Vec<PipelineStatus> {
+        let runs = history
+            .runs("example", "hubdash", &RunFilter::default())
+            .await
+            .unwrap();
+        runs.into_iter().rev().map(|run| run.status).collect()
+    }
+
+    #[tokio::test]
+    async fn stores_every_status() {
+        let history = SqliteHistory::open_in_memory().unwrap();
+        let runs: Vec<_> = (0..)
+            .zip(PipelineStatus::ALL)
+            .map(|(run_id, status)| run(run_id, status))
+            .collect();
+        history.record(&runs).await.unwrap();
+        assert_eq!(statuses(&history).await, PipelineStatus::ALL);
+    }
+}
@@ -4,20 +4,169 @@
 //! This is split into a library crate in order to enable deployment
 //! in either a Cloudflare Worker or a standalone server.
 
-use axum::{Router, routing::get};
+use std::sync::Arc;
 
+use axum::{
+    Router, middleware,
+    routing::{get, post},
+};
+use jiff::SignedDuration;
+
+use crate::cache::RepoCache;
+use crate::github::RateLimits;
+use crate::history::Recorder;
+use crate::session::SessionStore;
+use crate::webhook::Webhooks;
+
+mod advisories;
 mod assets;
+mod auth;
+mod cache;
+mod charts;
 mod dashboard;
-mod github;
+mod deps;
+pub mod github;
+mod history;
 mod landing;
 mod layout;
 mod mocks;
+pub mod registry;
+mod session;
+mod source;
+mod status;
+mod trends;
+mod versions;
+mod webhook;
+mod workflows;
+
+pub use advisories::{AdvisoryDb, AdvisoryError, Severity, Vulnerability};
+pub use auth::{DEFAULT_AUTHORIZE_URL, DEFAULT_TOKEN_URL, OAuthConfig};
+pub use dashboard::{
+    Dependency, DependencyKind, Ecosystem, JobSummary, PipelineStatus, PipelineSummary,
+    RepoSummary, StepSummary, Triggers,
+};
+#[cfg(feature = "sqlite")]
+pub use history::SqliteHistory;
+pub use history::{HistoryError, MemoryHistory, RunFilter, RunHistory, RunRecord};
+pub use mocks::MockRepos;
+pub use source::{RepoSource, SourceError};
+pub use versions::VersionGap;
+
+/// Shared state handed to every request handler.
+#[derive(Clone)]
+pub struct AppState {
+    repos: RepoCache,
+    oauth: Option<Arc<OAuthConfig>>,
+    sessions: SessionStore,
+    webhooks: Option<Arc<Webhooks>>,
+    rate_limits: Option<RateLimits>,
+    history: Recorder,
+}
+
+impl AppState {
+    /// Creates application state backed by the given repository source.
+    pub fn new(repos: impl RepoSource + 'static) -> Self {
+        Self {
+            repos: RepoCache::new(Arc::new(repos)),
+            oauth: None,
+            sessions: SessionStore::default(),
+            webhooks: None,
+            rate_limits: None,
+            history: Recorder::default(),
+        }
+    }
+
+    /// Enables signing in with the given GitHub OAuth app.
+    ///
+    /// Once enabled, the dashboard is only served to signed-in users.
+    pub fn with_oauth(mut self, config: OAuthConfig) -> Self {
+        self.oauth = Some(Arc::new(config));
+        self
+    }
+
+    /// Sets how long a session lasts after signing in.
+    pub fn with_session_ttl(mut self, ttl: SignedDuration) -> Self {
+        self.sessions = SessionStore::new(ttl);
+        self
+    }
+
+    /// Sets how long repository data is cached.
+    ///
+    /// Data older than `ttl` is revalidated in the background and served
+    /// stale for at most `stale_ttl` longer; after that, requests wait for
+    /// fresh data.
+    pub fn with_cache_ttl(mut self, ttl: SignedDuration, stale_ttl: SignedDuration) -> Self {
+        self.repos = RepoCache::with_ttl(self.repos.source(), ttl, stale_ttl);
+        self
+    }
+
+    /// Enables the GitHub webhook receiver, verifying deliveries with the
+    /// given secret.
+    ///
+    /// Deliveries update the cached repository data as soon as they
+    /// arrive, ahead of the next revalidation.
+    pub fn with_webhook_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.webhooks = Some(Arc::new(Webhooks::new(secret.into())));
+        self
+    }
+
+    /// Shows the given GitHub rate limits on the status page and warns on
+    /// the dashboard while they are exhausted.
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Self {
+        self.rate_limits = Some(limits);
+        self
+    }
+
+    /// Computes success rates from `history` and records the workflow runs
+    /// reported by webhooks in it.
+    ///
+    /// Sources record the runs they fetch themselves; see
+    /// [`GitHubRepos::with_history`](github::GitHubRepos::with_history).
+    pub fn with_history(mut self, history: Arc<dyn RunHistory>) -> Self {
+        self.history = Recorder::new(history);
+        self
+    }
+
+    /// Returns the repository source used by the dashboard.
+    pub fn repos(&self) -> &dyn RepoSource {
+        &self.repos
+    }
+
+    fn cache(&self) -> &RepoCache {
+        &self.repos
+    }
+
+    fn oauth(&self) -> Option<&OAuthConfig> {
+        self.oauth.as_deref()
+    }
+
+    fn sessions(&self) -> &SessionStore {
+        &self.sessions
+    }
+
+    fn webhooks(&self) -> Option<&Webhooks> {
+        self.webhooks.as_deref()
+    }
+
+    fn rate_limits(&self) -> Option<&RateLimits> {
+        self.rate_limits.as_ref()
+    }
+
+    fn history(&self) -> &Recorder {
+        &self.history
+    }
+
+    fn run_history(&self) -> Option<&dyn RunHistory> {
+        self.history.history()
+    }
+}
 
 /// Creates an Axum router for the Hubdash application.
-pub fn create_router() -> Router {
-    Router::new()
-        .route("/", get(landing::landing_page))
+pub fn create_router(state: AppState) -> Router {
+    let dashboard = Router::new()
         .route("/dashboard", get(dashboard::dashboard_page))
+        .route("/dashboard/status", get(status::status_page))
+        .route("/dashboard/repo/{owner}/{repo}", get(trends::repo_page))
         .route(
             "/dashboard/repo/{owner}/{repo}/expand",
             get(dashboard::repo_expand),
@@ -26,5 +175,22 @@ pub fn create_router() -> Router {
             "/dashboard/repo/{owner}/{repo}/deps",
             get(dashboard::repo_deps),
         )
+        .route(
+            "/dashboard/repo/{owner}/{repo}/run/{run_id}/jobs",
+            get(dashboard::run_jobs),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            session::require_session,
+        ));
+
+    Router::new()
+        .route("/", get(landing::landing_page))
+        .route("/auth/signin", get(auth::signin))
+        .route("/auth/callback", get(auth::callback))
+        .route("/auth/signout", post(auth::signout))
+        .route("/webhooks/github", post(webhook::receive))
+        .merge(dashboard)
+        .with_state(state)
         .nest("/assets", assets::router())
 }
@@ -1,7 +1,15 @@
 //! The Hubdash binary.
 
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use clap::Parser;
+use hubdash::github::{self, AppCredentials, GitHubClient, GitHubRepos, OrgRepos, RepoRef};
+use hubdash::registry::{self, CratesIndex, NpmRegistry, PyPI, Registries};
+use hubdash::{AdvisoryDb, RunHistory};
+use jiff::SignedDuration;
 use tracing_subscriber::{EnvFilter, fmt};
+use url::Url;
 
 /// The Hubdash server CLI.
 #[derive(Parser, Debug)]
@@ -14,6 +22,102 @@ struct Args {
     /// Enable verbose logging
     #[arg(short, long, env = "LOG_LEVEL", default_value = "info")]
     log_level: String,
+
+    /// Base URL of the GitHub REST API
+    #[arg(long, env = "GITHUB_API_URL", default_value = github::DEFAULT_API_URL)]
+    github_api_url: Url,
+
+    /// Token used to authenticate against the GitHub API
+    #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+    github_token: Option<String>,
+
+    /// ID of the GitHub App to authenticate as instead of using a token
+    #[arg(
+        long,
+        env = "GITHUB_APP_ID",
+        requires_all = ["github_app_installation_id", "github_app_private_key"],
+        conflicts_with = "github_token"
+    )]
+    github_app_id: Option<u64>,
+
+    /// ID of the GitHub App installation to act on behalf of
+    #[arg(long, env = "GITHUB_APP_INSTALLATION_ID", requires = "github_app_id")]
+    github_app_installation_id: Option<u64>,
+
+    /// Path to the GitHub App's PEM-encoded private key
+    #[arg(long, env = "GITHUB_APP_PRIVATE_KEY_PATH", requires = "github_app_id")]
+    github_app_private_key: Option<PathBuf>,
+
+    /// Repositories to show, as `owner/repo`; if empty, a GitHub App shows
+    /// every repository it is installed on, otherwise mock data is shown
+    #[arg(long = "repo", env = "HUBDASH_REPOS", value_delimiter = ',')]
+    repos: Vec<RepoRef>,
+
+    /// Show every repository in this organization, fetched via GraphQL
+    #[arg(long, env = "HUBDASH_ORG", conflicts_with = "repos")]
+    org: Option<String>,
+
+    /// Client ID of the GitHub OAuth app used for sign-in
+    #[arg(long, env = "GITHUB_CLIENT_ID", requires = "github_client_secret")]
+    github_client_id: Option<String>,
+
+    /// Client secret of the GitHub OAuth app used for sign-in
+    #[arg(long, env = "GITHUB_CLIENT_SECRET", hide_env_values = true)]
+    github_client_secret: Option<String>,
+
+    /// OAuth authorization endpoint
+    #[arg(long, env = "OAUTH_AUTHORIZE_URL", default_value = hubdash::DEFAULT_AUTHORIZE_URL)]
+    oauth_authorize_url: Url,
+
+    /// OAuth token endpoint
+    #[arg(long, env = "OAUTH_TOKEN_URL", default_value = hubdash::DEFAULT_TOKEN_URL)]
+    oauth_token_url: Url,
+
+    /// How long repository data is cached before it is revalidated
+    #[arg(long, env = "CACHE_TTL", default_value = "1m")]
+    cache_ttl: SignedDuration,
+
+    /// How long stale repository data is served while it is revalidated
+    #[arg(long, env = "CACHE_STALE_TTL", default_value = "10m")]
+    cache_stale_ttl: SignedDuration,
+
+    /// Base URL of the crates.io sparse index, or a mirror of it
+    #[arg(long, env = "CRATES_INDEX_URL", default_value = registry::DEFAULT_CRATES_INDEX_URL)]
+    crates_index_url: Url,
+
+    /// Base URL of the npm registry, or a mirror of it
+    #[arg(long, env = "NPM_REGISTRY_URL", default_value = registry::DEFAULT_NPM_REGISTRY_URL)]
+    npm_registry_url: Url,
+
+    /// Base URL of PyPI, or a mirror serving its JSON API
+    #[arg(long, env = "PYPI_URL", default_value = registry::DEFAULT_PYPI_URL)]
+    pypi_url: Url,
+
+    /// How long the latest version of a package is cached
+    #[arg(long, env = "REGISTRY_CACHE_TTL", default_value = "1h")]
+    registry_cache_ttl: SignedDuration,
+
+    /// Directory of OSV advisories, such as the RustSec or GitHub advisory
+    /// database, to match dependencies against
+    #[arg(long, env = "HUBDASH_ADVISORY_DB")]
+    advisory_db: Option<PathBuf>,
+
+    /// Secret used to verify deliveries to `/webhooks/github`
+    #[arg(long, env = "GITHUB_WEBHOOK_SECRET", hide_env_values = true)]
+    github_webhook_secret: Option<String>,
+
+    /// SQLite database the history of observed workflow runs is kept in
+    #[cfg(feature = "sqlite")]
+    #[arg(long, env = "HUBDASH_DATABASE", default_value = "hubdash.db")]
+    database: PathBuf,
+
+    /// Public URL of this server, used to build the OAuth callback URL
+    #[arg(long, env = "PUBLIC_URL")]
+    public_url: Option<Url>,
+
+    /// How long a sign-in lasts, e.g. `8h` or `30m`
+    #[arg(long, env = "SESSION_TTL", default_value = "8h")]
+    session_ttl: SignedDuration,
 }
 
 #[cfg(not(feature = "tokio"))]
@@ -30,7 +134,97 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_env_filter(EnvFilter::new(&args.log_level))
         .init();
 
-    let router = hubdash::create_router();
+    let mut client = GitHubClient::new(args.github_api_url.clone());
+    if let Some(token) = args.github_token {
+        client = client.with_token(token);
+    }
+    if let (Some(app_id), Some(installation_id), Some(key_path)) = (
+        args.github_app_id,
+        args.github_app_installation_id,
+        args.github_app_private_key,
+    ) {
+        let pem = std::fs::read(&key_path)?;
+        client = client.with_app(AppCredentials::from_pem(app_id, installation_id, &pem)?);
+    }
+
+    #[cfg(feature = "sqlite")]
+    let history: Arc<dyn RunHistory> = Arc::new(hubdash::SqliteHistory::open(&args.database)?);
+    #[cfg(not(feature = "sqlite"))]
+    let history: Arc<dyn RunHistory> = Arc::new(hubdash::MemoryHistory::new());
+
+    let registries = Registries::new()
+        .with_registry(CratesIndex::new(args.crates_index_url))
+        .with_registry(NpmRegistry::new(args.npm_registry_url))
+        .with_registry(PyPI::new(args.pypi_url))
+        .with_ttl(args.registry_cache_ttl);
+
+    let advisories = match &args.advisory_db {
+        Some(dir) => {
+            let db = AdvisoryDb::load(dir)?;
+            tracing::info!(advisories = db.len(), dir = %dir.display(), "loaded advisory database");
+            Arc::new(db)
+        }
+        None => Arc::default(),
+    };
+
+    let rate_limits = client.rate_limits();
+    let state = if let Some(org) = args.org {
+        let source = OrgRepos::new(client, org)
+            .with_history(history.clone())
+            .with_registries(registries)
+            .with_advisories(advisories);
+        hubdash::AppState::new(source)
+            .with_rate_limits(rate_limits)
+            .with_history(history)
+    } else if !args.repos.is_empty() {
+        let source = GitHubRepos::new(client, args.repos)
+            .with_history(history.clone())
+            .with_registries(registries)
+            .with_advisories(advisories);
+        hubdash::AppState::new(source)
+            .with_rate_limits(rate_limits)
+            .with_history(history)
+    } else if client.is_app_installation() {
+        let source = GitHubRepos::installation(client)
+            .with_history(history.clone())
+            .with_registries(registries)
+            .with_advisories(advisories);
+        hubdash::AppState::new(source)
+            .with_rate_limits(rate_limits)
+            .with_history(history)
+    } else {
+        tracing::info!("no repositories configured, serving mock data");
+        hubdash::AppState::new(hubdash::MockRepos)
+            .with_history(Arc::new(hubdash::MockRepos.history()))
+    };
+
+    let state = state.with_cache_ttl(args.cache_ttl, args.cache_stale_ttl);
+
+    let state = match args.github_webhook_secret {
+        Some(secret) => state.with_webhook_secret(secret),
+        None => state,
+    };
+
+    let state = match (args.github_client_id, args.github_client_secret) {
+        (Some(client_id), Some(client_secret)) => {
+            let mut oauth = hubdash::OAuthConfig::new(client_id, client_secret)
+                .with_authorize_url(args.oauth_authorize_url)
+                .with_token_url(args.oauth_token_url)
+                .with_api_url(args.github_api_url);
+            if let Some(public_url) = args.public_url {
+                oauth = oauth.with_redirect_url(public_url.join("auth/callback")?);
+            }
+            state.with_oauth(oauth).with_session_ttl(args.session_ttl)
+        }
+        _ => {
+            tracing::warn!(
+                "GitHub sign-in is not configured, the dashboard is publicly accessible"
+            );
+            state
+        }
+    };
+
+    let router = hubdash::create_router(state);
     let listener = tokio::net::TcpListener::bind(args.bind_address).await?;
     Ok(axum::serve(listener, router.into_make_service()).await?)
 }
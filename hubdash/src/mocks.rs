@@ -1,9 +1,20 @@
 //! Mock data for testing and development.
 
-use jiff::SignedDuration;
+use async_trait::async_trait;
+use jiff::{SignedDuration, Timestamp};
 use url::Url;
 
-use crate::dashboard::{Dependency, PipelineStatus, PipelineSummary, RepoSummary, Triggers};
+use crate::advisories::{Severity, Vulnerability};
+use crate::dashboard::{
+    Dependency, DependencyKind, Ecosystem, JobSummary, PipelineStatus, PipelineSummary,
+    RepoSummary, StepSummary, Triggers,
+};
+use crate::history::{MemoryHistory, RunRecord};
+use crate::source::{RepoSource, SourceError};
+
+/// A [`RepoSource`] serving the built-in mock data.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockRepos;
 
 fn github_actions_url(owner: &str, repo: &str, run_id: u64) -> Url {
     let mut url = Url::parse("https://github.com").expect("valid base URL");
@@ -19,6 +30,7 @@ fn github_actions_url(owner: &str, repo: &str, run_id: u64) -> Url {
 
 /// Returns mock repository data for development and testing.
 pub fn mock_repos() -> Vec<RepoSummary> {
+    let now = Timestamp::now();
     vec![
         RepoSummary {
             owner: "example".into(),
@@ -26,43 +38,59 @@ pub fn mock_repos() -> Vec<RepoSummary> {
             description: "A GitHub dashboard for monitoring CI/CD pipeline health".into(),
             success_rate: 95,
             last_status: PipelineStatus::Success,
-            triggers: Triggers::MAIN | Triggers::PR | Triggers::SCHEDULED,
+            triggers: Triggers::MAIN
+                | Triggers::PR
+                | Triggers::SCHEDULED
+                | Triggers::TAG
+                | Triggers::DISPATCH,
             deps_total: 42,
             deps_up_to_date: 40,
+            vulnerabilities: 0,
             pipelines: vec![
                 PipelineSummary {
                     name: "CI".into(),
                     status: PipelineStatus::Success,
                     run_time: Some(SignedDuration::new(154, 0)),
                     github_url: github_actions_url("example", "hubdash", 123),
+                    triggers: Triggers::MAIN | Triggers::PR | Triggers::DISPATCH,
+                    run_id: Some(123),
                 },
                 PipelineSummary {
                     name: "Deploy".into(),
                     status: PipelineStatus::Success,
                     run_time: Some(SignedDuration::new(312, 0)),
                     github_url: github_actions_url("example", "hubdash", 124),
+                    triggers: Triggers::MAIN | Triggers::SCHEDULED | Triggers::TAG,
+                    run_id: Some(124),
                 },
             ],
             dependencies: vec![
                 Dependency {
                     name: "axum".into(),
+                    ecosystem: Ecosystem::Cargo,
+                    kind: DependencyKind::Direct,
                     current_version: "0.7.5".into(),
-                    latest_version: "0.8.0".into(),
-                    is_outdated: true,
+                    latest_version: Some("0.8.0".into()),
+                    vulnerabilities: Vec::new(),
                 },
                 Dependency {
                     name: "tokio".into(),
+                    ecosystem: Ecosystem::Cargo,
+                    kind: DependencyKind::Direct,
                     current_version: "1.40.0".into(),
-                    latest_version: "1.40.0".into(),
-                    is_outdated: false,
+                    latest_version: Some("1.40.0".into()),
+                    vulnerabilities: Vec::new(),
                 },
                 Dependency {
                     name: "maud".into(),
+                    ecosystem: Ecosystem::Cargo,
+                    kind: DependencyKind::Direct,
                     current_version: "0.26.0".into(),
-                    latest_version: "0.27.0".into(),
-                    is_outdated: true,
+                    latest_version: Some("0.27.0".into()),
+                    vulnerabilities: Vec::new(),
                 },
             ],
+            fetched_at: now,
         },
         RepoSummary {
             owner: "example".into(),
@@ -70,21 +98,41 @@ pub fn mock_repos() -> Vec<RepoSummary> {
             description: "API gateway service for routing and authentication".into(),
             success_rate: 87,
             last_status: PipelineStatus::Failure,
-            triggers: Triggers::MAIN | Triggers::PR | Triggers::MANUAL,
+            triggers: Triggers::MAIN
+                | Triggers::BRANCH
+                | Triggers::PR
+                | Triggers::MERGE_GROUP
+                | Triggers::MANUAL,
             deps_total: 78,
             deps_up_to_date: 65,
+            vulnerabilities: 1,
             pipelines: vec![PipelineSummary {
                 name: "Build".into(),
                 status: PipelineStatus::Failure,
                 run_time: Some(SignedDuration::new(105, 0)),
                 github_url: github_actions_url("example", "api-gateway", 456),
+                triggers: Triggers::MAIN
+                    | Triggers::BRANCH
+                    | Triggers::PR
+                    | Triggers::MERGE_GROUP
+                    | Triggers::MANUAL,
+                run_id: Some(456),
             }],
             dependencies: vec![Dependency {
                 name: "express".into(),
+                ecosystem: Ecosystem::Npm,
+                kind: DependencyKind::Direct,
                 current_version: "4.18.0".into(),
-                latest_version: "4.21.0".into(),
-                is_outdated: true,
+                latest_version: Some("4.21.0".into()),
+                vulnerabilities: vec![Vulnerability {
+                    id: "GHSA-rv95-896h-c2vc".into(),
+                    aliases: vec!["CVE-2024-29041".into()],
+                    summary: "Express.js Open Redirect in malformed URLs".into(),
+                    severity: Some(Severity::Medium),
+                    fixed_version: Some("4.19.2".into()),
+                }],
             }],
+            fetched_at: now,
         },
         RepoSummary {
             owner: "example".into(),
@@ -95,13 +143,17 @@ pub fn mock_repos() -> Vec<RepoSummary> {
             triggers: Triggers::MAIN | Triggers::PR | Triggers::SCHEDULED | Triggers::MANUAL,
             deps_total: 156,
             deps_up_to_date: 156,
+            vulnerabilities: 0,
             pipelines: vec![PipelineSummary {
                 name: "Test".into(),
                 status: PipelineStatus::Success,
                 run_time: Some(SignedDuration::new(202, 0)),
                 github_url: github_actions_url("example", "frontend-app", 789),
+                triggers: Triggers::MAIN | Triggers::PR | Triggers::SCHEDULED | Triggers::MANUAL,
+                run_id: Some(789),
             }],
             dependencies: vec![],
+            fetched_at: now,
         },
         RepoSummary {
             owner: "example".into(),
@@ -112,18 +164,24 @@ pub fn mock_repos() -> Vec<RepoSummary> {
             triggers: Triggers::MAIN | Triggers::SCHEDULED,
             deps_total: 34,
             deps_up_to_date: 22,
+            vulnerabilities: 0,
             pipelines: vec![PipelineSummary {
                 name: "ETL".into(),
                 status: PipelineStatus::Pending,
                 run_time: None,
                 github_url: github_actions_url("example", "data-pipeline", 101),
+                triggers: Triggers::MAIN | Triggers::SCHEDULED,
+                run_id: Some(101),
             }],
             dependencies: vec![Dependency {
                 name: "pandas".into(),
+                ecosystem: Ecosystem::PyPI,
+                kind: DependencyKind::Direct,
                 current_version: "1.5.0".into(),
-                latest_version: "2.2.0".into(),
-                is_outdated: true,
+                latest_version: Some("2.2.0".into()),
+                vulnerabilities: Vec::new(),
             }],
+            fetched_at: now,
         },
         RepoSummary {
             owner: "example".into(),
@@ -134,15 +192,146 @@ pub fn mock_repos() -> Vec<RepoSummary> {
             triggers: Triggers::MAIN,
             deps_total: 89,
             deps_up_to_date: 31,
+            vulnerabilities: 0,
             pipelines: vec![],
             dependencies: vec![],
+            fetched_at: now,
         },
     ]
 }
 
-/// Finds a repository by owner and name from the mock data.
-pub fn find_repo(owner: &str, repo: &str) -> Option<RepoSummary> {
-    mock_repos()
-        .into_iter()
-        .find(|r| r.owner == owner && r.repo == repo)
+/// Number of runs in the mock history of each repository.
+const MOCK_RUNS: u64 = 60;
+
+/// Time between runs in the mock history, spreading them over 90 days.
+const MOCK_RUN_INTERVAL: SignedDuration = SignedDuration::from_hours(36);
+
+/// Returns mock runs for a repository, newest first.
+///
+/// The newest run has the repository's last status; older runs succeed at
+/// roughly its mock success rate.
+fn mock_runs(index: u64, repo: &RepoSummary, now: Timestamp) -> Vec<RunRecord> {
+    (0..MOCK_RUNS)
+        .map(|k| {
+            let pipeline = repo.pipelines.get(k as usize % repo.pipelines.len().max(1));
+            let status = if k == 0 {
+                repo.last_status
+            } else if (k * 37) % 100 < u64::from(repo.success_rate) {
+                PipelineStatus::Success
+            } else {
+                PipelineStatus::Failure
+            };
+            let run_id = (index + 1) * 10_000 + k;
+            let started_at = now - SignedDuration::from_mins(30) - MOCK_RUN_INTERVAL * k as i32;
+            let run_time = pipeline
+                .and_then(|p| p.run_time)
+                .unwrap_or(SignedDuration::from_mins(3));
+            RunRecord {
+                owner: repo.owner.clone(),
+                repo: repo.repo.clone(),
+                run_id,
+                workflow: pipeline.map_or_else(|| "CI".into(), |p| p.name.clone()),
+                status,
+                started_at,
+                finished_at: status.is_finished().then(|| started_at + run_time),
+                head_sha: format!("{run_id:040x}"),
+                branch: Some("main".into()),
+                url: github_actions_url(&repo.owner, &repo.repo, run_id),
+            }
+        })
+        .collect()
+}
+
+/// Returns a mock step numbered `number` that ended with `status`.
+fn mock_step(number: u32, name: &str, status: PipelineStatus, secs: i64) -> StepSummary {
+    StepSummary {
+        number,
+        name: name.into(),
+        status,
+        run_time: status
+            .is_finished()
+            .then(|| SignedDuration::from_secs(secs)),
+    }
+}
+
+/// Returns mock jobs for the latest run of a pipeline: a build job that
+/// passed and a test job that ended as the run did, or is still running.
+fn mock_jobs(pipeline: &PipelineSummary) -> Vec<JobSummary> {
+    let run_id = pipeline.run_id.unwrap_or_default();
+    let test = if pipeline.status.is_finished() {
+        pipeline.status
+    } else {
+        PipelineStatus::InProgress
+    };
+    let job = |number: u64, name: &str, status: PipelineStatus, command: &str| {
+        let id = run_id * 10 + number;
+        let mut github_url = pipeline.github_url.clone();
+        github_url
+            .path_segments_mut()
+            .expect("cannot be base")
+            .push("job")
+            .push(&id.to_string());
+        let rest = match status {
+            PipelineStatus::Success => PipelineStatus::Success,
+            status if status.is_finished() => PipelineStatus::Skipped,
+            _ => PipelineStatus::Pending,
+        };
+        JobSummary {
+            name: name.into(),
+            status,
+            run_time: status.is_finished().then(|| SignedDuration::from_secs(101)),
+            runner_labels: vec!["ubuntu-latest".into()],
+            runner_name: Some(format!("GitHub Actions {id}")),
+            github_url: Some(github_url),
+            steps: vec![
+                mock_step(1, "Set up job", PipelineStatus::Success, 2),
+                mock_step(2, "Checkout", PipelineStatus::Success, 3),
+                mock_step(3, command, status, 95),
+                mock_step(4, "Complete job", rest, 1),
+            ],
+        }
+    };
+    vec![
+        job(1, "build", PipelineStatus::Success, "Build"),
+        job(2, "test", test, "Run tests"),
+    ]
+}
+
+impl MockRepos {
+    /// Returns a run history to go with the mock data.
+    pub fn history(&self) -> MemoryHistory {
+        let now = Timestamp::now();
+        let runs = (0..)
+            .zip(mock_repos())
+            .flat_map(|(index, repo)| mock_runs(index, &repo, now))
+            .collect::<Vec<_>>();
+        MemoryHistory::from(runs)
+    }
+}
+
+#[async_trait]
+impl RepoSource for MockRepos {
+    async fn list_repos(&self) -> Result<Vec<RepoSummary>, SourceError> {
+        Ok(mock_repos())
+    }
+
+    async fn find_repo(&self, owner: &str, repo: &str) -> Result<Option<RepoSummary>, SourceError> {
+        Ok(mock_repos()
+            .into_iter()
+            .find(|r| r.owner == owner && r.repo == repo))
+    }
+
+    async fn list_jobs(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<Vec<JobSummary>, SourceError> {
+        Ok(self
+            .find_repo(owner, repo)
+            .await?
+            .and_then(|r| r.pipelines.into_iter().find(|p| p.run_id == Some(run_id)))
+            .map(|pipeline| mock_jobs(&pipeline))
+            .unwrap_or_default())
+    }
 }
@@ -0,0 +1,298 @@
+//! Looking up the newest published version of dependencies.
+//!
+//! Each ecosystem's package registry is queried through a [`Registry`],
+//! whose base URL can point at a mirror. [`Registries`] routes lookups to
+//! the registry for a dependency's ecosystem and caches the answers, so
+//! repositories sharing dependencies, and repeated refreshes, do not query
+//! the registry again until the cache's TTL has passed. Lookups run a few
+//! at a time, and each request is bounded by a timeout, so a slow registry
+//! cannot hold up the dashboard for long.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::{StreamExt, stream};
+use jiff::{SignedDuration, Timestamp};
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::{Response, StatusCode};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::dashboard::{Dependency, DependencyKind, Ecosystem};
+
+mod crates;
+mod npm;
+mod pypi;
+
+pub use crates::{CratesIndex, DEFAULT_CRATES_INDEX_URL};
+pub use npm::{DEFAULT_NPM_REGISTRY_URL, NpmRegistry};
+pub use pypi::{DEFAULT_PYPI_URL, PyPI};
+
+/// How long a looked-up version is used before asking the registry again
+/// by default.
+pub const DEFAULT_REGISTRY_TTL: SignedDuration = SignedDuration::from_hours(1);
+
+/// How long a failed lookup is remembered before asking the registry again.
+const FAILURE_TTL: SignedDuration = SignedDuration::from_mins(1);
+
+/// How many lookups are sent at once.
+const CONCURRENT_LOOKUPS: usize = 8;
+
+/// How long a registry has to accept a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a registry has to answer a request in full.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors returned by registry clients.
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    /// The request could not be sent or the response could not be read.
+    #[error("request to registry failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The registry answered with a non-success status code.
+    #[error("registry returned {0}")]
+    Status(StatusCode),
+    /// The response body was not the JSON that was expected.
+    #[error("could not decode registry response: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+impl RegistryError {
+    /// Whether the registry could not be reached or did not answer in
+    /// time, rather than failing for one package.
+    fn is_unreachable(&self) -> bool {
+        matches!(self, Self::Http(err) if err.is_connect() || err.is_timeout())
+    }
+}
+
+/// A package registry that knows the versions published in one ecosystem.
+#[async_trait]
+pub trait Registry: Send + Sync {
+    /// The ecosystem whose packages this registry serves.
+    fn ecosystem(&self) -> Ecosystem;
+
+    /// Returns the newest stable version of `package`, or `None` if the
+    /// registry does not know it.
+    async fn latest_version(&self, package: &str) -> Result<Option<String>, RegistryError>;
+}
+
+/// Builds the HTTP client registry clients share their settings through.
+fn http_client(mut headers: HeaderMap) -> reqwest::Client {
+    headers.insert(USER_AGENT, HeaderValue::from_static("hubdash"));
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("valid HTTP client configuration")
+}
+
+/// Returns `url` with `segments` appended to its path, percent-encoding
+/// each of them.
+fn join_segments<'a>(url: &Url, segments: impl IntoIterator<Item = &'a str>) -> Url {
+    let mut url = url.clone();
+    url.path_segments_mut()
+        .expect("registry URL cannot be a base")
+        .pop_if_empty()
+        .extend(segments);
+    url
+}
+
+/// Reads a registry response, treating `404 Not Found` as an unknown
+/// package.
+async fn read_response(response: Response) -> Result<Option<String>, RegistryError> {
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        status if status.is_success() => Ok(Some(response.text().await?)),
+        status => Err(RegistryError::Status(status)),
+    }
+}
+
+/// Fetches a JSON document, or `None` for an unknown package.
+async fn get_json<T: DeserializeOwned>(
+    http: &reqwest::Client,
+    url: Url,
+) -> Result<Option<T>, RegistryError> {
+    let Some(body) = read_response(http.get(url).send().await?).await? else {
+        return Ok(None);
+    };
+    Ok(Some(serde_json::from_str(&body)?))
+}
+
+struct Cached {
+    /// The latest version, or `None` if the lookup failed.
+    latest: Option<Option<String>>,
+    fetched_at: Timestamp,
+}
+
+impl Cached {
+    fn ttl(&self, ttl: SignedDuration) -> SignedDuration {
+        if self.latest.is_some() {
+            ttl
+        } else {
+            FAILURE_TTL
+        }
+    }
+}
+
+/// The registries latest versions are looked up in, with a cache of their
+/// answers in front of them.
+///
+/// Failed lookups are cached too, but only briefly, so they are retried
+/// on a later refresh.
+#[derive(Clone)]
+pub struct Registries {
+    registries: HashMap<Ecosystem, Arc<dyn Registry>>,
+    ttl: SignedDuration,
+    cache: Arc<Mutex<HashMap<(Ecosystem, String), Cached>>>,
+}
+
+impl Default for Registries {
+    fn default() -> Self {
+        Self {
+            registries: HashMap::new(),
+            ttl: DEFAULT_REGISTRY_TTL,
+            cache: Arc::default(),
+        }
+    }
+}
+
+impl fmt::Debug for Registries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registries")
+            .field("ecosystems", &self.registries.keys().collect::<Vec<_>>())
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Registries {
+    /// Creates an empty set of registries, which looks nothing up.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks up packages of `registry`'s ecosystem in it, replacing any
+    /// registry configured for that ecosystem before.
+    pub fn with_registry(mut self, registry: impl Registry + 'static) -> Self {
+        self.registries
+            .insert(registry.ecosystem(), Arc::new(registry));
+        self
+    }
+
+    /// Sets how long a looked-up version is used before asking again.
+    pub fn with_ttl(mut self, ttl: SignedDuration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Returns the cached answer for a package: its latest version, or
+    /// `None` if the lookup failed.
+    fn cached(&self, key: &(Ecosystem, String)) -> Option<Option<Option<String>>> {
+        let cache = self.cache.lock().expect("registry cache poisoned");
+        let entry = cache.get(key)?;
+        let age = Timestamp::now().duration_since(entry.fetched_at);
+        (age < entry.ttl(self.ttl)).then(|| entry.latest.clone())
+    }
+
+    fn store(&self, key: (Ecosystem, String), latest: Option<Option<String>>) {
+        let mut cache = self.cache.lock().expect("registry cache poisoned");
+        let now = Timestamp::now();
+        cache.retain(|_, entry| now.duration_since(entry.fetched_at) < entry.ttl(self.ttl));
+        cache.insert(
+            key,
+            Cached {
+                latest,
+                fetched_at: now,
+            },
+        );
+    }
+
+    /// Sets the latest version of every direct and development dependency
+    /// whose ecosystem has a registry.
+    ///
+    /// Transitive dependencies are skipped: a lockfile can list hundreds of
+    /// them, and they are upgraded through the packages that pull them in.
+    /// Once a registry cannot be reached, lookups in it that have not
+    /// started yet are given up, so an unreachable registry costs a few
+    /// failed requests rather than one per dependency. A package the
+    /// registry fails to answer for only fails its own lookup.
+    pub async fn fill(&self, dependencies: &mut [Dependency]) {
+        let mut seen = HashSet::new();
+        let packages = dependencies
+            .iter()
+            .filter(|d| d.kind != DependencyKind::Transitive)
+            .filter(|d| self.registries.contains_key(&d.ecosystem))
+            .map(|d| (d.ecosystem, d.name.clone()))
+            .filter(|key| seen.insert(key.clone()));
+
+        let mut answers = HashMap::new();
+        let mut missing = Vec::new();
+        for key in packages {
+            match self.cached(&key) {
+                Some(latest) => {
+                    answers.insert(key, latest);
+                }
+                None => missing.push(key),
+            }
+        }
+
+        let failed = Mutex::new(HashSet::new());
+        let lookups = stream::iter(missing)
+            .map(|key| self.look_up(key, &failed))
+            .buffer_unordered(CONCURRENT_LOOKUPS)
+            .collect::<Vec<_>>()
+            .await;
+        for (key, latest) in lookups {
+            self.store(key.clone(), latest.clone());
+            answers.insert(key, latest);
+        }
+
+        for dependency in dependencies {
+            let key = (dependency.ecosystem, dependency.name.clone());
+            if let Some(Some(latest)) = answers.get(&key) {
+                dependency.latest_version.clone_from(latest);
+            }
+        }
+    }
+
+    /// Looks up the latest version of a package unless its registry was
+    /// found unreachable, as recorded in `failed`, returning `None` if it
+    /// cannot be found out.
+    async fn look_up(
+        &self,
+        key: (Ecosystem, String),
+        failed: &Mutex<HashSet<Ecosystem>>,
+    ) -> ((Ecosystem, String), Option<Option<String>>) {
+        let (ecosystem, package) = &key;
+        if failed
+            .lock()
+            .expect("failed registries poisoned")
+            .contains(ecosystem)
+        {
+            return (key, None);
+        }
+        match self.registries[ecosystem].latest_version(package).await {
+            Ok(latest) => (key, Some(latest)),
+            Err(err) => {
+                tracing::warn!(
+                    error = %err,
+                    ecosystem = ecosystem.as_str(),
+                    package = %package,
+                    "failed to look up latest version"
+                );
+                if err.is_unreachable() {
+                    failed
+                        .lock()
+                        .expect("failed registries poisoned")
+                        .insert(*ecosystem);
+                }
+                (key, None)
+            }
+        }
+    }
+}
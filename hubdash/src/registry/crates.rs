@@ -0,0 +1,90 @@
+//! The crates.io sparse index.
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use url::Url;
+
+use super::{Registry, RegistryError, http_client, join_segments, read_response};
+use crate::dashboard::Ecosystem;
+use crate::versions::semver::Version;
+
+/// The sparse index of crates.io.
+pub const DEFAULT_CRATES_INDEX_URL: &str = "https://index.crates.io/";
+
+/// A line of an index file, describing one published version.
+#[derive(Deserialize)]
+struct IndexEntry {
+    vers: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// Looks up crates in a sparse registry index, such as crates.io's or a
+/// mirror of it.
+#[derive(Debug, Clone)]
+pub struct CratesIndex {
+    http: reqwest::Client,
+    index_url: Url,
+}
+
+impl CratesIndex {
+    /// Creates a client for the sparse index rooted at `index_url`.
+    pub fn new(index_url: Url) -> Self {
+        Self {
+            http: http_client(HeaderMap::new()),
+            index_url,
+        }
+    }
+
+    /// Returns the URL of the index file describing `name`, which the
+    /// index spreads over directories named after its first characters.
+    fn file_url(&self, name: &str) -> Url {
+        let name = name.to_ascii_lowercase();
+        let prefix = match name.len() {
+            1 => vec!["1"],
+            2 => vec!["2"],
+            3 => vec!["3", &name[..1]],
+            _ => vec![&name[..2], &name[2..4]],
+        };
+        join_segments(&self.index_url, prefix.into_iter().chain([name.as_str()]))
+    }
+}
+
+/// Returns the newest version in an index file that is not yanked,
+/// preferring releases over pre-releases.
+fn newest(index_file: &str) -> Result<Option<String>, RegistryError> {
+    let mut newest: Option<(bool, Version, String)> = None;
+    for line in index_file.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: IndexEntry = serde_json::from_str(line)?;
+        if entry.yanked {
+            continue;
+        }
+        let Ok(version) = entry.vers.parse::<Version>() else {
+            continue;
+        };
+        let candidate = (!version.is_prerelease(), version, entry.vers);
+        if newest
+            .as_ref()
+            .is_none_or(|n| (n.0, &n.1) < (candidate.0, &candidate.1))
+        {
+            newest = Some(candidate);
+        }
+    }
+    Ok(newest.map(|(_, _, vers)| vers))
+}
+
+#[async_trait]
+impl Registry for CratesIndex {
+    fn ecosystem(&self) -> Ecosystem {
+        Ecosystem::Cargo
+    }
+
+    async fn latest_version(&self, package: &str) -> Result<Option<String>, RegistryError> {
+        let response = self.http.get(self.file_url(package)).send().await?;
+        match read_response(response).await? {
+            Some(index_file) => newest(&index_file),
+            None => Ok(None),
+        }
+    }
+}
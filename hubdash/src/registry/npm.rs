@@ -0,0 +1,62 @@
+//! The npm registry.
+
+use async_trait::async_trait;
+use reqwest::header::{ACCEPT, HeaderMap, HeaderValue};
+use serde::Deserialize;
+use url::Url;
+
+use super::{Registry, RegistryError, get_json, http_client, join_segments};
+use crate::dashboard::Ecosystem;
+
+/// The public npm registry.
+pub const DEFAULT_NPM_REGISTRY_URL: &str = "https://registry.npmjs.org/";
+
+/// The abbreviated package document, which leaves out every version's
+/// readme and metadata.
+const ABBREVIATED: &str = "application/vnd.npm.install-v1+json";
+
+#[derive(Deserialize)]
+struct Packument {
+    #[serde(rename = "dist-tags", default)]
+    dist_tags: DistTags,
+}
+
+#[derive(Default, Deserialize)]
+struct DistTags {
+    latest: Option<String>,
+}
+
+/// Looks up packages in the npm registry or a registry mirroring it.
+#[derive(Debug, Clone)]
+pub struct NpmRegistry {
+    http: reqwest::Client,
+    registry_url: Url,
+}
+
+impl NpmRegistry {
+    /// Creates a client for the registry at `registry_url`.
+    pub fn new(registry_url: Url) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(ABBREVIATED));
+        Self {
+            http: http_client(headers),
+            registry_url,
+        }
+    }
+}
+
+#[async_trait]
+impl Registry for NpmRegistry {
+    fn ecosystem(&self) -> Ecosystem {
+        Ecosystem::Npm
+    }
+
+    /// Returns the version the `latest` dist-tag points at, which is what
+    /// `npm install` picks.
+    async fn latest_version(&self, package: &str) -> Result<Option<String>, RegistryError> {
+        // A scoped name is a single path segment, with its slash encoded.
+        let url = join_segments(&self.registry_url, [package]);
+        let packument: Option<Packument> = get_json(&self.http, url).await?;
+        Ok(packument.and_then(|p| p.dist_tags.latest))
+    }
+}
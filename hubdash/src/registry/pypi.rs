@@ -0,0 +1,54 @@
+//! The Python Package Index.
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use url::Url;
+
+use super::{Registry, RegistryError, get_json, http_client, join_segments};
+use crate::dashboard::Ecosystem;
+
+/// The public Python Package Index.
+pub const DEFAULT_PYPI_URL: &str = "https://pypi.org/";
+
+#[derive(Deserialize)]
+struct Project {
+    info: ProjectInfo,
+}
+
+#[derive(Deserialize)]
+struct ProjectInfo {
+    version: String,
+}
+
+/// Looks up projects through the JSON API of PyPI or a mirror serving it.
+#[derive(Debug, Clone)]
+pub struct PyPI {
+    http: reqwest::Client,
+    index_url: Url,
+}
+
+impl PyPI {
+    /// Creates a client for the index at `index_url`.
+    pub fn new(index_url: Url) -> Self {
+        Self {
+            http: http_client(HeaderMap::new()),
+            index_url,
+        }
+    }
+}
+
+#[async_trait]
+impl Registry for PyPI {
+    fn ecosystem(&self) -> Ecosystem {
+        Ecosystem::PyPI
+    }
+
+    /// Returns the version PyPI reports for the project, its newest
+    /// release that is not a pre-release or yanked.
+    async fn latest_version(&self, package: &str) -> Result<Option<String>, RegistryError> {
+        let url = join_segments(&self.index_url, ["pypi", package, "json"]);
+        let project: Option<Project> = get_json(&self.http, url).await?;
+        Ok(project.map(|p| p.info.version))
+    }
+}
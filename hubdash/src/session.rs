@@ -0,0 +1,156 @@
+//! Server-side sessions and the guard protecting dashboard routes.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::cookie::CookieJar;
+use jiff::{SignedDuration, Timestamp};
+
+use crate::AppState;
+
+/// Name of the cookie carrying the session ID.
+pub const SESSION_COOKIE: &str = "hubdash_session";
+
+/// How long a session lasts unless configured otherwise.
+pub const DEFAULT_SESSION_TTL: SignedDuration = SignedDuration::from_hours(8);
+
+/// A signed-in user and the token GitHub issued for them.
+#[derive(Clone)]
+pub struct Session {
+    pub login: String,
+    pub access_token: String,
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("login", &self.login)
+            .field("access_token", &"<redacted>")
+            .finish()
+    }
+}
+
+struct StoredSession {
+    session: Session,
+    expires_at: Timestamp,
+}
+
+/// In-memory session storage keyed by random session IDs.
+///
+/// Sessions expire a fixed time after sign-in; expired entries are
+/// dropped lazily on lookup and whenever a new session is stored.
+#[derive(Clone)]
+pub struct SessionStore {
+    sessions: Arc<Mutex<HashMap<String, StoredSession>>>,
+    ttl: SignedDuration,
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_TTL)
+    }
+}
+
+impl SessionStore {
+    /// Creates an empty store whose sessions last for `ttl`.
+    pub fn new(ttl: SignedDuration) -> Self {
+        Self {
+            sessions: Arc::default(),
+            ttl,
+        }
+    }
+
+    /// Stores a session and returns the ID to hand to the browser.
+    pub fn insert(&self, session: Session) -> String {
+        let id = random_token();
+        let now = Timestamp::now();
+        let mut sessions = self.sessions.lock().expect("session store poisoned");
+        sessions.retain(|_, stored| stored.expires_at > now);
+        sessions.insert(
+            id.clone(),
+            StoredSession {
+                session,
+                expires_at: now.checked_add(self.ttl).unwrap_or(Timestamp::MAX),
+            },
+        );
+        id
+    }
+
+    /// Looks up the unexpired session with the given ID.
+    pub fn get(&self, id: &str) -> Option<Session> {
+        let mut sessions = self.sessions.lock().expect("session store poisoned");
+        match sessions.get(id) {
+            Some(stored) if stored.expires_at > Timestamp::now() => Some(stored.session.clone()),
+            Some(_) => {
+                sessions.remove(id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Forgets the session with the given ID, returning it if it existed.
+    pub fn remove(&self, id: &str) -> Option<Session> {
+        self.sessions
+            .lock()
+            .expect("session store poisoned")
+            .remove(id)
+            .map(|stored| stored.session)
+    }
+}
+
+/// Returns 256 bits of hex-encoded randomness.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).expect("system random number generator unavailable");
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Whether a request was issued by htmx rather than a full page load.
+fn is_htmx(headers: &HeaderMap) -> bool {
+    headers
+        .get("HX-Request")
+        .is_some_and(|value| value.as_bytes() == b"true")
+}
+
+/// Middleware admitting only signed-in users.
+///
+/// The current [`Session`] is added to the request extensions. Page loads
+/// without a session are redirected to the landing page, while htmx
+/// fragment requests get a `401` with an `HX-Redirect` so that the whole
+/// page navigates instead of swapping the landing page into a fragment.
+///
+/// When sign-in is not configured, every request is let through.
+pub async fn require_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if state.oauth().is_none() {
+        return next.run(request).await;
+    }
+
+    let session = jar
+        .get(SESSION_COOKIE)
+        .and_then(|cookie| state.sessions().get(cookie.value()));
+    match session {
+        Some(session) => {
+            request.extensions_mut().insert(session);
+            next.run(request).await
+        }
+        None if is_htmx(request.headers()) => {
+            (StatusCode::UNAUTHORIZED, [("HX-Redirect", "/")]).into_response()
+        }
+        None => Redirect::to("/").into_response(),
+    }
+}
@@ -0,0 +1,43 @@
+//! Pluggable sources of repository data for the dashboard.
+
+use async_trait::async_trait;
+use jiff::Timestamp;
+
+use crate::dashboard::{JobSummary, RepoSummary};
+
+/// Error returned when a [`RepoSource`] cannot produce data.
+#[derive(Debug, thiserror::Error)]
+pub enum SourceError {
+    /// The upstream service failed or returned unusable data.
+    #[error("repository source unavailable: {0}")]
+    Upstream(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The upstream service is rate limiting requests.
+    #[error("repository source is rate limited until {until}")]
+    RateLimited {
+        /// When requests may resume.
+        until: Timestamp,
+    },
+}
+
+/// A provider of repository summaries for the dashboard.
+///
+/// Implementations may be backed by static data, the GitHub API, or
+/// anything else that can describe a set of repositories.
+#[async_trait]
+pub trait RepoSource: Send + Sync {
+    /// Returns summaries for every repository this source knows about.
+    async fn list_repos(&self) -> Result<Vec<RepoSummary>, SourceError>;
+
+    /// Looks up a single repository by owner and name.
+    async fn find_repo(&self, owner: &str, repo: &str) -> Result<Option<RepoSummary>, SourceError>;
+
+    /// Lists the jobs of a pipeline run, with their steps.
+    ///
+    /// Runs the source does not know about have no jobs.
+    async fn list_jobs(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<Vec<JobSummary>, SourceError>;
+}
@@ -0,0 +1,90 @@
+//! Status page showing how much of the GitHub API budget is left.
+
+use axum::{extract::State, response::IntoResponse};
+use jiff::Timestamp;
+use maud::{Markup, html};
+
+use crate::AppState;
+use crate::dashboard::rate_limit_banner;
+use crate::github::{Budget, RateLimits};
+use crate::layout::base_layout;
+
+fn budget_row(budget: &Budget, now: Timestamp) -> Markup {
+    let used = budget.limit.saturating_sub(budget.remaining);
+    let resets = if budget.reset > now {
+        budget.reset.strftime("%H:%M:%S UTC").to_string()
+    } else {
+        "—".into()
+    };
+    html! {
+        tr {
+            td { (budget.resource.as_str()) }
+            td { (used) }
+            td { (budget.remaining) }
+            td { (budget.limit) }
+            td { (resets) }
+        }
+    }
+}
+
+fn rate_limit_section(limits: Option<&RateLimits>) -> Markup {
+    let Some(limits) = limits else {
+        return html! {
+            p class="status-note" { "Repository data does not come from the GitHub API." }
+        };
+    };
+    let budgets = limits.budgets();
+    let now = Timestamp::now();
+    html! {
+        @if let Some(until) = limits.limited_until() {
+            (rate_limit_banner(until))
+        }
+        @if budgets.is_empty() {
+            p class="status-note" { "No requests have been made to GitHub yet." }
+        } @else {
+            table class="repo-table status-table" {
+                thead {
+                    tr {
+                        th { "Resource" }
+                        th { "Used" }
+                        th { "Remaining" }
+                        th { "Limit" }
+                        th { "Resets" }
+                    }
+                }
+                tbody {
+                    @for budget in &budgets {
+                        (budget_row(budget, now))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders the status page.
+pub async fn status_page(State(state): State<AppState>) -> impl IntoResponse {
+    let body = html! {
+        div class="dashboard-container" {
+            header class="dashboard-header" {
+                h1 { "Status" }
+                div class="dashboard-user" {
+                    a href="/dashboard" class="status-link" { "Dashboard" }
+                }
+            }
+            main class="dashboard-main" {
+                section class="status-section" {
+                    h2 { "GitHub API rate limits" }
+                    (rate_limit_section(state.rate_limits()))
+                }
+            }
+        }
+    };
+
+    base_layout(
+        "Status | Hubdash",
+        &["/assets/theme.css", "/assets/dashboard.css"],
+        &[],
+        body,
+    )
+}
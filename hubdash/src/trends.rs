@@ -0,0 +1,271 @@
+//! Per-repository page charting how each workflow's runs trend over time.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use jiff::{SignedDuration, Span, Timestamp, civil::Date, tz::TimeZone};
+use maud::{Markup, html};
+
+use crate::AppState;
+use crate::charts::{DayStats, duration_chart, success_chart};
+use crate::dashboard::{
+    DashboardQuery, RepoSummary, history_rate, render_source_error, status_badge, window_picker,
+};
+use crate::history::{RunFilter, RunHistory, RunRecord, Window};
+use crate::layout::base_layout;
+
+fn utc_day(at: Timestamp) -> Date {
+    at.to_zoned(TimeZone::UTC).date()
+}
+
+/// Returns the `percent`th percentile of `sorted` by the nearest-rank
+/// method.
+fn percentile(sorted: &[SignedDuration], percent: usize) -> Option<SignedDuration> {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+/// The most points a chart plots; longer ranges are grouped into spans of
+/// several days.
+const MAX_BUCKETS: i32 = 120;
+
+/// Groups runs by the day they started on, from `first` until `last`.
+///
+/// Every day in the range is included, so gaps show up on the charts. When
+/// the range is longer than [`MAX_BUCKETS`] days, consecutive days are
+/// grouped and each group is labelled with its first day.
+fn daily_stats(runs: &[RunRecord], first: Date, last: Date) -> Vec<DayStats> {
+    let days = (last - first).get_days().max(0) + 1;
+    let width = Span::new().days((days + MAX_BUCKETS - 1) / MAX_BUCKETS);
+    let starts: Vec<Date> = first
+        .series(width)
+        .take_while(|&start| start <= last)
+        .collect();
+
+    let mut buckets: BTreeMap<Date, Vec<RunRecord>> = BTreeMap::new();
+    for run in runs {
+        let day = utc_day(run.started_at);
+        if !(first..=last).contains(&day) {
+            continue;
+        }
+        let start = starts[starts.partition_point(|&start| start <= day) - 1];
+        buckets.entry(start).or_default().push(run.clone());
+    }
+
+    starts
+        .into_iter()
+        .map(|day| {
+            let on_day = buckets.remove(&day).unwrap_or_default();
+            let mut durations: Vec<_> = on_day
+                .iter()
+                .filter_map(|r| Some(r.finished_at?.duration_since(r.started_at)))
+                .collect();
+            durations.sort();
+            DayStats {
+                day,
+                runs: on_day.len(),
+                p50: percentile(&durations, 50),
+                p90: percentile(&durations, 90),
+                success_rate: history_rate(&on_day),
+            }
+        })
+        .collect()
+}
+
+async fn workflow_section(
+    history: &dyn RunHistory,
+    repo: &RepoSummary,
+    workflow: &str,
+    window: Window,
+    now: Timestamp,
+) -> Markup {
+    let filter = RunFilter {
+        workflow: Some(workflow.to_owned()),
+        ..window.filter(now)
+    };
+    let runs = match history.runs(&repo.owner, &repo.repo, &filter).await {
+        Ok(runs) => runs,
+        Err(err) => {
+            tracing::warn!(error = %err, workflow, "failed to read run history");
+            return html! { div class="error" { "Run history is currently unavailable" } };
+        }
+    };
+    let last = utc_day(now);
+    let first = filter
+        .since
+        .or_else(|| runs.last().map(|r| r.started_at))
+        .map_or(last, utc_day);
+    let days = daily_stats(&runs, first, last);
+
+    html! {
+        section class="trend-section" {
+            h2 { (workflow) }
+            @if runs.is_empty() {
+                p class="chart-empty" { "No runs in this window." }
+            } @else {
+                div class="trend-charts" {
+                    figure class="trend-figure" {
+                        figcaption { "Duration" }
+                        (duration_chart(&days))
+                    }
+                    figure class="trend-figure" {
+                        figcaption { "Success rate" }
+                        (success_chart(&days))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn error_page(error: Markup) -> Markup {
+    html! {
+        div class="dashboard-container" {
+            main class="dashboard-main" {
+                (error)
+                a href="/dashboard" class="status-link" { "Back to the dashboard" }
+            }
+        }
+    }
+}
+
+fn trends_layout(title: &str, body: Markup) -> Markup {
+    base_layout(
+        &format!("{title} | Hubdash"),
+        &["/assets/theme.css", "/assets/dashboard.css"],
+        &[],
+        body,
+    )
+}
+
+/// Renders the trends page for a repository.
+///
+/// Each of the repository's workflows gets charts of its daily run
+/// duration and success rate over the window selected by the `window`
+/// query parameter.
+pub async fn repo_page(
+    State(state): State<AppState>,
+    Path((owner, repo)): Path<(String, String)>,
+    Query(query): Query<DashboardQuery>,
+) -> Response {
+    let now = Timestamp::now();
+    let window = query.window();
+    let summary = match state.repos().find_repo(&owner, &repo).await {
+        Ok(Some(summary)) => summary,
+        Ok(None) => {
+            let body = html! { div class="error" { "Repository not found" } };
+            return (
+                StatusCode::NOT_FOUND,
+                trends_layout("Not found", error_page(body)),
+            )
+                .into_response();
+        }
+        Err(err) => {
+            let body = error_page(render_source_error(&err));
+            return (StatusCode::BAD_GATEWAY, trends_layout("Unavailable", body)).into_response();
+        }
+    };
+    let full_name = format!("{}/{}", summary.owner, summary.repo);
+
+    let mut sections = Vec::with_capacity(summary.pipelines.len());
+    if let Some(history) = state.run_history() {
+        for pipeline in &summary.pipelines {
+            sections.push(workflow_section(history, &summary, &pipeline.name, window, now).await);
+        }
+    }
+
+    let body = html! {
+        div class="dashboard-container" {
+            header class="dashboard-header" {
+                h1 { (full_name) " " (status_badge(summary.last_status)) }
+                @if state.run_history().is_some() {
+                    (window_picker(window))
+                }
+                div class="dashboard-user" {
+                    a href="/dashboard" class="status-link" { "Dashboard" }
+                }
+            }
+            main class="dashboard-main" {
+                @if state.run_history().is_none() {
+                    p class="status-note" { "Run history is not being kept." }
+                } @else if summary.pipelines.is_empty() {
+                    p class="status-note" { "This repository has no workflows." }
+                }
+                @for section in sections {
+                    (section)
+                }
+            }
+        }
+    };
+    trends_layout(&full_name, body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::civil::date;
+
+    use super::*;
+    use crate::PipelineStatus;
+
+    fn run(day: Date, status: PipelineStatus) -> RunRecord {
+        let started_at = day.at(12, 0, 0, 0).to_zoned(TimeZone::UTC).unwrap();
+        RunRecord {
+            owner: "example".to_owned(),
+            repo: "hubdash".to_owned(),
+            run_id: 1,
+            workflow: "CI".to_owned(),
+            status,
+            started_at: started_at.timestamp(),
+            finished_at: Some(started_at.timestamp() + SignedDuration::from_mins(5)),
+            head_sha: "abc123".to_owned(),
+            branch: Some("main".to_owned()),
+            url: "https://github.com/example/hubdash/actions/runs/1"
+                .parse()
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn includes_every_day_of_short_ranges() {
+        let runs = [
+            run(date(2025, 3, 1), PipelineStatus::Success),
+            run(date(2025, 3, 3), PipelineStatus::Success),
+            run(date(2025, 3, 3), PipelineStatus::Failure),
+        ];
+        let days = daily_stats(&runs, date(2025, 3, 1), date(2025, 3, 4));
+        let summary: Vec<_> = days
+            .iter()
+            .map(|d| (d.day, d.runs, d.success_rate))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (date(2025, 3, 1), 1, Some(100)),
+                (date(2025, 3, 2), 0, None),
+                (date(2025, 3, 3), 2, Some(50)),
+                (date(2025, 3, 4), 0, None),
+            ]
+        );
+        assert_eq!(days[2].p50, Some(SignedDuration::from_mins(5)));
+    }
+
+    #[test]
+    fn groups_days_of_long_ranges() {
+        let first = date(2015, 1, 1);
+        let last = date(2025, 1, 1);
+        let runs = [
+            run(first, PipelineStatus::Success),
+            run(date(2020, 6, 15), PipelineStatus::Failure),
+            run(last, PipelineStatus::Success),
+        ];
+        let days = daily_stats(&runs, first, last);
+        assert!(days.len() <= MAX_BUCKETS as usize, "{}", days.len());
+        assert_eq!(days[0].day, first);
+        assert_eq!(days.iter().map(|d| d.runs).sum::<usize>(), 3);
+        assert_eq!(days.last().unwrap().runs, 1);
+    }
+}
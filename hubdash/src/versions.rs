@@ -0,0 +1,183 @@
+//! Comparing package versions the way each ecosystem orders them.
+
+use std::cmp::Ordering;
+
+use crate::dashboard::Ecosystem;
+
+pub(crate) mod go;
+pub(crate) mod pep440;
+pub(crate) mod semver;
+
+/// How far a dependency is behind the newest version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VersionGap {
+    /// Only the patch version differs.
+    Patch,
+    /// A release with new features is available.
+    Minor,
+    /// A release with breaking changes is available.
+    Major,
+}
+
+impl VersionGap {
+    /// Returns the CSS class for this gap.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Self::Patch => "dep-status outdated patch",
+            Self::Minor => "dep-status outdated minor",
+            Self::Major => "dep-status outdated major",
+        }
+    }
+
+    /// Returns the display name for this gap.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Patch => "patch",
+            Self::Minor => "minor",
+            Self::Major => "major",
+        }
+    }
+}
+
+/// Classifies the gap between semantic versions, where `caret` says
+/// whether `0.x` minor releases are breaking, as Cargo and npm treat them.
+fn semver_gap(current: &semver::Version, latest: &semver::Version, caret: bool) -> VersionGap {
+    let breaking = if caret {
+        current.caret_key() != latest.caret_key()
+    } else {
+        current.major != latest.major
+    };
+    if breaking {
+        VersionGap::Major
+    } else if current.minor != latest.minor {
+        VersionGap::Minor
+    } else {
+        VersionGap::Patch
+    }
+}
+
+/// Classifies the gap between Python versions, where a new epoch, which
+/// restarts the numbering, is always a major change.
+fn pep440_gap(current: &pep440::Version, latest: &pep440::Version) -> VersionGap {
+    if current.epoch() != latest.epoch() {
+        return VersionGap::Major;
+    }
+    let segment = |index| {
+        (
+            current.release_segment(index),
+            latest.release_segment(index),
+        )
+    };
+    let (major, minor) = (segment(0), segment(1));
+    if major.0 != major.1 {
+        VersionGap::Major
+    } else if minor.0 != minor.1 {
+        VersionGap::Minor
+    } else {
+        VersionGap::Patch
+    }
+}
+
+/// Returns how far `current` is behind `latest` in `ecosystem`, or `None`
+/// if it is not behind or either version cannot be parsed.
+///
+/// Go pseudo-versions are pre-releases of the version after the tag they
+/// follow, so they compare as such.
+pub(crate) fn gap(ecosystem: Ecosystem, current: &str, latest: &str) -> Option<VersionGap> {
+    match ecosystem {
+        Ecosystem::Cargo | Ecosystem::Npm | Ecosystem::Go => {
+            let current: semver::Version = current.parse().ok()?;
+            let latest: semver::Version = latest.parse().ok()?;
+            (current < latest).then(|| semver_gap(&current, &latest, ecosystem != Ecosystem::Go))
+        }
+        Ecosystem::PyPI => {
+            let current: pep440::Version = current.parse().ok()?;
+            let latest: pep440::Version = latest.parse().ok()?;
+            (current < latest).then(|| pep440_gap(&current, &latest))
+        }
+    }
+}
+
+/// Compares two versions the way `ecosystem` orders them, or returns
+/// `None` if either cannot be parsed.
+pub(crate) fn compare(ecosystem: Ecosystem, a: &str, b: &str) -> Option<Ordering> {
+    match ecosystem {
+        Ecosystem::Cargo | Ecosystem::Npm | Ecosystem::Go => {
+            let a: semver::Version = a.parse().ok()?;
+            let b: semver::Version = b.parse().ok()?;
+            Some(a.cmp(&b))
+        }
+        Ecosystem::PyPI => {
+            let a: pep440::Version = a.parse().ok()?;
+            let b: pep440::Version = b.parse().ok()?;
+            Some(a.cmp(&b))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gap_classifies_each_ecosystem() {
+        use Ecosystem::{Cargo, Go, Npm, PyPI};
+        use VersionGap::{Major, Minor, Patch};
+        for (ecosystem, current, latest, expected) in [
+            (Cargo, "0.8.8", "0.8.9", Some(Patch)),
+            (Cargo, "0.8.8", "0.9.0", Some(Major)),
+            (Cargo, "0.0.3", "0.0.4", Some(Major)),
+            (Cargo, "1.2.0", "1.3.0", Some(Minor)),
+            (Cargo, "1.2.0", "1.2.0", None),
+            (Cargo, "1.3.0", "1.2.0", None),
+            (Cargo, "^0.8.8", "0.8.9", None),
+            (Npm, "1.0.0-rc.1", "1.0.0", Some(Patch)),
+            (Npm, "1.9.9", "2.0.0", Some(Major)),
+            (Npm, "*", "2.0.0", None),
+            (Go, "v0.8.0", "v0.9.0", Some(Minor)),
+            (Go, "v1.4.0", "v2.0.0", Some(Major)),
+            (
+                Go,
+                "v0.0.0-20191109021931-daa7c04131f5",
+                "v0.1.0",
+                Some(Minor),
+            ),
+            (
+                Go,
+                "v0.1.1-0.20220101000000-0123456789ab",
+                "v0.1.1",
+                Some(Patch),
+            ),
+            (PyPI, "1.0", "1.0.1", Some(Patch)),
+            (PyPI, "1.0", "1.1", Some(Minor)),
+            (PyPI, "1.9", "2.0", Some(Major)),
+            (PyPI, "2.0rc1", "2.0", Some(Patch)),
+            (PyPI, "1!1.0", "1!1.1", Some(Minor)),
+            (PyPI, "1.9", "1!1.0", Some(Major)),
+            (PyPI, "1!1.0", "2.0", None),
+            (PyPI, ">=1.0", "2.0", None),
+        ] {
+            assert_eq!(
+                gap(ecosystem, current, latest),
+                expected,
+                "{ecosystem:?} {current} -> {latest}"
+            );
+        }
+    }
+
+    #[test]
+    fn compare_needs_both_versions_to_parse() {
+        use Ecosystem::{Cargo, Go, PyPI};
+        for (ecosystem, a, b, expected) in [
+            (Cargo, "1.0.0", "1.0.0+build.1", Some(Ordering::Equal)),
+            (Cargo, "1.0.0-beta", "1.0.0", Some(Ordering::Less)),
+            (Go, "v1.2.3", "1.2.3", Some(Ordering::Equal)),
+            (PyPI, "1.0", "1.0.0", Some(Ordering::Equal)),
+            (PyPI, "1.0.post1", "1.0", Some(Ordering::Greater)),
+            (Cargo, "^1.0", "1.0.0", None),
+            (PyPI, "1.0", "~=1.0", None),
+        ] {
+            assert_eq!(compare(ecosystem, a, b), expected, "{ecosystem:?} {a} {b}");
+        }
+    }
+}
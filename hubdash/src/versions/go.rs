@@ -0,0 +1,77 @@
+//! Go module pseudo-versions, which name a commit rather than a tag.
+
+use jiff::civil::DateTime;
+
+/// The commit a pseudo-version such as
+/// `v0.0.0-20191109021931-daa7c04131f5` refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PseudoVersion {
+    /// When the commit was made, in UTC.
+    pub(crate) time: DateTime,
+    /// The commit hash, abbreviated to 12 characters.
+    pub(crate) revision: String,
+}
+
+impl PseudoVersion {
+    /// Parses a pseudo-version in any of its three forms:
+    /// `vX.0.0-yyyymmddhhmmss-abcdefabcdef` when there is no earlier tag,
+    /// `vX.Y.Z-pre.0.yyyymmddhhmmss-abcdefabcdef` after a pre-release tag,
+    /// and `vX.Y.(Z+1)-0.yyyymmddhhmmss-abcdefabcdef` after a release tag.
+    ///
+    /// Returns `None` for ordinary versions.
+    pub(crate) fn parse(version: &str) -> Option<Self> {
+        let version = version.split_once('+').map_or(version, |(v, _build)| v);
+        let (_, pre) = version.split_once('-')?;
+        let (rest, revision) = pre.rsplit_once('-')?;
+        let timestamp = rest.get(rest.len().checked_sub(14)?..)?;
+        let before = &rest[..rest.len() - timestamp.len()];
+        let is_hex = |s: &str| s.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'));
+        if revision.len() != 12
+            || !is_hex(revision)
+            || !(before.is_empty() || before.ends_with('.'))
+        {
+            return None;
+        }
+        let time = DateTime::strptime("%Y%m%d%H%M%S", timestamp).ok()?;
+        Some(Self {
+            time,
+            revision: revision.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_pseudo_version_form() {
+        for (version, expected) in [
+            (
+                "v0.0.0-20191109021931-daa7c04131f5",
+                Some(("2019-11-09T02:19:31", "daa7c04131f5")),
+            ),
+            (
+                "v1.2.3-pre.0.20220101000000-0123456789ab",
+                Some(("2022-01-01T00:00:00", "0123456789ab")),
+            ),
+            (
+                "v1.2.4-0.20230615120000-abcdefabcdef+incompatible",
+                Some(("2023-06-15T12:00:00", "abcdefabcdef")),
+            ),
+            ("v1.2.3", None),
+            ("v1.2.3-rc.1", None),
+            ("v0.0.0-20191109021931-DAA7C04131F5", None),
+            ("v0.0.0-20191109021931-daa7c04131", None),
+            ("v0.0.0-x20191109021931-daa7c04131f5", None),
+            ("v0.0.0-20191309021931-daa7c04131f5", None),
+        ] {
+            let parsed = PseudoVersion::parse(version);
+            let parsed = parsed
+                .as_ref()
+                .map(|p| (p.time.to_string(), p.revision.as_str()));
+            let expected = expected.map(|(time, revision)| (time.to_owned(), revision));
+            assert_eq!(parsed, expected, "{version}");
+        }
+    }
+}
@@ -0,0 +1,556 @@
+//! Python package versions and version specifiers, as defined by PEP 440.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A value that sorts before or after every other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Bound<T> {
+    Min,
+    Value(T),
+    Max,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum PreKind {
+    Alpha,
+    Beta,
+    Candidate,
+}
+
+impl PreKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Alpha => "a",
+            Self::Beta => "b",
+            Self::Candidate => "rc",
+        }
+    }
+}
+
+/// A segment of a local version label, where text sorts before numbers.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum LocalSegment {
+    Text(String),
+    Number(u64),
+}
+
+impl fmt::Display for LocalSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => f.write_str(text),
+            Self::Number(number) => write!(f, "{number}"),
+        }
+    }
+}
+
+/// A version, such as `1.0`, `2.1.0rc1` or `1!3.0.post2.dev1+local.7`.
+///
+/// Versions compare and display in their normalized form, so `1.0` and
+/// `1.0.0` are equal, and `1.0-alpha.1` is shown as `1.0a1`.
+#[derive(Debug, Clone)]
+pub(crate) struct Version {
+    epoch: u64,
+    release: Vec<u64>,
+    pre: Option<(PreKind, u64)>,
+    post: Option<u64>,
+    dev: Option<u64>,
+    local: Vec<LocalSegment>,
+}
+
+/// Error returned when a version or specifier is not valid.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid PEP 440 version")]
+pub(crate) struct InvalidVersion;
+
+/// The unparsed remainder of a version.
+struct Cursor<'a>(&'a str);
+
+impl Cursor<'_> {
+    fn number(&mut self) -> Option<u64> {
+        let end = self
+            .0
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.0.len());
+        let number = self.0[..end].parse().ok()?;
+        self.0 = &self.0[end..];
+        Some(number)
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        match self.0.strip_prefix(prefix) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn separator(&mut self) {
+        let _ = self.eat("-") || self.eat("_") || self.eat(".");
+    }
+
+    /// Parses an optionally separated label from `labels`, followed by an
+    /// optionally separated number, restoring the cursor if none matches.
+    fn labelled<T: Copy>(&mut self, labels: &[(&str, T)]) -> Option<(T, u64)> {
+        let start = self.0;
+        self.separator();
+        let Some(&(_, value)) = labels.iter().find(|(label, _)| self.eat(label)) else {
+            self.0 = start;
+            return None;
+        };
+        let before_number = self.0;
+        self.separator();
+        let number = self.number().unwrap_or_else(|| {
+            self.0 = before_number;
+            0
+        });
+        Some((value, number))
+    }
+}
+
+impl FromStr for Version {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let s = s.strip_prefix('v').unwrap_or(&s);
+        let (public, local) = match s.split_once('+') {
+            Some((public, local)) => (public, Some(local)),
+            None => (s, None),
+        };
+        let (epoch, public) = match public.split_once('!') {
+            Some((epoch, public)) => (epoch.parse().map_err(|_| InvalidVersion)?, public),
+            None => (0, public),
+        };
+
+        let mut cursor = Cursor(public);
+        let mut release = vec![cursor.number().ok_or(InvalidVersion)?];
+        while cursor.0.starts_with('.') && cursor.0[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            cursor.eat(".");
+            release.push(cursor.number().ok_or(InvalidVersion)?);
+        }
+        let pre = cursor.labelled(&[
+            ("alpha", PreKind::Alpha),
+            ("beta", PreKind::Beta),
+            ("preview", PreKind::Candidate),
+            ("pre", PreKind::Candidate),
+            ("rc", PreKind::Candidate),
+            ("a", PreKind::Alpha),
+            ("b", PreKind::Beta),
+            ("c", PreKind::Candidate),
+        ]);
+        let post = if cursor.0.starts_with('-')
+            && cursor.0[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            cursor.eat("-");
+            cursor.number()
+        } else {
+            cursor
+                .labelled(&[("post", ()), ("rev", ()), ("r", ())])
+                .map(|(_, number)| number)
+        };
+        let dev = cursor.labelled(&[("dev", ())]).map(|(_, number)| number);
+        if !cursor.0.is_empty() {
+            return Err(InvalidVersion);
+        }
+
+        let local = match local {
+            Some(local) => local
+                .split(['-', '_', '.'])
+                .map(|segment| match segment.parse() {
+                    Ok(number) => Ok(LocalSegment::Number(number)),
+                    Err(_)
+                        if !segment.is_empty()
+                            && segment.chars().all(|c| c.is_ascii_alphanumeric()) =>
+                    {
+                        Ok(LocalSegment::Text(segment.to_owned()))
+                    }
+                    Err(_) => Err(InvalidVersion),
+                })
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            epoch,
+            release,
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<_> = self.release.iter().map(u64::to_string).collect();
+        f.write_str(&release.join("."))?;
+        if let Some((kind, number)) = self.pre {
+            write!(f, "{}{number}", kind.as_str())?;
+        }
+        if let Some(post) = self.post {
+            write!(f, ".post{post}")?;
+        }
+        if let Some(dev) = self.dev {
+            write!(f, ".dev{dev}")?;
+        }
+        if !self.local.is_empty() {
+            let local: Vec<_> = self.local.iter().map(ToString::to_string).collect();
+            write!(f, "+{}", local.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+type SortKey<'a> = (
+    u64,
+    &'a [u64],
+    Bound<(PreKind, u64)>,
+    Bound<u64>,
+    Bound<u64>,
+    Bound<&'a [LocalSegment]>,
+);
+
+impl Version {
+    /// Returns the release segments without trailing zeros, which do not
+    /// affect comparisons.
+    fn significant_release(&self) -> &[u64] {
+        let end = self
+            .release
+            .iter()
+            .rposition(|&n| n != 0)
+            .map_or(0, |i| i + 1);
+        &self.release[..end]
+    }
+
+    fn sort_key(&self, with_local: bool) -> SortKey<'_> {
+        let pre = match (self.pre, self.post, self.dev) {
+            // A development release of a final release comes before its
+            // pre-releases.
+            (None, None, Some(_)) => Bound::Min,
+            (None, _, _) => Bound::Max,
+            (Some(pre), _, _) => Bound::Value(pre),
+        };
+        let local = if with_local && !self.local.is_empty() {
+            Bound::Value(self.local.as_slice())
+        } else {
+            Bound::Min
+        };
+        (
+            self.epoch,
+            self.significant_release(),
+            pre,
+            self.post.map_or(Bound::Min, Bound::Value),
+            self.dev.map_or(Bound::Max, Bound::Value),
+            local,
+        )
+    }
+
+    /// Compares versions ignoring any local version label.
+    fn cmp_public(&self, other: &Self) -> Ordering {
+        self.sort_key(false).cmp(&other.sort_key(false))
+    }
+
+    /// Returns the epoch, which is zero unless written as in `1!2.0`.
+    pub(crate) fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the `index`th release segment, where missing ones are zero.
+    pub(crate) fn release_segment(&self, index: usize) -> u64 {
+        self.release.get(index).copied().unwrap_or(0)
+    }
+
+    fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    /// Whether the release segments start with `prefix`, padding with
+    /// zeros as needed.
+    fn release_starts_with(&self, epoch: u64, prefix: &[u64]) -> bool {
+        self.epoch == epoch
+            && prefix
+                .iter()
+                .enumerate()
+                .all(|(i, &n)| self.release.get(i).copied().unwrap_or(0) == n)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key(true).cmp(&other.sort_key(true))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Compatible,
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+    Arbitrary,
+}
+
+/// A single version clause, such as `>=1.2` or `==2.*`.
+#[derive(Debug, Clone)]
+struct Specifier {
+    operator: Operator,
+    version: Version,
+    /// Whether the version ended in `.*`.
+    wildcard: bool,
+    /// The version as written, which `===` compares against.
+    raw: String,
+}
+
+impl FromStr for Specifier {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (operator, rest) = [
+            ("===", Operator::Arbitrary),
+            ("~=", Operator::Compatible),
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("<=", Operator::LessEqual),
+            (">=", Operator::GreaterEqual),
+            ("<", Operator::Less),
+            (">", Operator::Greater),
+        ]
+        .into_iter()
+        .find_map(|(prefix, operator)| Some((operator, s.strip_prefix(prefix)?.trim())))
+        .ok_or(InvalidVersion)?;
+        let (version, wildcard) = match rest.strip_suffix(".*") {
+            Some(version) if matches!(operator, Operator::Equal | Operator::NotEqual) => {
+                (version, true)
+            }
+            _ => (rest, false),
+        };
+        if operator == Operator::Compatible && !version.contains('.') {
+            return Err(InvalidVersion);
+        }
+        Ok(Self {
+            operator,
+            version: version.parse()?,
+            wildcard,
+            raw: rest.to_owned(),
+        })
+    }
+}
+
+impl Specifier {
+    fn contains(&self, candidate: &Version) -> bool {
+        let version = &self.version;
+        let same_release = || {
+            candidate.significant_release() == version.significant_release()
+                && candidate.epoch == version.epoch
+        };
+        match self.operator {
+            Operator::Equal | Operator::NotEqual => {
+                let equal = if self.wildcard {
+                    candidate.release_starts_with(version.epoch, &version.release)
+                } else if version.local.is_empty() {
+                    candidate.cmp_public(version) == Ordering::Equal
+                } else {
+                    candidate == version
+                };
+                equal == (self.operator == Operator::Equal)
+            }
+            Operator::LessEqual => candidate.cmp_public(version) != Ordering::Greater,
+            Operator::GreaterEqual => candidate.cmp_public(version) != Ordering::Less,
+            Operator::Less => {
+                candidate.cmp_public(version) == Ordering::Less
+                    && (version.is_prerelease() || !candidate.is_prerelease() || !same_release())
+            }
+            Operator::Greater => {
+                candidate.cmp_public(version) == Ordering::Greater
+                    && (version.post.is_some() || candidate.post.is_none() || !same_release())
+            }
+            Operator::Compatible => {
+                let prefix = &version.release[..version.release.len() - 1];
+                candidate.cmp_public(version) != Ordering::Less
+                    && candidate.release_starts_with(version.epoch, prefix)
+            }
+            Operator::Arbitrary => candidate.to_string().eq_ignore_ascii_case(&self.raw),
+        }
+    }
+}
+
+/// A comma-separated list of version clauses, all of which a version must
+/// satisfy, such as `>=1.2,<2`.
+#[derive(Debug, Clone)]
+pub(crate) struct Specifiers(Vec<Specifier>);
+
+impl FromStr for Specifiers {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|clause| !clause.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Specifiers {
+    /// Whether `version` satisfies every clause.
+    pub(crate) fn contains(&self, version: &Version) -> bool {
+        self.0.iter().all(|specifier| specifier.contains(version))
+    }
+
+    /// Returns the version pinned by a lone `==` or `===` clause, if that
+    /// is what this is.
+    pub(crate) fn pinned(&self) -> Option<&Version> {
+        match self.0.as_slice() {
+            [only]
+                if matches!(only.operator, Operator::Equal | Operator::Arbitrary)
+                    && !only.wildcard =>
+            {
+                Some(&only.version)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn normalizes_alternative_spellings() {
+        for (written, normalized) in [
+            ("1.0.0", "1.0.0"),
+            ("v1.0", "1.0"),
+            ("1.0-alpha.1", "1.0a1"),
+            ("1.0_RC2", "1.0rc2"),
+            ("1.0c1", "1.0rc1"),
+            ("1.0preview1", "1.0rc1"),
+            ("1.0b", "1.0b0"),
+            ("1.0-1", "1.0.post1"),
+            ("1.0-r2", "1.0.post2"),
+            ("1.0.dev", "1.0.dev0"),
+            ("2!1.0+Ubuntu-1", "2!1.0+ubuntu.1"),
+        ] {
+            assert_eq!(version(written).to_string(), normalized, "{written}");
+        }
+        assert_eq!(version("1.0"), version("1.0.0"));
+        for invalid in ["", "a1", "1.0x", "1.0+", "1.0+a..b", "x!1.0"] {
+            assert!(invalid.parse::<Version>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn orders_as_pep_440_does() {
+        let ordered = [
+            "1.0.dev456",
+            "1.0a1",
+            "1.0a2.dev456",
+            "1.0a12.dev456",
+            "1.0a12",
+            "1.0b1.dev456",
+            "1.0b2",
+            "1.0b2.post345.dev456",
+            "1.0b2.post345",
+            "1.0rc1.dev456",
+            "1.0rc1",
+            "1.0",
+            "1.0+abc.5",
+            "1.0+abc.7",
+            "1.0+5",
+            "1.0.post456.dev34",
+            "1.0.post456",
+            "1.1.dev1",
+            "2.0",
+            "1!0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(
+                version(pair[0]) < version(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn specifiers_contain_matching_versions() {
+        for (specifiers, candidate, expected) in [
+            (">=1.2,<2", "1.5", true),
+            (">=1.2,<2", "2.0", false),
+            ("~=2.2", "2.9", true),
+            ("~=2.2", "3.0", false),
+            ("~=1.4.5", "1.4.9", true),
+            ("~=1.4.5", "1.5.0", false),
+            ("==2.*", "2.3.1", true),
+            ("==2.*", "3.0", false),
+            ("!=1.5.*", "1.5.2", false),
+            ("!=1.5.*", "1.6", true),
+            ("<2.0", "1.9", true),
+            ("<2.0", "2.0a1", false),
+            ("<2.0rc1", "2.0a1", true),
+            (">1.7", "1.7.post1", false),
+            (">1.7.post1", "1.7.post2", true),
+            ("==1.0", "1.0+local", true),
+            ("==1.0+local", "1.0", false),
+            ("<=1.0", "1.0+local", true),
+            ("===1.0", "1.0", true),
+            ("===1.0", "1.0.0", false),
+            ("", "0.1", true),
+        ] {
+            let parsed: Specifiers = specifiers.parse().unwrap();
+            assert_eq!(
+                parsed.contains(&version(candidate)),
+                expected,
+                "{candidate} in {specifiers}"
+            );
+        }
+        for invalid in ["~=1", "1.0", "=>1.0", ">=1.0.*", "==1.x"] {
+            assert!(invalid.parse::<Specifiers>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn pinned_needs_a_lone_exact_clause() {
+        for (specifiers, expected) in [
+            ("==1.2", Some("1.2")),
+            ("===1.2", Some("1.2")),
+            ("==1.*", None),
+            (">=1", None),
+            ("==1.2,<2", None),
+        ] {
+            let parsed: Specifiers = specifiers.parse().unwrap();
+            let pinned = parsed.pinned().map(ToString::to_string);
+            assert_eq!(pinned.as_deref(), expected, "{specifiers}");
+        }
+    }
+}
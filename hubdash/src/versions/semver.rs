@@ -0,0 +1,186 @@
+//! Semantic versions, as used by Cargo, npm and Go modules.
+
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// A dot-separated part of a pre-release, where numbers sort before text.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+/// A version such as `1.2.3` or `v0.4.0-beta.2+build.5`.
+///
+/// A leading `v`, as Go requires, is accepted, and build metadata is
+/// ignored, as it does not affect precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Version {
+    pub(crate) major: u64,
+    pub(crate) minor: u64,
+    pub(crate) patch: u64,
+    pre: Vec<Identifier>,
+}
+
+/// Error returned when a version is not a valid semantic version.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid semantic version")]
+pub(crate) struct InvalidVersion;
+
+impl FromStr for Version {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix('v').unwrap_or(s);
+        let s = s.split_once('+').map_or(s, |(version, _build)| version);
+        let (core, pre) = match s.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (s, None),
+        };
+        let mut numbers = core.split('.').map(|n| n.parse::<u64>());
+        let (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) = (
+            numbers.next(),
+            numbers.next(),
+            numbers.next(),
+            numbers.next(),
+        ) else {
+            return Err(InvalidVersion);
+        };
+        let pre = match pre {
+            Some(pre) => pre
+                .split('.')
+                .map(|identifier| match identifier.parse() {
+                    Ok(number) => Ok(Identifier::Numeric(number)),
+                    Err(_)
+                        if !identifier.is_empty()
+                            && identifier
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '-') =>
+                    {
+                        Ok(Identifier::Alphanumeric(identifier.to_owned()))
+                    }
+                    Err(_) => Err(InvalidVersion),
+                })
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+}
+
+impl Version {
+    /// Whether this is a pre-release such as `1.0.0-rc.1`.
+    pub(crate) fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /// Returns the part of the version a caret requirement such as `^1.2`
+    /// holds fixed: the major version, or for `0.x` releases the first
+    /// non-zero part.
+    pub(crate) fn caret_key(&self) -> (u8, u64) {
+        match (self.major, self.minor) {
+            (0, 0) => (2, self.patch),
+            (0, minor) => (1, minor),
+            (major, _) => (0, major),
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A release comes after all of its pre-releases.
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_prefixes_pre_releases_and_build_metadata() {
+        assert_eq!(version("v1.2.3"), version("1.2.3"));
+        assert_eq!(version(" 1.2.3+build.5 "), version("1.2.3"));
+        assert!(version("1.0.0-x-y.7").is_prerelease());
+        assert!(!version("1.0.0+build-1").is_prerelease());
+        for invalid in [
+            "",
+            "1",
+            "1.2",
+            "1.2.3.4",
+            "1.2.x",
+            "1.2.3-",
+            "1.2.3-a..b",
+            "1.2.3-a_b",
+            "^1.2.3",
+        ] {
+            assert_eq!(invalid.parse::<Version>(), Err(InvalidVersion), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn orders_pre_releases_before_their_release() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1-0.20220101000000-0123456789ab",
+            "1.0.1",
+            "1.10.0",
+            "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(
+                version(pair[0]) < version(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn caret_key_holds_the_first_non_zero_part() {
+        for (a, b, compatible) in [
+            ("1.2.3", "1.9.0", true),
+            ("1.2.3", "2.0.0", false),
+            ("0.8.8", "0.8.9", true),
+            ("0.8.8", "0.9.0", false),
+            ("0.0.3", "0.0.4", false),
+        ] {
+            assert_eq!(
+                version(a).caret_key() == version(b).caret_key(),
+                compatible,
+                "{a} ~ {b}"
+            );
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Receiver for GitHub webhook deliveries.
+//!
+//! Deliveries are authenticated with the `X-Hub-Signature-256` HMAC and
+//! applied directly to the [`RepoCache`](crate::cache::RepoCache), so the dashboard reflects new
+//! runs without waiting for the next poll.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, de::DeserializeOwned};
+use sha2::Sha256;
+
+use crate::AppState;
+use crate::cache::background;
+use crate::dashboard::RepoSummary;
+use crate::github::{Job, Repository, WorkflowRun, event_trigger, pipeline_status};
+
+/// Number of delivery IDs remembered for replay protection.
+const DELIVERY_LOG_SIZE: usize = 1024;
+
+/// Settings and state for receiving webhooks.
+pub struct Webhooks {
+    secret: Vec<u8>,
+    deliveries: DeliveryLog,
+}
+
+impl Webhooks {
+    /// Creates a receiver that verifies deliveries with `secret`.
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self {
+            secret,
+            deliveries: DeliveryLog::default(),
+        }
+    }
+
+    /// Checks a `sha256=<hex>` signature against the raw request body.
+    fn verify(&self, signature: &str, body: &[u8]) -> bool {
+        let Some(digest) = signature
+            .strip_prefix("sha256=")
+            .and_then(|hex| hex::decode(hex).ok())
+        else {
+            return false;
+        };
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any size");
+        mac.update(body);
+        mac.verify_slice(&digest).is_ok()
+    }
+}
+
+/// The most recently processed delivery IDs.
+#[derive(Default)]
+struct DeliveryLog {
+    seen: Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl DeliveryLog {
+    /// Claims a delivery for processing, unless it was already claimed.
+    fn reserve(&self, id: &str) -> bool {
+        let mut guard = self.seen.lock().expect("delivery log poisoned");
+        let (seen, order) = &mut *guard;
+        if !seen.insert(id.to_owned()) {
+            return false;
+        }
+        order.push_back(id.to_owned());
+        if order.len() > DELIVERY_LOG_SIZE
+            && let Some(oldest) = order.pop_front()
+        {
+            seen.remove(&oldest);
+        }
+        true
+    }
+
+    /// Gives up the claim on a delivery that was rejected, so it can be
+    /// redelivered.
+    fn release(&self, id: &str) {
+        let mut guard = self.seen.lock().expect("delivery log poisoned");
+        let (seen, order) = &mut *guard;
+        if seen.remove(id) {
+            order.retain(|seen| seen != id);
+        }
+    }
+}
+
+/// Reasons a delivery is rejected.
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("webhooks are not configured")]
+    Disabled,
+    #[error("missing `{0}` header")]
+    MissingHeader(&'static str),
+    #[error("signature does not match the payload")]
+    InvalidSignature,
+    #[error("delivery was already processed")]
+    Replayed,
+    #[error("malformed payload: {0}")]
+    Payload(#[from] serde_json::Error),
+}
+
+impl IntoResponse for WebhookError {
+    fn into_response(self) -> Response {
+        tracing::warn!(error = %self, "rejected webhook delivery");
+        let status = match self {
+            Self::Disabled => StatusCode::NOT_FOUND,
+            Self::MissingHeader(_) | Self::Payload(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidSignature => StatusCode::UNAUTHORIZED,
+            Self::Replayed => StatusCode::CONFLICT,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct WorkflowRunEvent {
+    workflow_run: WorkflowRun,
+    repository: Repository,
+}
+
+#[derive(Deserialize)]
+struct WorkflowJobEvent {
+    workflow_job: Job,
+    repository: Repository,
+}
+
+#[derive(Deserialize)]
+struct CheckSuiteEvent {
+    check_suite: CheckSuite,
+    repository: Repository,
+}
+
+/// The slug of the GitHub App that runs GitHub Actions workflows.
+const ACTIONS_APP: &str = "github-actions";
+
+#[derive(Deserialize)]
+struct CheckSuite {
+    app: Option<App>,
+    head_branch: Option<String>,
+    status: Option<String>,
+    conclusion: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct App {
+    slug: String,
+}
+
+impl CheckSuite {
+    /// Whether GitHub Actions, rather than some other CI app, ran the
+    /// suite.
+    fn is_actions(&self) -> bool {
+        self.app.as_ref().is_some_and(|app| app.slug == ACTIONS_APP)
+    }
+}
+
+#[derive(Deserialize)]
+struct PushEvent {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    repository: Repository,
+}
+
+fn is_default_branch(repository: &Repository, branch: Option<&str>) -> bool {
+    branch == Some(repository.default_branch.as_str())
+}
+
+/// Records the latest run of a workflow on a cached summary.
+fn apply_run(summary: &mut RepoSummary, repository: &Repository, run: &WorkflowRun) {
+    let pipeline = run.to_pipeline_summary();
+    summary.last_status = pipeline.status;
+    summary.triggers |= event_trigger(
+        &run.event,
+        is_default_branch(repository, run.head_branch.as_deref()),
+    );
+    match summary
+        .pipelines
+        .iter_mut()
+        .find(|p| p.name == pipeline.name)
+    {
+        Some(existing) => *existing = pipeline,
+        None => summary.pipelines.push(pipeline),
+    }
+}
+
+/// Records a job's progress on the pipeline it belongs to.
+///
+/// Jobs only move a pipeline back to an unfinished status or fail it early;
+/// any other conclusion is left to the `workflow_run` event that completes
+/// it.
+fn apply_job(summary: &mut RepoSummary, job: &Job) {
+    let status = job.pipeline_status();
+    if status.is_finished() && !status.is_failure() {
+        return;
+    }
+    let Some(pipeline) = summary
+        .pipelines
+        .iter_mut()
+        .find(|p| Some(&p.name) == job.workflow_name.as_ref())
+    else {
+        return;
+    };
+    pipeline.status = status;
+    if !status.is_finished() {
+        pipeline.run_time = None;
+    }
+    summary.last_status = status;
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, WebhookError> {
+    Ok(serde_json::from_slice(body)?)
+}
+
+fn header<'h>(headers: &'h HeaderMap, name: &'static str) -> Result<&'h str, WebhookError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(WebhookError::MissingHeader(name))
+}
+
+/// Receives a webhook delivery from GitHub.
+///
+/// `workflow_run`, `workflow_job`, `check_suite` and `push` events update
+/// the cached repository data; other events are acknowledged and ignored.
+/// A push to the default branch refreshes the repository in the
+/// background. Each delivery ID is only processed once, but a delivery
+/// that was rejected can be redelivered.
+pub async fn receive(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, WebhookError> {
+    let webhooks = state.webhooks().ok_or(WebhookError::Disabled)?;
+    let signature = header(&headers, "X-Hub-Signature-256")?;
+    if !webhooks.verify(signature, &body) {
+        return Err(WebhookError::InvalidSignature);
+    }
+    let event = header(&headers, "X-GitHub-Event")?;
+    let delivery = header(&headers, "X-GitHub-Delivery")?;
+    // Claimed before handling, so concurrent copies of a delivery are not
+    // both applied.
+    if !webhooks.deliveries.reserve(delivery) {
+        return Err(WebhookError::Replayed);
+    }
+    tracing::debug!(event, delivery, "received webhook");
+
+    if let Err(err) = handle(&state, event, &body).await {
+        webhooks.deliveries.release(delivery);
+        return Err(err);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Applies a delivery of `event` to the cached repository data.
+async fn handle(state: &AppState, event: &str, body: &[u8]) -> Result<(), WebhookError> {
+    let cache = state.cache();
+    match event {
+        "workflow_run" => {
+            let WorkflowRunEvent {
+                workflow_run,
+                repository,
+            } = parse(body)?;
+            cache.update(&repository.owner.login, &repository.name, |summary| {
+                apply_run(summary, &repository, &workflow_run)
+            });
+            let record = workflow_run.to_run_record(&repository.owner.login, &repository.name);
+            state.history().record(&[record]).await;
+        }
+        "workflow_job" => {
+            let WorkflowJobEvent {
+                workflow_job,
+                repository,
+            } = parse(body)?;
+            cache.update(&repository.owner.login, &repository.name, |summary| {
+                apply_job(summary, &workflow_job)
+            });
+        }
+        "check_suite" => {
+            let CheckSuiteEvent {
+                check_suite,
+                repository,
+            } = parse(body)?;
+            if check_suite.is_actions()
+                && is_default_branch(&repository, check_suite.head_branch.as_deref())
+            {
+                let status = pipeline_status(
+                    check_suite.status.as_deref(),
+                    check_suite.conclusion.as_deref(),
+                );
+                cache.update(&repository.owner.login, &repository.name, |summary| {
+                    summary.last_status = status
+                });
+            }
+        }
+        "push" => {
+            let PushEvent {
+                git_ref,
+                repository,
+            } = parse(body)?;
+            let branch = git_ref.strip_prefix("refs/heads/");
+            if is_default_branch(&repository, branch) {
+                // A full refresh can outlast GitHub's delivery timeout.
+                let cache = cache.clone();
+                background(async move {
+                    let refreshed = cache
+                        .refresh(&repository.owner.login, &repository.name)
+                        .await;
+                    if let Err(err) = refreshed {
+                        tracing::warn!(repo = %repository.name, error = %err, "failed to refresh repository after push");
+                    }
+                })
+                .await;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
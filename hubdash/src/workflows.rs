@@ -0,0 +1,330 @@
+//! Triggers of GitHub Actions workflows, read from their YAML files.
+//!
+//! The `on:` key of a workflow takes an event name, a list of them, or a
+//! map from event names to their configuration. Branch filters decide
+//! whether pushes to and pull requests into the default branch count, and
+//! branch and tag filters whether other pushes do.
+
+use serde_yaml_ng::Value;
+
+use crate::dashboard::Triggers;
+
+/// The directory GitHub looks for workflow files in.
+pub(crate) const WORKFLOWS_DIR: &str = ".github/workflows";
+
+/// Whether `file_name` is that of a workflow file.
+pub(crate) fn is_workflow_file(file_name: &str) -> bool {
+    file_name.ends_with(".yml") || file_name.ends_with(".yaml")
+}
+
+/// The triggers of one workflow file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WorkflowTriggers {
+    /// The workflow's name, which defaults to the path of its file.
+    pub(crate) name: String,
+    /// The events that run the workflow.
+    pub(crate) triggers: Triggers,
+}
+
+/// Whether a branch name matches a filter pattern, where `*` matches
+/// anything but `/` and `**` matches anything.
+fn pattern_matches(pattern: &str, branch: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**") {
+        return (0..=branch.len())
+            .filter(|&i| branch.is_char_boundary(i))
+            .any(|i| pattern_matches(rest, &branch[i..]));
+    }
+    if let Some(rest) = pattern.strip_prefix('*') {
+        let segment = branch.find('/').unwrap_or(branch.len());
+        return (0..=segment)
+            .filter(|&i| branch.is_char_boundary(i))
+            .any(|i| pattern_matches(rest, &branch[i..]));
+    }
+    let mut chars = pattern.chars();
+    match chars.next() {
+        None => branch.is_empty(),
+        Some(expected) => {
+            let mut rest = branch.chars();
+            rest.next() == Some(expected) && pattern_matches(chars.as_str(), rest.as_str())
+        }
+    }
+}
+
+/// Returns the patterns of a filter, which may be a single string.
+fn patterns<'a>(config: &'a Value, key: &str) -> Option<Vec<&'a str>> {
+    match config.get(key)? {
+        Value::String(pattern) => Some(vec![pattern]),
+        Value::Sequence(patterns) => Some(patterns.iter().filter_map(Value::as_str).collect()),
+        _ => None,
+    }
+}
+
+/// Whether a filter matching `patterns` in order lets `branch` through,
+/// with later patterns overriding earlier ones and `!` negating a pattern.
+fn filter_allows(patterns: &[&str], branch: &str) -> bool {
+    patterns
+        .iter()
+        .fold(false, |matched, pattern| match pattern.strip_prefix('!') {
+            Some(negated) if pattern_matches(negated, branch) => false,
+            Some(_) => matched,
+            None => matched || pattern_matches(pattern, branch),
+        })
+}
+
+/// Whether an ignore filter matches every name, leaving nothing through.
+fn ignores_all(patterns: &[&str]) -> bool {
+    patterns.contains(&"**")
+}
+
+/// Whether a branch filter lets `branch` through, and whether it lets
+/// any other branch through, or `None` if there is no branch filter.
+fn branch_filter(config: &Value, branch: &str) -> Option<(bool, bool)> {
+    if let Some(patterns) = patterns(config, "branches") {
+        let others = patterns
+            .iter()
+            .any(|&pattern| !pattern.starts_with('!') && pattern != branch);
+        return Some((filter_allows(&patterns, branch), others));
+    }
+    let patterns = patterns(config, "branches-ignore")?;
+    let ignored = patterns
+        .iter()
+        .any(|pattern| pattern_matches(pattern, branch));
+    Some((!ignored, !ignores_all(&patterns)))
+}
+
+/// Returns the triggers of a `push` event configured with `config`.
+///
+/// A push filtered only by branches never fires for tags, and one
+/// filtered only by tags never fires for branches.
+fn push_triggers(config: Option<&Value>, default_branch: &str) -> Triggers {
+    let Some(config) = config.filter(|config| config.is_mapping()) else {
+        return Triggers::MAIN | Triggers::BRANCH | Triggers::TAG;
+    };
+    let tags = match (patterns(config, "tags"), patterns(config, "tags-ignore")) {
+        (Some(_), _) => Some(true),
+        (None, Some(ignored)) => Some(!ignores_all(&ignored)),
+        (None, None) => None,
+    };
+    let branches = branch_filter(config, default_branch);
+    let (main, others) = branches.unwrap_or((tags.is_none(), tags.is_none()));
+    let mut triggers = Triggers::empty();
+    triggers.set(Triggers::MAIN, main);
+    triggers.set(Triggers::BRANCH, others);
+    triggers.set(Triggers::TAG, tags.unwrap_or(branches.is_none()));
+    triggers
+}
+
+/// Whether a pull request event configured with `config` fires for pull
+/// requests into `branch`.
+fn pull_request_fires(config: Option<&Value>, branch: &str) -> bool {
+    config
+        .filter(|config| config.is_mapping())
+        .and_then(|config| branch_filter(config, branch))
+        .is_none_or(|(fires, _)| fires)
+}
+
+/// Returns the events in an `on:` value, with their configuration if any.
+fn events(on: &Value) -> Vec<(&str, Option<&Value>)> {
+    match on {
+        Value::String(event) => vec![(event.as_str(), None)],
+        Value::Sequence(events) => events
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|event| (event, None))
+            .collect(),
+        Value::Mapping(events) => events
+            .iter()
+            .filter_map(|(event, config)| Some((event.as_str()?, Some(config))))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the triggers of an `on:` value for a repository whose default
+/// branch is `default_branch`.
+fn triggers(on: &Value, default_branch: &str) -> Triggers {
+    events(on)
+        .into_iter()
+        .map(|(event, config)| match event {
+            "push" => push_triggers(config, default_branch),
+            "pull_request" | "pull_request_target"
+                if pull_request_fires(config, default_branch) =>
+            {
+                Triggers::PR
+            }
+            "merge_group" => Triggers::MERGE_GROUP,
+            "schedule" => Triggers::SCHEDULED,
+            "workflow_dispatch" => Triggers::MANUAL,
+            "release" => Triggers::RELEASE,
+            "repository_dispatch" => Triggers::DISPATCH,
+            "workflow_call" => Triggers::WORKFLOW_CALL,
+            _ => Triggers::empty(),
+        })
+        .fold(Triggers::empty(), |acc, t| acc | t)
+}
+
+/// Parses the workflow file at `path` for a repository whose default
+/// branch is `default_branch`.
+pub(crate) fn parse(
+    path: &str,
+    text: &str,
+    default_branch: &str,
+) -> Result<WorkflowTriggers, serde_yaml_ng::Error> {
+    let workflow: Value = serde_yaml_ng::from_str(text)?;
+    let name = workflow
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or(path)
+        .to_owned();
+    let triggers = workflow
+        .get("on")
+        .map_or(Triggers::empty(), |on| triggers(on, default_branch));
+    Ok(WorkflowTriggers { name, triggers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUSHES: Triggers = Triggers::MAIN.union(Triggers::BRANCH).union(Triggers::TAG);
+
+    fn parse_on(on: &str) -> Triggers {
+        parse("ci.yml", &format!("on:\n{on}"), "main")
+            .unwrap()
+            .triggers
+    }
+
+    #[test]
+    fn pattern_matches_stars_within_and_across_segments() {
+        for (pattern, branch, expected) in [
+            ("main", "main", true),
+            ("main", "mainline", false),
+            ("release/*", "release/1.0", true),
+            ("release/*", "release/1.0/hotfix", false),
+            ("release/**", "release/1.0/hotfix", true),
+            ("**", "feature/a/b", true),
+            ("*", "feature/a", false),
+            ("*", "", true),
+            ("v*.*", "v1.2", true),
+            ("**-rc", "release/2.0-rc", true),
+            ("feat*/**", "feature/x/y", true),
+        ] {
+            assert_eq!(
+                pattern_matches(pattern, branch),
+                expected,
+                "{pattern} ~ {branch}"
+            );
+        }
+    }
+
+    #[test]
+    fn later_patterns_override_earlier_ones() {
+        for (patterns, branch, expected) in [
+            (
+                &["release/**", "!release/**-alpha"][..],
+                "release/1.0",
+                true,
+            ),
+            (
+                &["release/**", "!release/**-alpha"][..],
+                "release/1.0-alpha",
+                false,
+            ),
+            (
+                &["!release/**-alpha", "release/**"][..],
+                "release/1.0-alpha",
+                true,
+            ),
+            (&["**", "!main", "main"][..], "main", true),
+            (&["**", "main", "!main"][..], "main", false),
+            (&["!main"][..], "develop", false),
+        ] {
+            assert_eq!(
+                filter_allows(patterns, branch),
+                expected,
+                "{patterns:?} {branch}"
+            );
+        }
+    }
+
+    #[test]
+    fn push_filters_decide_which_refs_count() {
+        for (on, expected) in [
+            ("  push:\n", PUSHES),
+            ("  push:\n    paths: [src/**]\n", PUSHES),
+            ("  push:\n    branches: [main]\n", Triggers::MAIN),
+            ("  push:\n    branches: main\n", Triggers::MAIN),
+            (
+                "  push:\n    branches: [main, 'release/**']\n",
+                Triggers::MAIN | Triggers::BRANCH,
+            ),
+            ("  push:\n    branches: ['**', '!main']\n", Triggers::BRANCH),
+            ("  push:\n    tags: ['v*']\n", Triggers::TAG),
+            ("  push:\n    tags-ignore: ['**']\n", Triggers::empty()),
+            ("  push:\n    tags-ignore: ['nightly']\n", Triggers::TAG),
+            ("  push:\n    branches-ignore: [main]\n", Triggers::BRANCH),
+            ("  push:\n    branches-ignore: ['**']\n", Triggers::empty()),
+            (
+                "  push:\n    branches: [main]\n    tags: ['v*']\n",
+                Triggers::MAIN | Triggers::TAG,
+            ),
+        ] {
+            assert_eq!(parse_on(on), expected, "{on}");
+        }
+    }
+
+    #[test]
+    fn pull_requests_count_when_they_target_the_default_branch() {
+        for (on, expected) in [
+            ("  pull_request:\n", Triggers::PR),
+            ("  pull_request:\n    types: [opened]\n", Triggers::PR),
+            ("  pull_request:\n    branches: [main]\n", Triggers::PR),
+            (
+                "  pull_request:\n    branches: ['release/*']\n",
+                Triggers::empty(),
+            ),
+            (
+                "  pull_request_target:\n    branches-ignore: [main]\n",
+                Triggers::empty(),
+            ),
+            (
+                "  pull_request_target:\n    branches-ignore: [gh-pages]\n",
+                Triggers::PR,
+            ),
+        ] {
+            assert_eq!(parse_on(on), expected, "{on}");
+        }
+    }
+
+    #[test]
+    fn parse_reads_every_form_of_on() {
+        for (text, expected) in [
+            ("on: push\n", PUSHES),
+            (
+                "on: [pull_request, workflow_dispatch]\n",
+                Triggers::PR | Triggers::MANUAL,
+            ),
+            (
+                "on:\n  schedule:\n    - cron: '0 0 * * *'\n  release:\n    types: [published]\n  merge_group:\n",
+                Triggers::SCHEDULED | Triggers::RELEASE | Triggers::MERGE_GROUP,
+            ),
+            (
+                "on:\n  repository_dispatch:\n  workflow_call:\n  issues:\n",
+                Triggers::DISPATCH | Triggers::WORKFLOW_CALL,
+            ),
+            ("name: No triggers\n", Triggers::empty()),
+        ] {
+            let parsed = parse(".github/workflows/ci.yml", text, "main").unwrap();
+            assert_eq!(parsed.triggers, expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn parse_names_workflows_after_their_file_by_default() {
+        let named = parse("ci.yml", "name: CI\non: push\n", "main").unwrap();
+        assert_eq!(named.name, "CI");
+        let unnamed = parse(".github/workflows/ci.yml", "on: push\n", "main").unwrap();
+        assert_eq!(unnamed.name, ".github/workflows/ci.yml");
+        assert!(parse("ci.yml", "on: [push\n", "main").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Tests for signing in and out against a stand-in GitHub.
+
+mod common;
+
+use std::time::Duration;
+
+use axum::{
+    Router,
+    http::StatusCode,
+    routing::{delete, get, post},
+};
+use hubdash::{AppState, MockRepos, OAuthConfig, create_router};
+use jiff::SignedDuration;
+use reqwest::{Response, header};
+use url::Url;
+
+use common::{json, stand_in};
+
+const SESSION_COOKIE: &str = "hubdash_session";
+const STATE_COOKIE: &str = "hubdash_oauth_state";
+
+/// Returns application state signing in against a stand-in GitHub that
+/// accepts any authorization code.
+async fn signing_in() -> AppState {
+    let router = Router::new()
+        .route(
+            "/login/oauth/access_token",
+            post(|| async { json(r#"{"access_token":"gho_test","token_type":"bearer"}"#.into()) }),
+        )
+        .route(
+            "/user",
+            get(|| async { json(r#"{"login":"octocat","name":null}"#.into()) }),
+        )
+        .route(
+            "/applications/{client_id}/token",
+            delete(|| async { StatusCode::NO_CONTENT }),
+        );
+    let github = stand_in(router).await;
+    let oauth = OAuthConfig::new("client-id", "client-secret")
+        .with_token_url(github.join("login/oauth/access_token").unwrap())
+        .with_api_url(github);
+    AppState::new(MockRepos).with_oauth(oauth)
+}
+
+/// The application served on a local port, and a browser that does not
+/// follow redirects.
+struct App {
+    base: Url,
+    http: reqwest::Client,
+}
+
+impl App {
+    async fn serve(state: AppState) -> Self {
+        Self {
+            base: stand_in(create_router(state)).await,
+            http: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap(),
+        }
+    }
+
+    async fn get(&self, path: &str, cookie: Option<(&str, &str)>) -> Response {
+        let mut request = self.http.get(self.base.join(path).unwrap());
+        if let Some((name, value)) = cookie {
+            request = request.header(header::COOKIE, format!("{name}={value}"));
+        }
+        request.send().await.unwrap()
+    }
+
+    /// Starts signing in and returns the `state` GitHub is sent.
+    async fn start_sign_in(&self) -> String {
+        let response = self.get("auth/signin", None).await;
+        assert!(response.status().is_redirection());
+        cookie(&response, STATE_COOKIE).expect("state cookie set")
+    }
+
+    /// Signs in and returns the session ID.
+    async fn sign_in(&self) -> String {
+        let state = self.start_sign_in().await;
+        let response = self
+            .get(
+                &format!("auth/callback?code=abc&state={state}"),
+                Some((STATE_COOKIE, &state)),
+            )
+            .await;
+        assert_eq!(location(&response), "/dashboard");
+        cookie(&response, SESSION_COOKIE).expect("session cookie set")
+    }
+
+    async fn sign_out(&self, session: &str, origin: &str) -> Response {
+        self.http
+            .post(self.base.join("auth/signout").unwrap())
+            .header(header::COOKIE, format!("{SESSION_COOKIE}={session}"))
+            .header(header::ORIGIN, origin)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    fn origin(&self) -> String {
+        self.base.origin().ascii_serialization()
+    }
+}
+
+/// Returns the value a response sets the named cookie to.
+fn cookie(response: &Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next()?.split_once('='))
+        .find(|(cookie, _)| *cookie == name)
+        .map(|(_, value)| value.to_owned())
+}
+
+fn location(response: &Response) -> &str {
+    response.headers()[header::LOCATION].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn signs_in_through_github() {
+    let app = App::serve(signing_in().await).await;
+
+    let response = app.get("auth/signin", None).await;
+    let state = cookie(&response, STATE_COOKIE).unwrap();
+    let authorize = Url::parse(location(&response)).unwrap();
+    assert!(
+        authorize
+            .query_pairs()
+            .any(|(key, value)| key == "state" && value == state)
+    );
+
+    let session = app.sign_in().await;
+    assert_eq!(session.len(), 64);
+}
+
+#[tokio::test]
+async fn callback_rejects_a_state_not_matching_the_cookie() {
+    let app = App::serve(signing_in().await).await;
+    let state = app.start_sign_in().await;
+
+    let state_cookie = Some((STATE_COOKIE, state.as_str()));
+    for (query, sent) in [
+        ("code=abc&state=forged", state_cookie),
+        ("code=abc&state=forged", None),
+        ("code=abc", state_cookie),
+    ] {
+        let response = app.get(&format!("auth/callback?{query}"), sent).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+        assert_eq!(cookie(&response, SESSION_COOKIE), None, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn sign_out_is_refused_from_other_sites() {
+    let app = App::serve(signing_in().await).await;
+    let session = app.sign_in().await;
+
+    let response = app.sign_out(&session, "https://evil.example").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(cookie(&response, SESSION_COOKIE), None);
+
+    let response = app.sign_out(&session, &app.origin()).await;
+    assert_eq!(location(&response), "/");
+    assert_eq!(cookie(&response, SESSION_COOKIE).as_deref(), Some(""));
+}
+
+#[tokio::test]
+async fn dashboard_redirects_to_sign_in_without_a_session() {
+    let app = App::serve(signing_in().await).await;
+
+    for cookie in [None, Some((SESSION_COOKIE, "not-a-session"))] {
+        let response = app.get("dashboard", cookie).await;
+        assert!(response.status().is_redirection(), "{cookie:?}");
+        assert_eq!(location(&response), "/", "{cookie:?}");
+    }
+}
+
+#[tokio::test]
+async fn htmx_requests_without_a_session_redirect_the_whole_page() {
+    let app = App::serve(signing_in().await).await;
+
+    let response = app
+        .http
+        .get(
+            app.base
+                .join("dashboard/repo/example/hubdash/expand")
+                .unwrap(),
+        )
+        .header("HX-Request", "true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["HX-Redirect"], "/");
+}
+
+#[tokio::test]
+async fn signed_in_users_see_the_dashboard() {
+    let app = App::serve(signing_in().await).await;
+    let session = app.sign_in().await;
+
+    let response = app.get("dashboard", Some((SESSION_COOKIE, &session))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.text().await.unwrap().contains("octocat"));
+}
+
+#[tokio::test]
+async fn expired_sessions_are_rejected() {
+    let state = signing_in()
+        .await
+        .with_session_ttl(SignedDuration::from_millis(50));
+    let app = App::serve(state).await;
+    let session = app.sign_in().await;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let response = app.get("dashboard", Some((SESSION_COOKIE, &session))).await;
+    assert_eq!(location(&response), "/");
+}
+
+#[tokio::test]
+async fn signed_out_sessions_are_rejected() {
+    let app = App::serve(signing_in().await).await;
+    let session = app.sign_in().await;
+
+    app.sign_out(&session, &app.origin()).await;
+    let response = app.get("dashboard", Some((SESSION_COOKIE, &session))).await;
+    assert_eq!(location(&response), "/");
+}
@@ -0,0 +1,49 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use axum::{
+    Router,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use url::Url;
+
+/// Reads a recorded response from `tests/fixtures`.
+pub fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("cannot read fixture {}: {err}", path.display()))
+}
+
+/// Responds with a JSON body.
+pub fn json(body: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/json; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+/// Responds with a recorded JSON fixture.
+pub fn json_fixture(name: &str) -> Response {
+    json(fixture(name))
+}
+
+/// Serves `router` on an ephemeral local port and returns its base URL.
+pub async fn stand_in(router: Router) -> Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind stand-in server");
+    let addr = listener.local_addr().expect("stand-in address");
+    tokio::spawn(async move {
+        axum::serve(listener, router)
+            .await
+            .expect("stand-in server failed");
+    });
+    Url::parse(&format!("http://{addr}/")).expect("valid stand-in URL")
+}
@@ -0,0 +1,246 @@
+//! Tests for conditional requests against a stand-in GitHub.
+
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use axum::{
+    Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use hubdash::github::{GitHubClient, RunQuery, WorkflowRun};
+use jiff::{SignedDuration, Timestamp};
+use tokio::sync::Notify;
+
+use common::{fixture, stand_in};
+
+const RUNS_ETAG: &str = "W/\"8c1f5e0d2b7a\"";
+const REPO_LAST_MODIFIED: &str = "Tue, 14 Jan 2025 18:02:34 GMT";
+
+/// A stand-in GitHub that charges one request of budget for every full
+/// response, but nothing for a `304 Not Modified`.
+#[derive(Clone)]
+struct Upstream {
+    remaining: Arc<AtomicU32>,
+    not_modified: Arc<AtomicU32>,
+    runs_version: Arc<AtomicU32>,
+}
+
+impl Upstream {
+    fn new() -> Self {
+        Self {
+            remaining: Arc::new(AtomicU32::new(5000)),
+            not_modified: Arc::new(AtomicU32::new(0)),
+            runs_version: Arc::new(AtomicU32::new(1)),
+        }
+    }
+
+    fn runs_etag(&self) -> String {
+        match self.runs_version.load(Ordering::SeqCst) {
+            1 => RUNS_ETAG.to_owned(),
+            version => format!("W/\"runs-v{version}\""),
+        }
+    }
+
+    fn respond(
+        &self,
+        unchanged: bool,
+        validator: (header::HeaderName, String),
+        body: &str,
+    ) -> Response {
+        let remaining = if unchanged {
+            self.not_modified.fetch_add(1, Ordering::SeqCst);
+            self.remaining.load(Ordering::SeqCst)
+        } else {
+            self.remaining.fetch_sub(1, Ordering::SeqCst) - 1
+        };
+        let reset = (Timestamp::now() + SignedDuration::from_hours(1)).as_second();
+        let headers = [
+            ("X-RateLimit-Limit".to_owned(), "5000".to_owned()),
+            ("X-RateLimit-Remaining".to_owned(), remaining.to_string()),
+            ("X-RateLimit-Reset".to_owned(), reset.to_string()),
+            ("X-RateLimit-Resource".to_owned(), "core".to_owned()),
+            (validator.0.to_string(), validator.1),
+        ];
+        if unchanged {
+            (StatusCode::NOT_MODIFIED, headers).into_response()
+        } else {
+            (
+                headers,
+                [(header::CONTENT_TYPE, "application/json; charset=utf-8")],
+                fixture(body),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn runs(State(upstream): State<Upstream>, headers: HeaderMap) -> Response {
+    let etag = upstream.runs_etag();
+    let unchanged = headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| value.as_bytes() == etag.as_bytes());
+    upstream.respond(unchanged, (header::ETAG, etag), "github/runs.json")
+}
+
+async fn repo(State(upstream): State<Upstream>, headers: HeaderMap) -> Response {
+    let unchanged = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .is_some_and(|value| value.as_bytes() == REPO_LAST_MODIFIED.as_bytes());
+    upstream.respond(
+        unchanged,
+        (header::LAST_MODIFIED, REPO_LAST_MODIFIED.to_owned()),
+        "github/repo.json",
+    )
+}
+
+async fn github_stand_in(upstream: &Upstream) -> GitHubClient {
+    let router = Router::new()
+        .route("/repos/example/hubdash", get(repo))
+        .route("/repos/example/hubdash/actions/runs", get(runs))
+        .with_state(upstream.clone());
+    GitHubClient::new(stand_in(router).await).with_token("test-token")
+}
+
+fn core_remaining(client: &GitHubClient) -> u32 {
+    client
+        .rate_limits()
+        .budgets()
+        .into_iter()
+        .find(|budget| budget.resource == "core")
+        .expect("core budget tracked")
+        .remaining
+}
+
+fn run_ids(runs: &[WorkflowRun]) -> Vec<u64> {
+    runs.iter().map(|run| run.id).collect()
+}
+
+#[tokio::test]
+async fn unchanged_runs_cost_no_budget() {
+    let upstream = Upstream::new();
+    let client = github_stand_in(&upstream).await;
+    let query = RunQuery::default();
+
+    let first = client
+        .list_workflow_runs("example", "hubdash", &query)
+        .await
+        .unwrap();
+    let spent = core_remaining(&client);
+    let second = client
+        .list_workflow_runs("example", "hubdash", &query)
+        .await
+        .unwrap();
+
+    assert_eq!(spent, 4999);
+    assert_eq!(core_remaining(&client), spent);
+    assert_eq!(upstream.not_modified.load(Ordering::SeqCst), 1);
+    assert_eq!(run_ids(&first), run_ids(&second));
+}
+
+#[tokio::test]
+async fn changed_runs_are_fetched_again() {
+    let upstream = Upstream::new();
+    let client = github_stand_in(&upstream).await;
+    let query = RunQuery::default();
+
+    client
+        .list_workflow_runs("example", "hubdash", &query)
+        .await
+        .unwrap();
+    upstream.runs_version.store(2, Ordering::SeqCst);
+    let runs = client
+        .list_workflow_runs("example", "hubdash", &query)
+        .await
+        .unwrap();
+
+    assert_eq!(runs.len(), 4);
+    assert_eq!(core_remaining(&client), 4998);
+    assert_eq!(upstream.not_modified.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn revalidates_with_last_modified() {
+    let upstream = Upstream::new();
+    let client = github_stand_in(&upstream).await;
+
+    client.get_repository("example", "hubdash").await.unwrap();
+    let repository = client.get_repository("example", "hubdash").await.unwrap();
+
+    assert_eq!(repository.name, "hubdash");
+    assert_eq!(core_remaining(&client), 4999);
+    assert_eq!(upstream.not_modified.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn validators_are_kept_per_endpoint() {
+    let upstream = Upstream::new();
+    let client = github_stand_in(&upstream).await;
+    let all = RunQuery::default();
+    let main = RunQuery {
+        branch: Some("main".into()),
+        ..RunQuery::default()
+    };
+
+    client
+        .list_workflow_runs("example", "hubdash", &all)
+        .await
+        .unwrap();
+    client
+        .list_workflow_runs("example", "hubdash", &main)
+        .await
+        .unwrap();
+
+    assert_eq!(core_remaining(&client), 4998);
+    assert_eq!(upstream.not_modified.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn refetches_bodies_dropped_while_revalidating() {
+    /// Answers the first revalidation only once a later response without
+    /// validators has dropped the cached body.
+    #[derive(Clone, Default)]
+    struct Racing {
+        requests: Arc<AtomicU32>,
+        revalidating: Arc<Notify>,
+        dropped: Arc<Notify>,
+    }
+
+    async fn repo(State(racing): State<Racing>) -> Response {
+        let last_modified = [(header::LAST_MODIFIED, REPO_LAST_MODIFIED)];
+        match racing.requests.fetch_add(1, Ordering::SeqCst) {
+            1 => {
+                racing.revalidating.notify_one();
+                racing.dropped.notified().await;
+                (StatusCode::NOT_MODIFIED, last_modified).into_response()
+            }
+            2 => fixture("github/repo.json").into_response(),
+            _ => (last_modified, fixture("github/repo.json")).into_response(),
+        }
+    }
+
+    let racing = Racing::default();
+    let router = Router::new()
+        .route("/repos/example/hubdash", get(repo))
+        .with_state(racing.clone());
+    let client = GitHubClient::new(stand_in(router).await).with_token("test-token");
+    client.get_repository("example", "hubdash").await.unwrap();
+
+    let (revalidated, replaced) =
+        tokio::join!(client.get_repository("example", "hubdash"), async {
+            racing.revalidating.notified().await;
+            let replaced = client.get_repository("example", "hubdash").await;
+            racing.dropped.notify_one();
+            replaced
+        });
+
+    assert_eq!(revalidated.unwrap().name, "hubdash");
+    assert_eq!(replaced.unwrap().name, "hubdash");
+    assert_eq!(racing.requests.load(Ordering::SeqCst), 4);
+}
@@ -0,0 +1,165 @@
+//! Tests for the batched GraphQL fetcher against a local mock endpoint.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Router,
+    extract::State,
+    http::{StatusCode, Uri},
+    response::Response,
+    routing::post,
+};
+use hubdash::github::{Error, GitHubClient, OrgRepos};
+use hubdash::{PipelineStatus, RepoSource, Triggers};
+use jiff::SignedDuration;
+use serde_json::Value;
+
+use common::{json_fixture, stand_in};
+
+type RequestLog = Arc<Mutex<Vec<Value>>>;
+
+/// Answers GraphQL queries from fixtures, recording the variables sent.
+async fn answer(State(log): State<RequestLog>, body: String) -> Response {
+    let request: Value = serde_json::from_str(&body).expect("JSON request body");
+    let variables = request["variables"].clone();
+    log.lock().unwrap().push(variables.clone());
+
+    let fixture = if variables["org"] == "missing" {
+        "github/graphql_not_found.json"
+    } else if variables.get("owner").is_some() {
+        "github/graphql_repo.json"
+    } else if variables["cursor"].is_null() {
+        "github/graphql_org_page1.json"
+    } else {
+        "github/graphql_org_page2.json"
+    };
+    json_fixture(fixture)
+}
+
+/// Records a request for anything but GraphQL, such as the contents API,
+/// by its path.
+async fn other_request(State(log): State<RequestLog>, uri: Uri) -> StatusCode {
+    log.lock()
+        .unwrap()
+        .push(Value::String(uri.path().to_owned()));
+    StatusCode::NOT_FOUND
+}
+
+async fn graphql_stand_in(path: &str) -> (GitHubClient, RequestLog) {
+    let log = RequestLog::default();
+    let router = Router::new()
+        .route(path, post(answer))
+        .fallback(other_request)
+        .with_state(log.clone());
+    let base = stand_in(router).await;
+    let api_url = if path == "/api/graphql" {
+        base.join("api/v3").unwrap()
+    } else {
+        base
+    };
+    (GitHubClient::new(api_url).with_token("test-token"), log)
+}
+
+#[tokio::test]
+async fn follows_pagination_across_the_organization() {
+    let (client, log) = graphql_stand_in("/graphql").await;
+
+    let repos = client.fetch_org_repos("example").await.unwrap();
+
+    let names: Vec<_> = repos.iter().map(|r| r.repo.as_str()).collect();
+    assert_eq!(names, ["api-gateway", "empty-repo", "hubdash"]);
+
+    let requests = log.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0]["cursor"].is_null());
+    assert_eq!(requests[1]["cursor"], "Y3Vyc29yOnYyOpHOAAAAAQ==");
+}
+
+#[tokio::test]
+async fn summarizes_default_branch_health() {
+    let (client, _) = graphql_stand_in("/graphql").await;
+
+    let repos = client.fetch_org_repos("example").await.unwrap();
+
+    let gateway = &repos[0];
+    assert_eq!(gateway.last_status, PipelineStatus::Failure);
+    assert_eq!(gateway.success_rate, 75);
+    assert_eq!(gateway.triggers, Triggers::MAIN);
+    assert_eq!(gateway.pipelines.len(), 1);
+    assert_eq!(gateway.pipelines[0].name, "Build");
+    // Timed from its first job starting to its last completing, leaving
+    // out the time it spent queued.
+    assert_eq!(
+        gateway.pipelines[0].run_time,
+        Some(SignedDuration::from_secs(70))
+    );
+
+    let empty = &repos[1];
+    assert_eq!(empty.description, "");
+    assert!(empty.pipelines.is_empty());
+    assert_eq!(empty.last_status, PipelineStatus::Pending);
+
+    let hubdash = &repos[2];
+    assert_eq!(hubdash.last_status, PipelineStatus::Pending);
+    assert_eq!(hubdash.triggers, Triggers::MAIN | Triggers::MANUAL);
+    let statuses: Vec<_> = hubdash.pipelines.iter().map(|p| p.status).collect();
+    assert_eq!(
+        statuses,
+        [PipelineStatus::Success, PipelineStatus::InProgress]
+    );
+    // Without check runs, timed from the run being created to last updated.
+    assert_eq!(
+        hubdash.pipelines[0].run_time,
+        Some(SignedDuration::from_secs(154))
+    );
+    assert_eq!(hubdash.pipelines[1].run_time, None);
+}
+
+#[tokio::test]
+async fn serves_single_repositories_for_the_organization() {
+    let (client, log) = graphql_stand_in("/graphql").await;
+    let source = OrgRepos::new(client, "example");
+
+    let repo = source
+        .find_repo("example", "hubdash")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(repo.success_rate, 100);
+    // Read from the workflow file and manifest the query returned.
+    assert_eq!(repo.triggers, Triggers::MAIN | Triggers::PR);
+    let names: Vec<_> = repo.dependencies.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["serde"]);
+    assert!(
+        source
+            .find_repo("other", "hubdash")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Nothing was read through the contents API.
+    let requests = log.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["name"], "hubdash");
+}
+
+#[tokio::test]
+async fn reports_graphql_errors() {
+    let (client, _) = graphql_stand_in("/graphql").await;
+
+    let err = client.fetch_org_repos("missing").await.unwrap_err();
+
+    assert!(matches!(err, Error::GraphQl(message) if message.contains("missing")));
+}
+
+#[tokio::test]
+async fn uses_the_enterprise_graphql_path() {
+    let (client, log) = graphql_stand_in("/api/graphql").await;
+
+    client.fetch_org_repos("example").await.unwrap();
+
+    assert_eq!(log.lock().unwrap().len(), 2);
+}
@@ -0,0 +1,310 @@
+//! Tests for the GitHub REST client against recorded fixtures.
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    http::Uri,
+    response::Response,
+    routing::{get, post},
+};
+use hubdash::github::{AppCredentials, GitHubClient, GitHubRepos, RepoRef, RunQuery};
+use hubdash::{PipelineStatus, RepoSource, Triggers};
+use jiff::{SignedDuration, Timestamp};
+use url::form_urlencoded;
+
+use common::{fixture, json, json_fixture, stand_in};
+
+async fn github_stand_in() -> GitHubClient {
+    let router = Router::new()
+        .route(
+            "/repos/example/hubdash",
+            get(|| async { json_fixture("github/repo.json") }),
+        )
+        .route(
+            "/repos/example/hubdash/actions/workflows",
+            get(|| async { json_fixture("github/workflows.json") }),
+        )
+        .route(
+            "/repos/example/hubdash/actions/runs",
+            get(|| async { json_fixture("github/runs.json") }),
+        )
+        .route(
+            "/repos/example/hubdash/actions/runs/30433601",
+            get(|| async { json_fixture("github/run.json") }),
+        )
+        .route(
+            "/repos/example/hubdash/actions/runs/30433601/jobs",
+            get(|| async { json_fixture("github/jobs.json") }),
+        );
+    GitHubClient::new(stand_in(router).await).with_token("test-token")
+}
+
+#[tokio::test]
+async fn lists_workflows() {
+    let client = github_stand_in().await;
+
+    let workflows = client.list_workflows("example", "hubdash").await.unwrap();
+
+    let names: Vec<_> = workflows.iter().map(|w| w.name.as_str()).collect();
+    assert_eq!(names, ["CI", "Deploy"]);
+    assert_eq!(workflows[0].path, ".github/workflows/ci.yml");
+}
+
+#[tokio::test]
+async fn maps_workflow_runs_to_pipelines() {
+    let client = github_stand_in().await;
+
+    let runs = client
+        .list_workflow_runs("example", "hubdash", &RunQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(runs.len(), 4);
+    assert_eq!(runs[0].pipeline_status(), PipelineStatus::InProgress);
+    assert_eq!(runs[0].run_time(), None);
+
+    let run = client
+        .get_workflow_run("example", "hubdash", 30433601)
+        .await
+        .unwrap();
+    let pipeline = run.to_pipeline_summary();
+    assert_eq!(pipeline.name, "CI");
+    assert_eq!(pipeline.status, PipelineStatus::Success);
+    assert_eq!(pipeline.run_time, Some(SignedDuration::from_secs(154)));
+    assert_eq!(
+        pipeline.github_url.as_str(),
+        "https://github.com/example/hubdash/actions/runs/30433601"
+    );
+}
+
+#[tokio::test]
+async fn lists_jobs_with_steps() {
+    let client = github_stand_in().await;
+
+    let jobs = client
+        .list_jobs("example", "hubdash", 30433601)
+        .await
+        .unwrap();
+
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].name, "build");
+    assert_eq!(jobs[0].labels, ["ubuntu-latest"]);
+    assert_eq!(jobs[0].steps.len(), 2);
+    assert_eq!(jobs[0].steps[1].name, "Run cargo test");
+}
+
+#[tokio::test]
+async fn lists_every_page_of_jobs() {
+    async fn jobs(uri: Uri) -> Response {
+        let query = uri.query().unwrap_or_default().as_bytes();
+        let page: HashMap<_, _> = form_urlencoded::parse(query).into_owned().collect();
+        assert_eq!(page["per_page"], "100");
+        let first = (page["page"].parse::<u64>().unwrap() - 1) * 100;
+        let jobs: Vec<_> = (first..(first + 100).min(150))
+            .map(|id| format!(r#"{{"id":{id},"run_id":1,"name":"job {id}","status":"queued"}}"#))
+            .collect();
+        json(format!(
+            r#"{{"total_count":150,"jobs":[{}]}}"#,
+            jobs.join(",")
+        ))
+    }
+
+    let router = Router::new().route("/repos/example/hubdash/actions/runs/1/jobs", get(jobs));
+    let client = GitHubClient::new(stand_in(router).await).with_token("test-token");
+
+    let jobs = client.list_jobs("example", "hubdash", 1).await.unwrap();
+
+    let ids: Vec<_> = jobs.iter().map(|job| job.id).collect();
+    assert_eq!(ids, (0..150).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn summarizes_repositories() {
+    let client = github_stand_in().await;
+    let source = GitHubRepos::new(client, vec!["example/hubdash".parse::<RepoRef>().unwrap()]);
+
+    let repo = source
+        .find_repo("example", "hubdash")
+        .await
+        .unwrap()
+        .expect("configured repository");
+
+    assert_eq!(
+        repo.description,
+        "A GitHub dashboard for monitoring CI/CD pipeline health"
+    );
+    assert_eq!(repo.last_status, PipelineStatus::InProgress);
+    assert_eq!(repo.success_rate, 67);
+    assert_eq!(
+        repo.triggers,
+        Triggers::MAIN | Triggers::PR | Triggers::SCHEDULED | Triggers::MANUAL
+    );
+    let pipelines: Vec<_> = repo.pipelines.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(pipelines, ["CI", "Deploy"]);
+
+    assert!(
+        source
+            .find_repo("example", "other")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn lists_the_repositories_that_could_be_summarized() {
+    let client = github_stand_in().await;
+    let repos = ["example/missing", "example/hubdash"];
+    let source = GitHubRepos::new(client, repos.map(|r| r.parse().unwrap()).into());
+
+    let listed = source.list_repos().await.unwrap();
+    let names: Vec<_> = listed.iter().map(|r| r.repo.as_str()).collect();
+    assert_eq!(names, ["hubdash"]);
+
+    let client = github_stand_in().await;
+    let source = GitHubRepos::new(client, vec!["example/missing".parse().unwrap()]);
+    assert!(source.list_repos().await.is_err());
+}
+
+#[tokio::test]
+async fn installations_only_serve_granted_repositories() {
+    let granted = format!(
+        r#"{{"total_count":1,"repositories":[{}]}}"#,
+        fixture("github/repo.json")
+    );
+    let public = fixture("github/repo.json").replace("example/hubdash", "octocat/public");
+    let router = Router::new()
+        .route(
+            "/installation/repositories",
+            get(move || async move { granted }),
+        )
+        .route("/repos/octocat/public", get(move || async move { public }))
+        .route(
+            "/repos/{owner}/{repo}/actions/runs",
+            get(|| async { json_fixture("github/runs.json") }),
+        )
+        .route(
+            "/repos/{owner}/{repo}/actions/runs/30433601/jobs",
+            get(|| async { json_fixture("github/jobs.json") }),
+        );
+    let client = GitHubClient::new(stand_in(router).await).with_token("test-token");
+    let source = GitHubRepos::installation(client);
+
+    assert!(
+        source
+            .find_repo("Example", "HubDash")
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        source
+            .find_repo("octocat", "public")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let jobs = source
+        .list_jobs("example", "hubdash", 30433601)
+        .await
+        .unwrap();
+    assert_eq!(jobs.len(), 1);
+    let jobs = source
+        .list_jobs("octocat", "public", 30433601)
+        .await
+        .unwrap();
+    assert!(jobs.is_empty());
+}
+
+#[tokio::test]
+async fn installations_share_their_token_and_granted_repositories() {
+    let minted = Arc::new(AtomicU32::new(0));
+    let listed = Arc::new(AtomicU32::new(0));
+    let granted = format!(
+        r#"{{"total_count":1,"repositories":[{}]}}"#,
+        fixture("github/repo.json")
+    );
+    let router = Router::new()
+        .route(
+            "/app/installations/42/access_tokens",
+            post({
+                let minted = minted.clone();
+                move || async move {
+                    minted.fetch_add(1, Ordering::SeqCst);
+                    // Give concurrent requests time to pile up.
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    let expires_at = Timestamp::now() + SignedDuration::from_hours(1);
+                    format!(r#"{{"token":"ghs_test","expires_at":"{expires_at}"}}"#)
+                }
+            }),
+        )
+        .route(
+            "/installation/repositories",
+            get({
+                let listed = listed.clone();
+                move || async move {
+                    listed.fetch_add(1, Ordering::SeqCst);
+                    granted
+                }
+            }),
+        )
+        .route(
+            "/repos/{owner}/{repo}/actions/runs",
+            get(|| async { json_fixture("github/runs.json") }),
+        )
+        .route(
+            "/repos/{owner}/{repo}/actions/runs/30433601/jobs",
+            get(|| async { json_fixture("github/jobs.json") }),
+        );
+    let key = fixture("github/app_key.pem");
+    let credentials = AppCredentials::from_pem(1, 42, key.as_bytes()).unwrap();
+    let client = GitHubClient::new(stand_in(router).await).with_app(credentials);
+    let source = GitHubRepos::installation(client);
+
+    let (first, second, jobs) = tokio::join!(
+        source.find_repo("example", "hubdash"),
+        source.find_repo("octocat", "public"),
+        source.list_jobs("example", "hubdash", 30433601),
+    );
+    assert!(first.unwrap().is_some());
+    assert!(second.unwrap().is_none());
+    assert_eq!(jobs.unwrap().len(), 1);
+    assert_eq!(minted.load(Ordering::SeqCst), 1);
+
+    let listed_before = listed.load(Ordering::SeqCst);
+    for _ in 0..3 {
+        assert!(
+            source
+                .find_repo("example", "hubdash")
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+    assert_eq!(listed.load(Ordering::SeqCst), listed_before);
+    assert!(listed_before <= 3);
+}
+
+#[tokio::test]
+async fn reports_missing_repositories() {
+    let client = github_stand_in().await;
+
+    let err = client
+        .get_repository("example", "missing")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        hubdash::github::Error::Status { status, .. } if status == 404
+    ));
+}
@@ -0,0 +1,221 @@
+//! Tests for throttling requests by the rate limits a stand-in GitHub
+//! reports.
+
+mod common;
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    Router,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use hubdash::github::{Error, GitHubClient, GitHubRepos, RepoRef};
+use hubdash::{AppState, create_router};
+use jiff::{SignedDuration, Timestamp};
+
+use common::{json_fixture, stand_in};
+
+/// What the stand-in reports about the budget with every response.
+#[derive(Clone, Copy)]
+struct Limit {
+    status: StatusCode,
+    remaining: u32,
+    reset: Timestamp,
+    retry_after: Option<u32>,
+}
+
+impl Limit {
+    fn ok(remaining: u32, reset_in: SignedDuration) -> Self {
+        Self {
+            status: StatusCode::OK,
+            remaining,
+            reset: Timestamp::now() + reset_in,
+            retry_after: None,
+        }
+    }
+
+    fn exhausted() -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            ..Self::ok(0, SignedDuration::from_hours(1))
+        }
+    }
+
+    fn secondary(retry_after: u32) -> Self {
+        Self {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after: Some(retry_after),
+            ..Self::ok(4000, SignedDuration::from_hours(1))
+        }
+    }
+}
+
+/// A stand-in GitHub serving one repository under a configurable limit.
+#[derive(Clone)]
+struct Upstream {
+    limit: Arc<Mutex<Limit>>,
+    requests: Arc<AtomicU32>,
+}
+
+impl Upstream {
+    fn new(limit: Limit) -> Self {
+        Self {
+            limit: Arc::new(Mutex::new(limit)),
+            requests: Arc::default(),
+        }
+    }
+
+    fn set(&self, limit: Limit) {
+        *self.limit.lock().unwrap() = limit;
+    }
+
+    fn requests(&self) -> u32 {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    async fn client(&self) -> GitHubClient {
+        async fn respond(State(upstream): State<Upstream>, uri: Uri) -> Response {
+            upstream.requests.fetch_add(1, Ordering::SeqCst);
+            let limit = *upstream.limit.lock().unwrap();
+            let mut headers = HeaderMap::new();
+            headers.insert("X-RateLimit-Limit", HeaderValue::from(5000));
+            headers.insert("X-RateLimit-Remaining", limit.remaining.into());
+            headers.insert("X-RateLimit-Reset", limit.reset.as_second().into());
+            headers.insert("X-RateLimit-Resource", HeaderValue::from_static("core"));
+            if let Some(retry_after) = limit.retry_after {
+                headers.insert("Retry-After", retry_after.into());
+            }
+            if limit.status != StatusCode::OK {
+                let body = r#"{"message":"API rate limit exceeded"}"#;
+                return (limit.status, headers, body).into_response();
+            }
+            let fixture = match uri.path() {
+                "/repos/example/hubdash" => "github/repo.json",
+                "/repos/example/hubdash/actions/workflows" => "github/workflows.json",
+                "/repos/example/hubdash/actions/runs" => "github/runs.json",
+                _ => return (StatusCode::NOT_FOUND, headers).into_response(),
+            };
+            (headers, json_fixture(fixture)).into_response()
+        }
+
+        let router = Router::new().fallback(respond).with_state(self.clone());
+        GitHubClient::new(stand_in(router).await).with_token("test-token")
+    }
+}
+
+async fn get_repository(client: &GitHubClient) -> Result<(), Error> {
+    client.get_repository("example", "hubdash").await.map(drop)
+}
+
+fn rate_limited_until(result: Result<(), Error>) -> Timestamp {
+    match result {
+        Err(Error::RateLimited { until }) => until,
+        other => panic!("expected to be rate limited, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn exhausted_budget_refuses_requests_until_reset() {
+    let upstream = Upstream::new(Limit::exhausted());
+    let client = upstream.client().await;
+    let reset = Timestamp::from_second(upstream.limit.lock().unwrap().reset.as_second()).unwrap();
+
+    let until = rate_limited_until(get_repository(&client).await);
+    assert_eq!(until, reset);
+    assert_eq!(client.rate_limits().limited_until(), Some(reset));
+
+    // Waiting an hour is out of the question, so the request is refused
+    // without being sent.
+    let until = rate_limited_until(get_repository(&client).await);
+    assert_eq!(until, reset);
+    assert_eq!(upstream.requests(), 1);
+}
+
+#[tokio::test]
+async fn secondary_limits_defer_requests_for_retry_after() {
+    let upstream = Upstream::new(Limit::secondary(1));
+    let client = upstream.client().await;
+
+    let until = rate_limited_until(get_repository(&client).await);
+    assert!(until > Timestamp::now(), "{until}");
+
+    upstream.set(Limit::ok(4000, SignedDuration::from_hours(1)));
+    let started = Instant::now();
+    get_repository(&client).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(500));
+    assert_eq!(upstream.requests(), 2);
+}
+
+#[tokio::test]
+async fn long_secondary_limits_refuse_requests() {
+    let upstream = Upstream::new(Limit::secondary(120));
+    let client = upstream.client().await;
+
+    rate_limited_until(get_repository(&client).await);
+    let until = rate_limited_until(get_repository(&client).await);
+    assert!(until > Timestamp::now() + SignedDuration::from_secs(100));
+    assert_eq!(client.rate_limits().limited_until(), Some(until));
+    assert_eq!(upstream.requests(), 1);
+}
+
+#[tokio::test]
+async fn low_budgets_space_requests_out_until_reset() {
+    // Fifty requests left over ten seconds leaves 200ms between requests.
+    let upstream = Upstream::new(Limit::ok(50, SignedDuration::from_secs(10)));
+    let client = upstream.client().await;
+
+    get_repository(&client).await.unwrap();
+    get_repository(&client).await.unwrap();
+    let started = Instant::now();
+    get_repository(&client).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert_eq!(upstream.requests(), 3);
+}
+
+#[tokio::test]
+async fn long_queues_fail_instead_of_waiting() {
+    // Two requests left over an hour leaves half an hour between them.
+    let upstream = Upstream::new(Limit::ok(2, SignedDuration::from_hours(1)));
+    let client = upstream.client().await;
+
+    get_repository(&client).await.unwrap();
+    get_repository(&client).await.unwrap();
+    let until = rate_limited_until(get_repository(&client).await);
+    assert!(until > Timestamp::now() + SignedDuration::from_mins(20));
+    assert_eq!(upstream.requests(), 2);
+}
+
+#[tokio::test]
+async fn dashboard_serves_cached_repositories_while_rate_limited() {
+    let upstream = Upstream::new(Limit::ok(4000, SignedDuration::from_hours(1)));
+    let client = upstream.client().await;
+    let limits = client.rate_limits();
+    let source = GitHubRepos::new(client, vec!["example/hubdash".parse::<RepoRef>().unwrap()]);
+    // Expire cached data right away, so every page load fetches again.
+    let state = AppState::new(source)
+        .with_cache_ttl(SignedDuration::ZERO, SignedDuration::ZERO)
+        .with_rate_limits(limits);
+    let base = stand_in(create_router(state)).await;
+    let dashboard = base.join("dashboard").unwrap();
+
+    let page = reqwest::get(dashboard.clone()).await.unwrap();
+    assert_eq!(page.status(), StatusCode::OK);
+    let page = page.text().await.unwrap();
+    assert!(page.contains("example/hubdash"));
+    assert!(!page.contains("Rate limited until"));
+
+    upstream.set(Limit::exhausted());
+    let page = reqwest::get(dashboard).await.unwrap();
+    assert_eq!(page.status(), StatusCode::OK);
+    let page = page.text().await.unwrap();
+    assert!(page.contains("example/hubdash"), "{page}");
+    assert!(page.contains("Rate limited until"), "{page}");
+}
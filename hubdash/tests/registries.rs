@@ -0,0 +1,207 @@
+//! Tests for latest-version lookups against stand-in package registries.
+
+mod common;
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use hubdash::registry::{CratesIndex, NpmRegistry, PyPI, Registries, Registry};
+use hubdash::{Dependency, DependencyKind, Ecosystem};
+use url::Url;
+
+use common::{fixture, json_fixture, stand_in};
+
+/// Serves one fixture per registry under a `mirror/` prefix, as an
+/// air-gapped mirror might, counting the requests it answers.
+async fn mirror(requests: Arc<AtomicU32>) -> Url {
+    async fn crate_file(
+        State(requests): State<Arc<AtomicU32>>,
+        Path((a, b, name)): Path<(String, String, String)>,
+    ) -> Response {
+        requests.fetch_add(1, Ordering::SeqCst);
+        match (a.as_str(), b.as_str(), name.as_str()) {
+            ("se", "rd", "serde") => fixture("registry/serde").into_response(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    async fn packument(
+        State(requests): State<Arc<AtomicU32>>,
+        Path(name): Path<String>,
+        headers: HeaderMap,
+    ) -> Response {
+        requests.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(
+            headers[header::ACCEPT],
+            "application/vnd.npm.install-v1+json"
+        );
+        match name.as_str() {
+            "@types/node" => json_fixture("registry/node.json"),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    async fn project(State(requests): State<Arc<AtomicU32>>, Path(name): Path<String>) -> Response {
+        requests.fetch_add(1, Ordering::SeqCst);
+        match name.as_str() {
+            "requests" => json_fixture("registry/requests.json"),
+            "broken" => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            "garbled" => "{".into_response(),
+            slow if slow.starts_with("slow-") => {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                StatusCode::NOT_FOUND.into_response()
+            }
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    let router = Router::new()
+        .route("/mirror/crates/{a}/{b}/{name}", get(crate_file))
+        .route("/mirror/npm/{name}", get(packument))
+        .route("/mirror/pypi/pypi/{name}/json", get(project))
+        .with_state(requests);
+    stand_in(router).await.join("mirror/").unwrap()
+}
+
+fn dependency(name: &str, ecosystem: Ecosystem, kind: DependencyKind) -> Dependency {
+    Dependency {
+        name: name.to_owned(),
+        ecosystem,
+        kind,
+        current_version: "1.0.0".to_owned(),
+        latest_version: None,
+        vulnerabilities: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn crates_index_skips_yanked_versions_and_prereleases() {
+    let base = mirror(Arc::default()).await;
+    let index = CratesIndex::new(base.join("crates/").unwrap());
+
+    let latest = index.latest_version("Serde").await.unwrap();
+    assert_eq!(latest.as_deref(), Some("1.0.219"));
+    assert_eq!(index.latest_version("no-such-crate").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn npm_registry_reads_the_latest_dist_tag_of_scoped_packages() {
+    let base = mirror(Arc::default()).await;
+    let registry = NpmRegistry::new(base.join("npm/").unwrap());
+
+    let latest = registry.latest_version("@types/node").await.unwrap();
+    assert_eq!(latest.as_deref(), Some("22.13.9"));
+    assert_eq!(registry.latest_version("left-pad").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn pypi_reads_the_project_version() {
+    let base = mirror(Arc::default()).await;
+    let pypi = PyPI::new(base.join("pypi/").unwrap());
+
+    let latest = pypi.latest_version("requests").await.unwrap();
+    assert_eq!(latest.as_deref(), Some("2.32.3"));
+    assert!(pypi.latest_version("broken").await.is_err());
+}
+
+#[tokio::test]
+async fn registries_cache_lookups_of_direct_dependencies() {
+    let requests = Arc::new(AtomicU32::new(0));
+    let base = mirror(requests.clone()).await;
+    let registries = Registries::new()
+        .with_registry(CratesIndex::new(base.join("crates/").unwrap()))
+        .with_registry(NpmRegistry::new(base.join("npm/").unwrap()))
+        .with_registry(PyPI::new(base.join("pypi/").unwrap()));
+
+    let mut dependencies = vec![
+        dependency("serde", Ecosystem::Cargo, DependencyKind::Direct),
+        dependency("@types/node", Ecosystem::Npm, DependencyKind::Dev),
+        dependency("requests", Ecosystem::PyPI, DependencyKind::Direct),
+        dependency("idna", Ecosystem::PyPI, DependencyKind::Transitive),
+        dependency("golang.org/x/mod", Ecosystem::Go, DependencyKind::Direct),
+    ];
+    registries.fill(&mut dependencies).await;
+    let latest: Vec<_> = dependencies
+        .iter()
+        .map(|d| d.latest_version.as_deref())
+        .collect();
+    assert_eq!(
+        latest,
+        [Some("1.0.219"), Some("22.13.9"), Some("2.32.3"), None, None]
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    let mut again = vec![dependency(
+        "serde",
+        Ecosystem::Cargo,
+        DependencyKind::Direct,
+    )];
+    registries.fill(&mut again).await;
+    assert_eq!(again[0].latest_version.as_deref(), Some("1.0.219"));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+    assert!(again[0].is_outdated());
+
+    // A requirement is not a version, so it is neither current nor behind.
+    let mut requirement = vec![dependency(
+        "serde",
+        Ecosystem::Cargo,
+        DependencyKind::Direct,
+    )];
+    requirement[0].current_version = ">=1.0".to_owned();
+    registries.fill(&mut requirement).await;
+    assert!(!requirement[0].is_outdated());
+    assert!(!requirement[0].is_up_to_date());
+}
+
+#[tokio::test]
+async fn registries_briefly_cache_failed_lookups() {
+    let requests = Arc::new(AtomicU32::new(0));
+    let base = mirror(requests.clone()).await;
+    let registries = Registries::new().with_registry(PyPI::new(base.join("pypi/").unwrap()));
+
+    let mut dependencies = vec![
+        dependency("broken", Ecosystem::PyPI, DependencyKind::Direct),
+        dependency("broken", Ecosystem::PyPI, DependencyKind::Dev),
+    ];
+    registries.fill(&mut dependencies).await;
+    assert!(dependencies.iter().all(|d| d.latest_version.is_none()));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    registries.fill(&mut dependencies).await;
+    assert!(dependencies.iter().all(|d| d.latest_version.is_none()));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn registries_keep_looking_up_after_one_package_fails() {
+    let requests = Arc::new(AtomicU32::new(0));
+    let base = mirror(requests.clone()).await;
+    let registries = Registries::new().with_registry(PyPI::new(base.join("pypi/").unwrap()));
+
+    // The failing lookups answer first, so the later ones start after the
+    // registry has already failed twice.
+    let mut names = vec!["broken".to_owned(), "garbled".to_owned()];
+    names.extend((0..8).map(|i| format!("slow-{i}")));
+    names.push("requests".to_owned());
+    let mut dependencies: Vec<_> = names
+        .iter()
+        .map(|name| dependency(name, Ecosystem::PyPI, DependencyKind::Direct))
+        .collect();
+    registries.fill(&mut dependencies).await;
+
+    assert_eq!(requests.load(Ordering::SeqCst), 11);
+    let found = dependencies.last().unwrap();
+    assert_eq!(found.latest_version.as_deref(), Some("2.32.3"));
+}
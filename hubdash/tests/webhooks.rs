@@ -0,0 +1,295 @@
+//! Tests for the GitHub webhook receiver against recorded deliveries.
+
+mod common;
+
+use hmac::{Hmac, KeyInit, Mac};
+use hubdash::{AppState, MockRepos, PipelineStatus, RepoSummary, create_router};
+use reqwest::StatusCode;
+use sha2::Sha256;
+use url::Url;
+
+use common::{fixture, stand_in};
+
+const SECRET: &str = "It's a Secret to Everybody";
+
+struct Receiver {
+    state: AppState,
+    url: Url,
+    http: reqwest::Client,
+}
+
+impl Receiver {
+    async fn start() -> Self {
+        let state = AppState::new(MockRepos).with_webhook_secret(SECRET);
+        // Load the cache so deliveries have something to update.
+        state.repos().list_repos().await.unwrap();
+        let base = stand_in(create_router(state.clone())).await;
+        Self {
+            state,
+            url: base.join("webhooks/github").unwrap(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn send(&self, event: &str, delivery: &str, body: String, signature: &str) -> StatusCode {
+        self.http
+            .post(self.url.clone())
+            .header("X-GitHub-Event", event)
+            .header("X-GitHub-Delivery", delivery)
+            .header("X-Hub-Signature-256", signature)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .unwrap()
+            .status()
+    }
+
+    async fn deliver(&self, event: &str, delivery: &str, body: String) -> StatusCode {
+        let signature = sign(&body);
+        self.send(event, delivery, body, &signature).await
+    }
+
+    async fn summary(&self) -> RepoSummary {
+        self.state
+            .repos()
+            .find_repo("example", "hubdash")
+            .await
+            .unwrap()
+            .unwrap()
+    }
+}
+
+fn sign(body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn pipeline_status(summary: &RepoSummary, name: &str) -> PipelineStatus {
+    summary
+        .pipelines
+        .iter()
+        .find(|p| p.name == name)
+        .unwrap_or_else(|| panic!("no pipeline named {name}"))
+        .status
+}
+
+#[tokio::test]
+async fn workflow_run_replaces_pipeline() {
+    let receiver = Receiver::start().await;
+
+    let status = receiver
+        .deliver(
+            "workflow_run",
+            "run-1",
+            fixture("webhooks/workflow_run.json"),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let summary = receiver.summary().await;
+    assert_eq!(summary.last_status, PipelineStatus::Failure);
+    assert_eq!(pipeline_status(&summary, "CI"), PipelineStatus::Failure);
+    let ci = summary.pipelines.iter().find(|p| p.name == "CI").unwrap();
+    assert_eq!(
+        ci.github_url.as_str(),
+        "https://github.com/example/hubdash/actions/runs/30433700"
+    );
+    assert_eq!(pipeline_status(&summary, "Deploy"), PipelineStatus::Success);
+}
+
+#[tokio::test]
+async fn workflow_job_marks_pipeline_in_progress() {
+    let receiver = Receiver::start().await;
+
+    let status = receiver
+        .deliver(
+            "workflow_job",
+            "job-1",
+            fixture("webhooks/workflow_job.json"),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let summary = receiver.summary().await;
+    assert_eq!(
+        pipeline_status(&summary, "Deploy"),
+        PipelineStatus::InProgress
+    );
+    assert_eq!(summary.last_status, PipelineStatus::InProgress);
+    let deploy = summary
+        .pipelines
+        .iter()
+        .find(|p| p.name == "Deploy")
+        .unwrap();
+    assert_eq!(deploy.run_time, None);
+}
+
+#[tokio::test]
+async fn check_suite_updates_last_status() {
+    let receiver = Receiver::start().await;
+
+    let status = receiver
+        .deliver(
+            "check_suite",
+            "suite-1",
+            fixture("webhooks/check_suite.json"),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let summary = receiver.summary().await;
+    assert_eq!(summary.last_status, PipelineStatus::Failure);
+    assert_eq!(pipeline_status(&summary, "CI"), PipelineStatus::Success);
+}
+
+#[tokio::test]
+async fn check_suite_from_other_apps_is_ignored() {
+    let receiver = Receiver::start().await;
+    let body = fixture("webhooks/check_suite.json").replace("github-actions", "other-ci");
+
+    let status = receiver.deliver("check_suite", "suite-1", body).await;
+
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let summary = receiver.summary().await;
+    assert_eq!(summary.last_status, PipelineStatus::Success);
+}
+
+#[tokio::test]
+async fn push_to_default_branch_refreshes_repository() {
+    let receiver = Receiver::start().await;
+    receiver
+        .deliver(
+            "workflow_run",
+            "run-1",
+            fixture("webhooks/workflow_run.json"),
+        )
+        .await;
+
+    let status = receiver
+        .deliver("push", "push-1", fixture("webhooks/push.json"))
+        .await;
+
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    // The refresh finishes after the delivery is acknowledged.
+    let mut summary = receiver.summary().await;
+    for _ in 0..100 {
+        if pipeline_status(&summary, "CI") == PipelineStatus::Success {
+            break;
+        }
+        tokio::task::yield_now().await;
+        summary = receiver.summary().await;
+    }
+    assert_eq!(pipeline_status(&summary, "CI"), PipelineStatus::Success);
+}
+
+#[tokio::test]
+async fn push_to_other_branch_is_ignored() {
+    let receiver = Receiver::start().await;
+    receiver
+        .deliver(
+            "workflow_run",
+            "run-1",
+            fixture("webhooks/workflow_run.json"),
+        )
+        .await;
+
+    let body = fixture("webhooks/push.json").replace("refs/heads/main", "refs/heads/feature");
+    let status = receiver.deliver("push", "push-1", body).await;
+
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let summary = receiver.summary().await;
+    assert_eq!(pipeline_status(&summary, "CI"), PipelineStatus::Failure);
+}
+
+#[tokio::test]
+async fn rejects_invalid_signatures() {
+    let receiver = Receiver::start().await;
+    let body = fixture("webhooks/workflow_run.json");
+    let forged = sign(&body.replace("failure", "success"));
+
+    let status = receiver.send("workflow_run", "run-1", body, &forged).await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let summary = receiver.summary().await;
+    assert_eq!(pipeline_status(&summary, "CI"), PipelineStatus::Success);
+}
+
+#[tokio::test]
+async fn rejects_replayed_deliveries() {
+    let receiver = Receiver::start().await;
+    let job = fixture("webhooks/workflow_job.json");
+    receiver.deliver("workflow_job", "job-1", job.clone()).await;
+    let finished = fixture("webhooks/workflow_run.json")
+        .replace("\"CI\"", "\"Deploy\"")
+        .replace("failure", "success");
+    receiver.deliver("workflow_run", "run-1", finished).await;
+
+    let status = receiver.deliver("workflow_job", "job-1", job).await;
+
+    assert_eq!(status, StatusCode::CONFLICT);
+    let summary = receiver.summary().await;
+    assert_eq!(pipeline_status(&summary, "Deploy"), PipelineStatus::Success);
+}
+
+#[tokio::test]
+async fn applies_concurrent_copies_of_a_delivery_once() {
+    let receiver = Receiver::start().await;
+    let run = fixture("webhooks/workflow_run.json");
+
+    let (first, second) = tokio::join!(
+        receiver.deliver("workflow_run", "run-1", run.clone()),
+        receiver.deliver("workflow_run", "run-1", run),
+    );
+
+    let mut statuses = [first, second];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::NO_CONTENT, StatusCode::CONFLICT]);
+}
+
+#[tokio::test]
+async fn accepts_redeliveries_of_rejected_deliveries() {
+    let receiver = Receiver::start().await;
+    let truncated = r#"{"workflow_run":"#.to_owned();
+    let status = receiver.deliver("workflow_run", "run-1", truncated).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let status = receiver
+        .deliver(
+            "workflow_run",
+            "run-1",
+            fixture("webhooks/workflow_run.json"),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let summary = receiver.summary().await;
+    assert_eq!(pipeline_status(&summary, "CI"), PipelineStatus::Failure);
+}
+
+#[tokio::test]
+async fn ignores_unhandled_events() {
+    let receiver = Receiver::start().await;
+    let body = r#"{"zen":"Keep it logically awesome.","hook_id":1}"#.to_owned();
+
+    let status = receiver.deliver("ping", "ping-1", body).await;
+
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn disabled_without_secret() {
+    let base = stand_in(create_router(AppState::new(MockRepos))).await;
+
+    let status = reqwest::Client::new()
+        .post(base.join("webhooks/github").unwrap())
+        .header("X-GitHub-Event", "ping")
+        .body("{}")
+        .send()
+        .await
+        .unwrap()
+        .status();
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}